@@ -119,6 +119,109 @@ defmodule Vettore.Nifs do
   def multi_vector_top_k(_documents, _query_vectors, _metric_code, _limit),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  @spec multi_vector_score_weighted([[float()]], [[float()]], [float()], non_neg_integer()) ::
+          {:ok, float()} | {:error, String.t()}
+  def multi_vector_score_weighted(_query_vectors, _document_vectors, _weights, _metric_code),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_top_k_weighted(
+          [{String.t(), [[float()]]}],
+          [[float()]],
+          [float()],
+          non_neg_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def multi_vector_top_k_weighted(_documents, _query_vectors, _weights, _metric_code, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_explain([[float()]], [[float()]], [float()] | nil, non_neg_integer()) ::
+          {:ok, {float(), [{non_neg_integer() | nil, float()}]}} | {:error, String.t()}
+  def multi_vector_explain(_query_vectors, _document_vectors, _weights, _metric_code),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_reduce(
+          [[float()]],
+          non_neg_integer(),
+          float() | nil,
+          pos_integer() | nil,
+          pos_integer()
+        ) :: {:ok, [[float()]]} | {:error, String.t()}
+  def multi_vector_reduce(_vectors, _metric_code, _dedup_threshold, _max_tokens, _pool_factor),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec mmr_rerank(
+          [{String.t(), float()}],
+          [{String.t(), [float()]}],
+          non_neg_integer(),
+          float(),
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def mmr_rerank(_candidates, _vectors, _metric_code, _lambda, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec aggregate_vectors(
+          [[float()]],
+          [float()] | nil,
+          non_neg_integer(),
+          non_neg_integer(),
+          non_neg_integer(),
+          float()
+        ) :: {:ok, {[float()], non_neg_integer() | nil}} | {:error, String.t()}
+  def aggregate_vectors(
+        _vectors,
+        _weights,
+        _method_code,
+        _metric_code,
+        _max_iterations,
+        _tolerance
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec kmeans_fit(
+          [[float()]],
+          non_neg_integer(),
+          pos_integer(),
+          pos_integer(),
+          float(),
+          non_neg_integer(),
+          pos_integer() | nil
+        ) ::
+          {:ok, {[[float()]], [non_neg_integer()], float(), pos_integer()}}
+          | {:error, String.t()}
+  def kmeans_fit(_vectors, _metric_code, _k, _max_iterations, _tolerance, _seed, _batch_size),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec fusion_rrf([[{String.t(), float()}]], [float()] | nil, float(), non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def fusion_rrf(_lists, _weights, _k, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec fusion_scores(
+          [[{String.t(), float()}]],
+          [float()] | nil,
+          [non_neg_integer() | nil] | nil,
+          non_neg_integer(),
+          non_neg_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def fusion_scores(
+        _lists,
+        _weights,
+        _metric_codes,
+        _normalization_code,
+        _combination_code,
+        _limit
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   @spec flat_new_l2() :: reference()
   def flat_new_l2, do: :erlang.nif_error(:nif_not_loaded)
@@ -164,6 +267,24 @@ defmodule Vettore.Nifs do
           :ok | {:ok, {}} | {:error, String.t()}
   def flat_insert_many(_index, _vectors), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  @spec flat_insert_fde(reference(), String.t(), [float()], non_neg_integer()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def flat_insert_fde(_index, _id, _vector, _fingerprint),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_insert_many_fde(reference(), [{String.t(), [float()]}], non_neg_integer()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def flat_insert_many_fde(_index, _vectors, _fingerprint),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_insert_many_observed(reference(), [{String.t(), [float()]}], reference()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def flat_insert_many_observed(_index, _vectors, _stats),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   @spec flat_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
   def flat_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
@@ -173,6 +294,85 @@ defmodule Vettore.Nifs do
           {:ok, [{String.t(), float()}]} | {:error, String.t()}
   def flat_search(_index, _query, _limit), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  @spec flat_funnel_search(reference(), [float()], [pos_integer()], pos_integer(), pos_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def flat_funnel_search(_index, _query, _stages, _candidates, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_knn_graph(reference(), non_neg_integer()) ::
+          {:ok, [{String.t(), [{String.t(), float()}]}]} | {:error, String.t()}
+  def flat_knn_graph(_index, _k), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_kmeans(
+          reference(),
+          pos_integer(),
+          pos_integer(),
+          float(),
+          non_neg_integer(),
+          pos_integer() | nil
+        ) ::
+          {:ok, {[[float()]], [{String.t(), non_neg_integer()}], float(), pos_integer()}}
+          | {:error, String.t()}
+  def flat_kmeans(_index, _k, _max_iterations, _tolerance, _seed, _batch_size),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_near_duplicates(reference(), float(), non_neg_integer()) ::
+          {:ok, [{String.t(), [String.t()]}]} | {:error, String.t()}
+  def flat_near_duplicates(_index, _threshold, _representative_code),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_mmr_rerank(reference(), [{String.t(), float()}], float(), non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def flat_mmr_rerank(_index, _candidates, _lambda, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_anomaly_scores(
+          reference(),
+          [[float()]],
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{float(), float(), float()}]} | {:error, String.t()}
+  def flat_anomaly_scores(_index, _queries, _k, _trees, _sample_size, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_anomaly_report(
+          reference(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), float(), float(), float()}]} | {:error, String.t()}
+  def flat_anomaly_report(_index, _k, _trees, _sample_size, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_aggregate(
+          reference(),
+          [String.t()],
+          [float()] | nil,
+          non_neg_integer(),
+          non_neg_integer(),
+          float()
+        ) :: {:ok, {[float()], String.t() | nil}} | {:error, String.t()}
+  def flat_aggregate(
+        _index,
+        _ids,
+        _weights,
+        _method_code,
+        _max_iterations,
+        _tolerance
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   @spec hnsw_new_l2(pos_integer(), pos_integer(), pos_integer(), pos_integer(), pos_integer()) ::
           {:ok, reference()} | {:error, String.t()}
@@ -206,6 +406,24 @@ defmodule Vettore.Nifs do
           :ok | {:ok, {}} | {:error, String.t()}
   def hnsw_insert_many(_index, _vectors), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  @spec hnsw_insert_fde(reference(), String.t(), [float()], non_neg_integer()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def hnsw_insert_fde(_index, _id, _vector, _fingerprint),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_insert_many_fde(reference(), [{String.t(), [float()]}], non_neg_integer()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def hnsw_insert_many_fde(_index, _vectors, _fingerprint),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_insert_many_observed(reference(), [{String.t(), [float()]}], reference()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def hnsw_insert_many_observed(_index, _vectors, _stats),
+    do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   @spec hnsw_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
   def hnsw_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
@@ -215,6 +433,361 @@ defmodule Vettore.Nifs do
           {:ok, [{String.t(), float()}]} | {:error, String.t()}
   def hnsw_search(_index, _query, _limit), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  @spec hnsw_knn_graph(reference(), non_neg_integer()) ::
+          {:ok, [{String.t(), [{String.t(), float()}]}]} | {:error, String.t()}
+  def hnsw_knn_graph(_index, _k), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_near_duplicates(reference(), float(), non_neg_integer()) ::
+          {:ok, [{String.t(), [String.t()]}]} | {:error, String.t()}
+  def hnsw_near_duplicates(_index, _threshold, _representative_code),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_mmr_rerank(reference(), [{String.t(), float()}], float(), non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def hnsw_mmr_rerank(_index, _candidates, _lambda, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_anomaly_scores(
+          reference(),
+          [[float()]],
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{float(), float(), float()}]} | {:error, String.t()}
+  def hnsw_anomaly_scores(_index, _queries, _k, _trees, _sample_size, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_anomaly_report(
+          reference(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), float(), float(), float()}]} | {:error, String.t()}
+  def hnsw_anomaly_report(_index, _k, _trees, _sample_size, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_aggregate(
+          reference(),
+          [String.t()],
+          [float()] | nil,
+          non_neg_integer(),
+          non_neg_integer(),
+          float()
+        ) :: {:ok, {[float()], String.t() | nil}} | {:error, String.t()}
+  def hnsw_aggregate(
+        _index,
+        _ids,
+        _weights,
+        _method_code,
+        _max_iterations,
+        _tolerance
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_new(non_neg_integer(), boolean()) :: {:ok, reference()} | {:error, String.t()}
+  def binary_new(_metric_code, _keep_vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_insert(reference(), String.t(), [float()]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def binary_insert(_index, _id, _vector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_insert_many(reference(), [{String.t(), [float()]}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def binary_insert_many(_index, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_insert_codes(reference(), [{String.t(), [non_neg_integer()]}], pos_integer()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def binary_insert_codes(_index, _codes, _dimensions), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def binary_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_hamming_search(reference(), [non_neg_integer()], non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def binary_hamming_search(_index, _query, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec binary_search(reference(), [float()], pos_integer(), pos_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def binary_search(_index, _query, _candidates, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec quantizer_train(
+          [[float()]],
+          pos_integer(),
+          non_neg_integer(),
+          non_neg_integer(),
+          non_neg_integer()
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def quantizer_train(_vectors, _bits, _rotation_code, _iterations, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec quantizer_info(reference()) :: {pos_integer(), float()}
+  def quantizer_info(_quantizer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec quantizer_encode(reference(), [[float()]]) ::
+          {:ok, [[non_neg_integer()]]} | {:error, String.t()}
+  def quantizer_encode(_quantizer, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec quantizer_error_bounds(reference(), [[float()]]) ::
+          {:ok, [float()]} | {:error, String.t()}
+  def quantizer_error_bounds(_quantizer, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec pca_fit([[float()]], pos_integer(), boolean(), non_neg_integer(), non_neg_integer()) ::
+          {:ok, reference()} | {:error, String.t()}
+  def pca_fit(_vectors, _components, _whiten, _power_iterations, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec pca_transform(reference(), [float()]) :: {:ok, [float()]} | {:error, String.t()}
+  def pca_transform(_pca, _vector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec pca_transform_batch(reference(), [[float()]]) ::
+          {:ok, [[float()]]} | {:error, String.t()}
+  def pca_transform_batch(_pca, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec pca_export(reference()) :: {[float()], [[float()]], [float()], boolean()}
+  def pca_export(_pca), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec pca_import([float()], [[float()]], [float()], boolean()) ::
+          {:ok, reference()} | {:error, String.t()}
+  def pca_import(_mean, _components, _explained_variance, _whiten),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec random_transform_new(
+          non_neg_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer()
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def random_transform_new(_kind_code, _input, _output, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec random_transform_info(reference()) :: {pos_integer(), pos_integer()}
+  def random_transform_info(_transform), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec random_transform_apply(reference(), [float()]) :: {:ok, [float()]} | {:error, String.t()}
+  def random_transform_apply(_transform, _vector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec random_transform_apply_batch(reference(), [[float()]]) ::
+          {:ok, [[float()]]} | {:error, String.t()}
+  def random_transform_apply_batch(_transform, _vectors),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_new(non_neg_integer()) :: {:ok, reference()} | {:error, String.t()}
+  def classifier_new(_metric_code), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_insert(reference(), String.t(), String.t(), [float()]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def classifier_insert(_classifier, _id, _label, _vector),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_insert_many(reference(), [{String.t(), String.t(), [float()]}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def classifier_insert_many(_classifier, _examples), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def classifier_delete(_classifier, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_set_centroid(reference(), String.t(), [float()] | nil) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def classifier_set_centroid(_classifier, _label, _vector),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec classifier_classify(
+          reference(),
+          [float()],
+          non_neg_integer(),
+          non_neg_integer(),
+          float()
+        ) ::
+          {:ok, [{String.t(), float(), float()}]} | {:error, String.t()}
+  def classifier_classify(_classifier, _query, _k, _vote_code, _temperature),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec stats_new(pos_integer(), pos_integer(), float(), non_neg_integer()) ::
+          {:ok, reference()} | {:error, String.t()}
+  def stats_new(_reservoir_size, _histogram_bins, _max_norm, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec stats_observe(reference(), [[float()]]) :: :ok | {:ok, {}} | {:error, String.t()}
+  def stats_observe(_stats, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec stats_summary(reference()) ::
+          {:ok, {non_neg_integer(), [float()], [float()], [non_neg_integer()]}}
+          | {:error, String.t()}
+  def stats_summary(_stats), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec stats_sample(reference()) :: {:ok, [[float()]]} | {:error, String.t()}
+  def stats_sample(_stats), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec stats_drift(reference(), reference()) ::
+          {:ok, {float(), float(), float(), float()}} | {:error, String.t()}
+  def stats_drift(_baseline, _current), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec sparse_new() :: reference()
+  def sparse_new, do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec sparse_insert(reference(), String.t(), [{non_neg_integer(), float()}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def sparse_insert(_index, _id, _vector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec sparse_insert_many(reference(), [{String.t(), [{non_neg_integer(), float()}]}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def sparse_insert_many(_index, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec sparse_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def sparse_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec sparse_search(reference(), [{non_neg_integer(), float()}], pos_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def sparse_search(_index, _query, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec text_new(float(), float(), [String.t()]) :: {:ok, reference()} | {:error, String.t()}
+  def text_new(_k1, _b, _stopwords), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec text_insert(reference(), String.t(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def text_insert(_index, _id, _text), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec text_insert_many(reference(), [{String.t(), String.t()}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def text_insert_many(_index, _documents), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec text_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def text_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec text_search(reference(), String.t(), pos_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def text_search(_index, _query, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_new(
+          non_neg_integer(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer()
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def multi_vector_index_new(_metric_code, _m, _m0, _ef_construction, _ef_search, _max_level),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_insert(reference(), String.t(), [[float()]]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def multi_vector_index_insert(_index, _id, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_insert_many(reference(), [{String.t(), [[float()]]}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def multi_vector_index_insert_many(_index, _documents), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_delete(reference(), String.t()) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def multi_vector_index_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_search(reference(), [[float()]], pos_integer(), pos_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def multi_vector_index_search(_index, _query_vectors, _token_candidates, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec multi_vector_index_set_reduction(
+          reference(),
+          {float() | nil, pos_integer() | nil, pos_integer()} | nil
+        ) :: :ok | {:ok, {}} | {:error, String.t()}
+  def multi_vector_index_set_reduction(_index, _reduction), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec plaid_build(
+          [{String.t(), [[float()]]}],
+          non_neg_integer(),
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer(),
+          non_neg_integer()
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def plaid_build(_documents, _metric_code, _centroids, _residual_bits, _max_iterations, _seed),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec plaid_insert(reference(), String.t(), [[float()]]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def plaid_insert(_index, _id, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec plaid_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def plaid_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec plaid_search(
+          reference(),
+          [[float()]],
+          pos_integer(),
+          float(),
+          pos_integer(),
+          float(),
+          pos_integer()
+        ) :: {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def plaid_search(
+        _index,
+        _query_vectors,
+        _ncells,
+        _centroid_threshold,
+        _ndocs,
+        _rescore_fraction,
+        _limit
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   @spec muvera_encode_query(
           [[float()]],
@@ -256,4 +829,162 @@ defmodule Vettore.Nifs do
         _final_projection_dimension
       ),
       do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encode_query_with_options(
+          [[float()]],
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()}
+        ) :: {:ok, [float()]} | {:error, String.t()}
+  def muvera_encode_query_with_options(_vectors, _config, _options),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encode_document_with_options(
+          [[float()]],
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()}
+        ) :: {:ok, [float()]} | {:error, String.t()}
+  def muvera_encode_document_with_options(_vectors, _config, _options),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_new(
+          pos_integer(),
+          pos_integer(),
+          non_neg_integer(),
+          non_neg_integer(),
+          pos_integer(),
+          pos_integer() | nil,
+          {boolean(), non_neg_integer(), non_neg_integer()}
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def muvera_encoder_new(
+        _dimension,
+        _num_repetitions,
+        _num_simhash_projections,
+        _seed,
+        _projection_dimension,
+        _final_projection_dimension,
+        _options
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_encode_query(reference(), [[float()]]) ::
+          {:ok, [float()]} | {:error, String.t()}
+  def muvera_encoder_encode_query(_encoder, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_encode_document(reference(), [[float()]]) ::
+          {:ok, [float()]} | {:error, String.t()}
+  def muvera_encoder_encode_document(_encoder, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_encode_queries(reference(), [[[float()]]]) ::
+          {:ok, [[float()]]} | {:error, String.t()}
+  def muvera_encoder_encode_queries(_encoder, _batch), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_encode_documents(reference(), [[[float()]]]) ::
+          {:ok, [[float()]]} | {:error, String.t()}
+  def muvera_encoder_encode_documents(_encoder, _batch), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encode_documents(
+          [{String.t(), [[float()]]}],
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()},
+          non_neg_integer()
+        ) :: {:ok, [{String.t(), [float()]}]} | {:error, String.t()}
+  def muvera_encode_documents(_documents, _config, _options, _threads),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encode_documents_into_flat(
+          [{String.t(), [[float()]]}],
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()},
+          non_neg_integer(),
+          reference()
+        ) :: :ok | {:ok, {}} | {:error, String.t()}
+  def muvera_encode_documents_into_flat(_documents, _config, _options, _threads, _index),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encode_documents_into_hnsw(
+          [{String.t(), [[float()]]}],
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()},
+          non_neg_integer(),
+          reference()
+        ) :: :ok | {:ok, {}} | {:error, String.t()}
+  def muvera_encode_documents_into_hnsw(_documents, _config, _options, _threads, _index),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_new(
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()},
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer(),
+          pos_integer()
+        ) :: {:ok, reference()} | {:error, String.t()}
+  def muvera_index_new(_config, _options, _m, _m0, _ef_construction, _ef_search, _max_level),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_insert(reference(), String.t(), [[float()]]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def muvera_index_insert(_index, _id, _vectors), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_insert_many(reference(), [{String.t(), [[float()]]}]) ::
+          :ok | {:ok, {}} | {:error, String.t()}
+  def muvera_index_insert_many(_index, _documents), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_delete(reference(), String.t()) :: :ok | {:ok, {}} | {:error, String.t()}
+  def muvera_index_delete(_index, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_search(reference(), [[float()]], pos_integer(), pos_integer()) ::
+          {:ok, [{String.t(), float(), float()}]} | {:error, String.t()}
+  def muvera_index_search(_index, _query_vectors, _candidates, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_fingerprint(
+          {pos_integer(), pos_integer(), non_neg_integer(), non_neg_integer(), pos_integer(),
+           pos_integer() | nil},
+          {boolean(), non_neg_integer(), non_neg_integer()}
+        ) :: {:ok, non_neg_integer()} | {:error, String.t()}
+  def muvera_fingerprint(_config, _options), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_encoder_fingerprint(reference()) :: non_neg_integer()
+  def muvera_encoder_fingerprint(_encoder), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec muvera_index_fingerprint(reference()) :: {:ok, non_neg_integer()} | {:error, String.t()}
+  def muvera_index_fingerprint(_index), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec flat_search_fde(reference(), [float()], non_neg_integer(), non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def flat_search_fde(_index, _query, _fingerprint, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  @spec hnsw_search_fde(reference(), [float()], non_neg_integer(), non_neg_integer()) ::
+          {:ok, [{String.t(), float()}]} | {:error, String.t()}
+  def hnsw_search_fde(_index, _query, _fingerprint, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
 end
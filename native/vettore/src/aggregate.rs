@@ -0,0 +1,427 @@
+//! Native vector aggregation over supplied vectors or stored index ids.
+//!
+//! Every method accepts optional non-negative weights and accumulates in f64,
+//! so large finite coordinates cannot overflow intermediate sums:
+//!
+//! - `Mean` / `WeightedMean`: the (weighted) arithmetic mean; the weighted
+//!   form requires weights.
+//! - `NormalizedMean`: the mean of L2-normalized inputs, normalized again, as
+//!   used for cosine profile vectors.
+//! - `GeometricMedian`: the Euclidean geometric median by Weiszfeld iteration
+//!   from the mean, robust to outlying inputs.
+//! - `Medoid`: the input minimizing its weighted rank distance to all inputs
+//!   under the metric; cosine uses true cosine so inputs need not be unit.
+//!
+//! Index aggregation reads stored vectors as held and skips ids that are no
+//! longer stored, mirroring how MMR reranking treats stale ids.
+
+use crate::distances::{f64_dot, f64_l2_squared, f64_to_f32, Metric};
+use crate::flat::FlatIndex;
+use crate::hnsw::HnswIndex;
+
+/// Weiszfeld steps closer than this to an input skip that input's pull.
+const COINCIDENCE: f64 = 1.0e-12;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Method {
+    Mean,
+    WeightedMean,
+    NormalizedMean,
+    GeometricMedian,
+    Medoid,
+}
+
+impl Method {
+    /// Decodes the compact aggregation selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Mean),
+            1 => Ok(Self::WeightedMean),
+            2 => Ok(Self::NormalizedMean),
+            3 => Ok(Self::GeometricMedian),
+            4 => Ok(Self::Medoid),
+            _ => Err("unknown aggregation".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub method: Method,
+    pub metric: Metric,
+    /// Weiszfeld iteration cap for `GeometricMedian`.
+    pub max_iterations: usize,
+    /// Weiszfeld stops once a step moves less than this, relative to the norm.
+    pub tolerance: f64,
+}
+
+/// Aggregates vectors, returning the result and, for `Medoid`, its position.
+pub fn aggregate(
+    vectors: &[&[f32]],
+    weights: Option<&[f32]>,
+    params: Params,
+) -> Result<(Vec<f32>, Option<usize>), String> {
+    let weights = validate(vectors, weights, params)?;
+    match params.method {
+        Method::Mean | Method::WeightedMean => {
+            Ok((to_f32(weighted_mean(vectors, &weights))?, None))
+        }
+        Method::NormalizedMean => {
+            let units: Vec<Vec<f32>> = vectors
+                .iter()
+                .map(|vector| crate::distances::normalize_l2(vector.to_vec()))
+                .collect::<Result<_, _>>()?;
+            let units: Vec<&[f32]> = units.iter().map(Vec::as_slice).collect();
+            let mean = to_f32(weighted_mean(&units, &weights))?;
+            Ok((crate::distances::normalize_l2(mean)?, None))
+        }
+        Method::GeometricMedian => Ok((to_f32(geometric_median(vectors, &weights, params))?, None)),
+        Method::Medoid => {
+            let position = medoid(vectors, &weights, params.metric)?;
+            Ok((vectors[position].to_vec(), Some(position)))
+        }
+    }
+}
+
+/// Aggregates the stored flat-index vectors of `ids`, returning any medoid id.
+pub fn aggregate_flat(
+    index: &FlatIndex,
+    ids: &[String],
+    weights: Option<&[f32]>,
+    params: Params,
+) -> Result<(Vec<f32>, Option<String>), String> {
+    aggregate_stored(ids, weights, params, |id| index.get(id))
+}
+
+/// Aggregates the stored HNSW vectors of `ids`, returning any medoid id.
+pub fn aggregate_hnsw(
+    index: &HnswIndex,
+    ids: &[String],
+    weights: Option<&[f32]>,
+    params: Params,
+) -> Result<(Vec<f32>, Option<String>), String> {
+    aggregate_stored(ids, weights, params, |id| index.get(id))
+}
+
+fn aggregate_stored<'a>(
+    ids: &[String],
+    weights: Option<&[f32]>,
+    params: Params,
+    get: impl Fn(&str) -> Option<&'a [f32]>,
+) -> Result<(Vec<f32>, Option<String>), String> {
+    if weights.is_some_and(|weights| weights.len() != ids.len()) {
+        return Err("weights must match vectors".to_string());
+    }
+    let mut found = Vec::with_capacity(ids.len());
+    let mut vectors = Vec::with_capacity(ids.len());
+    let mut kept_weights = Vec::with_capacity(ids.len());
+    for (position, id) in ids.iter().enumerate() {
+        if let Some(vector) = get(id) {
+            found.push(id);
+            vectors.push(vector);
+            if let Some(weights) = weights {
+                kept_weights.push(weights[position]);
+            }
+        }
+    }
+    let (vector, medoid) = aggregate(&vectors, weights.map(|_| kept_weights.as_slice()), params)?;
+    Ok((vector, medoid.map(|position| found[position].clone())))
+}
+
+/// Checks shapes and values, returning f64 weights that sum to one.
+fn validate(
+    vectors: &[&[f32]],
+    weights: Option<&[f32]>,
+    params: Params,
+) -> Result<Vec<f64>, String> {
+    let Some(first) = vectors.first() else {
+        return Err("no vectors to aggregate".to_string());
+    };
+    if first.is_empty() {
+        return Err("vector must not be empty".to_string());
+    }
+    for vector in vectors {
+        if vector.len() != first.len() {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+    }
+    if params.method == Method::GeometricMedian
+        && (!params.tolerance.is_finite() || params.tolerance < 0.0)
+    {
+        return Err("tolerance must be finite and non-negative".to_string());
+    }
+
+    let Some(weights) = weights else {
+        if params.method == Method::WeightedMean {
+            return Err("weighted mean needs weights".to_string());
+        }
+        return Ok(vec![1.0 / vectors.len() as f64; vectors.len()]);
+    };
+    if weights.len() != vectors.len() {
+        return Err("weights must match vectors".to_string());
+    }
+    if weights
+        .iter()
+        .any(|weight| !weight.is_finite() || *weight < 0.0)
+    {
+        return Err("weights must be finite and non-negative".to_string());
+    }
+    let total: f64 = weights.iter().map(|weight| f64::from(*weight)).sum();
+    if total <= 0.0 {
+        return Err("weights must not all be zero".to_string());
+    }
+    Ok(weights
+        .iter()
+        .map(|weight| f64::from(*weight) / total)
+        .collect())
+}
+
+fn weighted_mean(vectors: &[&[f32]], weights: &[f64]) -> Vec<f64> {
+    let mut mean = vec![0.0f64; vectors[0].len()];
+    for (vector, weight) in vectors.iter().zip(weights) {
+        for (sum, value) in mean.iter_mut().zip(vector.iter()) {
+            *sum += weight * f64::from(*value);
+        }
+    }
+    mean
+}
+
+fn geometric_median(vectors: &[&[f32]], weights: &[f64], params: Params) -> Vec<f64> {
+    let mut median = weighted_mean(vectors, weights);
+    for _ in 0..params.max_iterations {
+        let mut numerator = vec![0.0f64; median.len()];
+        let mut denominator = 0.0f64;
+        for (vector, weight) in vectors.iter().zip(weights) {
+            let distance = f64_l2_squared(vector, &median).sqrt();
+            if distance < COINCIDENCE || *weight == 0.0 {
+                continue;
+            }
+            let pull = weight / distance;
+            for (sum, value) in numerator.iter_mut().zip(vector.iter()) {
+                *sum += pull * f64::from(*value);
+            }
+            denominator += pull;
+        }
+        if denominator == 0.0 {
+            break;
+        }
+        let next: Vec<f64> = numerator.iter().map(|sum| sum / denominator).collect();
+        let step = f64_l2_squared(&next, &median).sqrt();
+        let scale = f64_dot(&next, &next).sqrt();
+        median = next;
+        if step <= params.tolerance * (1.0 + scale) {
+            break;
+        }
+    }
+    median
+}
+
+/// Position minimizing the weighted rank distance to every input; ties go to
+/// the earliest position.
+fn medoid(vectors: &[&[f32]], weights: &[f64], metric: Metric) -> Result<usize, String> {
+    let mut best = (f64::INFINITY, 0);
+    for (position, candidate) in vectors.iter().enumerate() {
+        let mut cost = 0.0f64;
+        for (other, weight) in vectors.iter().zip(weights) {
+            let distance = if metric == Metric::Cosine {
+                1.0 - crate::distances::cosine(candidate, other)?
+            } else {
+                crate::distances::rank_distance(metric, candidate, other)?
+            };
+            cost += weight * f64::from(distance);
+        }
+        if cost < best.0 {
+            best = (cost, position);
+        }
+    }
+    Ok(best.1)
+}
+
+fn to_f32(vector: Vec<f64>) -> Result<Vec<f32>, String> {
+    vector
+        .into_iter()
+        .map(|value| f64_to_f32(value).ok_or_else(|| "aggregation overflow".to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hnsw::HnswParams;
+
+    fn params(method: Method) -> Params {
+        Params {
+            method,
+            metric: Metric::L2,
+            max_iterations: 200,
+            tolerance: 1.0e-9,
+        }
+    }
+
+    fn slices(vectors: &[Vec<f32>]) -> Vec<&[f32]> {
+        vectors.iter().map(Vec::as_slice).collect()
+    }
+
+    #[test]
+    fn means_accumulate_in_f64_and_honour_weights() {
+        let vectors = vec![vec![f32::MAX, 1.0], vec![f32::MAX, 3.0]];
+        let (mean, medoid) = aggregate(&slices(&vectors), None, params(Method::Mean)).unwrap();
+        assert_eq!(mean, vec![f32::MAX, 2.0]);
+        assert_eq!(medoid, None);
+
+        let vectors = vec![vec![0.0, 0.0], vec![4.0, 8.0]];
+        let (weighted, _) = aggregate(
+            &slices(&vectors),
+            Some(&[3.0, 1.0]),
+            params(Method::WeightedMean),
+        )
+        .unwrap();
+        assert_eq!(weighted, vec![1.0, 2.0]);
+        let (zero_weight, _) =
+            aggregate(&slices(&vectors), Some(&[0.0, 2.0]), params(Method::Mean)).unwrap();
+        assert_eq!(zero_weight, vec![4.0, 8.0]);
+    }
+
+    #[test]
+    fn normalized_mean_ignores_input_scale() {
+        let small = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let large = vec![vec![100.0, 0.0], vec![0.0, 0.01]];
+        let (left, _) = aggregate(&slices(&small), None, params(Method::NormalizedMean)).unwrap();
+        let (right, _) = aggregate(&slices(&large), None, params(Method::NormalizedMean)).unwrap();
+        assert_eq!(left, right);
+        assert!((left[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1.0e-6);
+        assert!((left[0] - left[1]).abs() < 1.0e-7);
+    }
+
+    #[test]
+    fn geometric_median_resists_outliers() {
+        let vectors = vec![
+            vec![0.0, 0.0],
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![1.0, 1.0],
+            vec![1_000.0, 1_000.0],
+        ];
+        let (mean, _) = aggregate(&slices(&vectors), None, params(Method::Mean)).unwrap();
+        let (median, _) =
+            aggregate(&slices(&vectors), None, params(Method::GeometricMedian)).unwrap();
+        assert!(mean[0] > 100.0);
+        assert!(
+            median.iter().all(|value| (0.4..=1.0).contains(value)),
+            "{median:?}"
+        );
+
+        let cost = |point: &[f32]| -> f64 {
+            vectors
+                .iter()
+                .map(|vector| f64::from(crate::distances::l2(point, vector)))
+                .sum()
+        };
+        for offset in [[0.01, 0.0], [-0.01, 0.0], [0.0, 0.01], [0.0, -0.01]] {
+            let moved = [median[0] + offset[0], median[1] + offset[1]];
+            assert!(cost(&median) <= cost(&moved) + 1.0e-6);
+        }
+
+        // Collinear inputs have the middle input as their median.
+        let line = vec![vec![0.0], vec![1.0], vec![10.0]];
+        let (median, _) = aggregate(&slices(&line), None, params(Method::GeometricMedian)).unwrap();
+        assert!((median[0] - 1.0).abs() < 1.0e-3, "{median:?}");
+    }
+
+    #[test]
+    fn medoids_follow_the_metric_and_weights() {
+        let vectors = vec![
+            vec![1.0, 0.0],
+            vec![100.0, 0.0],
+            vec![0.0, 1.0],
+            vec![50.0, 30.0],
+        ];
+        let (vector, position) =
+            aggregate(&slices(&vectors), None, params(Method::Medoid)).unwrap();
+        assert_eq!((vector, position), (vec![1.0, 0.0], Some(0)));
+
+        let cosine = Params {
+            metric: Metric::Cosine,
+            ..params(Method::Medoid)
+        };
+        let (_, position) = aggregate(&slices(&vectors), None, cosine).unwrap();
+        assert_eq!(position, Some(3));
+
+        let (_, position) = aggregate(
+            &slices(&vectors),
+            Some(&[0.0, 0.0, 1.0, 0.0]),
+            params(Method::Medoid),
+        )
+        .unwrap();
+        assert_eq!(position, Some(2));
+    }
+
+    #[test]
+    fn index_aggregation_skips_stale_ids_and_reports_medoid_ids() {
+        let stored = vec![
+            ("a".to_string(), vec![0.0, 0.0]),
+            ("b".to_string(), vec![2.0, 0.0]),
+            ("c".to_string(), vec![2.0, 2.0]),
+        ];
+        let mut flat = FlatIndex::new(Metric::L2);
+        flat.insert_many(stored.clone()).unwrap();
+        let mut hnsw = HnswIndex::new(
+            Metric::L2,
+            HnswParams {
+                m: 4,
+                m0: 8,
+                ef_construction: 16,
+                ef_search: 16,
+                max_level: 4,
+            },
+        )
+        .unwrap();
+        hnsw.insert_many(stored).unwrap();
+
+        let ids: Vec<String> = ["a", "gone", "c", "b"].map(String::from).to_vec();
+        let weights = [1.0, 100.0, 1.0, 2.0];
+        for params in [params(Method::WeightedMean), params(Method::Medoid)] {
+            let flat_result = aggregate_flat(&flat, &ids, Some(&weights), params).unwrap();
+            let hnsw_result = aggregate_hnsw(&hnsw, &ids, Some(&weights), params).unwrap();
+            assert_eq!(flat_result, hnsw_result);
+        }
+        assert_eq!(
+            aggregate_flat(&flat, &ids, Some(&weights), params(Method::WeightedMean)),
+            Ok((vec![1.5, 0.5], None))
+        );
+        assert_eq!(
+            aggregate_flat(&flat, &ids, None, params(Method::Medoid)),
+            Ok((vec![2.0, 0.0], Some("b".to_string())))
+        );
+        assert!(aggregate_flat(&flat, &ids, Some(&[1.0]), params(Method::Mean)).is_err());
+        assert_eq!(
+            aggregate_flat(&flat, &["gone".to_string()], None, params(Method::Mean)).err(),
+            Some("no vectors to aggregate".into())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_vectors_weights_and_params() {
+        assert_eq!(
+            Method::from_code(5).err(),
+            Some("unknown aggregation".into())
+        );
+        let vectors = vec![vec![1.0, 2.0], vec![3.0, 4.0]];
+        let vectors = slices(&vectors);
+        assert!(aggregate(&[], None, params(Method::Mean)).is_err());
+        assert!(aggregate(&[&[]], None, params(Method::Mean)).is_err());
+        assert!(aggregate(&[&[1.0], &[1.0, 2.0]], None, params(Method::Mean)).is_err());
+        assert!(aggregate(&[&[f32::NAN]], None, params(Method::Mean)).is_err());
+        assert!(aggregate(&vectors, None, params(Method::WeightedMean)).is_err());
+        for weights in [[1.0, -1.0], [0.0, 0.0], [1.0, f32::INFINITY]] {
+            assert!(aggregate(&vectors, Some(&weights), params(Method::Mean)).is_err());
+        }
+        assert!(aggregate(&vectors, Some(&[1.0]), params(Method::Mean)).is_err());
+        let invalid = Params {
+            tolerance: f64::NAN,
+            ..params(Method::GeometricMedian)
+        };
+        assert!(aggregate(&vectors, None, invalid).is_err());
+    }
+}
@@ -0,0 +1,628 @@
+//! Native outlier scoring against vectors stored in flat or HNSW indexes.
+//!
+//! Every score grows with how isolated a vector is from the stored reference
+//! set:
+//!
+//! - `knn`: mean rank distance to the `k` nearest stored vectors.
+//! - `lof`: Local Outlier Factor over the `k`-neighbourhood; about 1 inside
+//!   uniformly dense regions and well above 1 for outliers.
+//! - `isolation`: isolation-forest score `2^(-E[h] / c(n))` from seeded random
+//!   axis-aligned trees over subsamples; values near 1 are easy to isolate.
+//!
+//! Neighbours come from the index's own top-k search (exact for flat, graph
+//! search for HNSW), and stored vectors never count as their own neighbour.
+//! Scoring needs a distance-like metric, so inner-product indexes are rejected.
+
+use std::collections::HashMap;
+
+use crate::distances::Metric;
+use crate::flat::{FlatIndex, NeighborGraph};
+use crate::hnsw::HnswIndex;
+use crate::muvera::hash4;
+
+/// Hash stream for isolation-tree subsamples and splits.
+const ISOLATION_STREAM: u64 = 0x6973_6F6C_6174_696F;
+
+/// Keeps local reachability densities finite when neighbours coincide.
+const DENSITY_EPSILON: f64 = 1.0e-12;
+
+/// Per-id `knn`, `lof` and `isolation` scores over every stored vector.
+pub type Report = Vec<(String, Scores)>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub k: usize,
+    pub trees: usize,
+    pub sample_size: usize,
+    pub seed: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scores {
+    pub knn: f32,
+    pub lof: f32,
+    pub isolation: f32,
+}
+
+/// Scores query vectors against a flat index's stored vectors.
+pub fn flat_scores(
+    index: &FlatIndex,
+    queries: &[Vec<f32>],
+    params: Params,
+) -> Result<Vec<Scores>, String> {
+    scores(index, queries, params)
+}
+
+/// Scores query vectors against an HNSW graph's stored vectors.
+pub fn hnsw_scores(
+    index: &HnswIndex,
+    queries: &[Vec<f32>],
+    params: Params,
+) -> Result<Vec<Scores>, String> {
+    scores(index, queries, params)
+}
+
+/// Scores every stored flat-index vector against the rest, by id.
+pub fn flat_report(index: &FlatIndex, params: Params) -> Result<Report, String> {
+    report(index, params)
+}
+
+/// Scores every stored HNSW vector against the rest, by id.
+pub fn hnsw_report(index: &HnswIndex, params: Params) -> Result<Report, String> {
+    report(index, params)
+}
+
+/// The index operations anomaly scoring needs.
+trait Reference {
+    fn metric(&self) -> Metric;
+    fn search(&self, query: &[f32], limit: usize) -> Result<Vec<(String, f32)>, String>;
+    fn knn_graph(&self, k: usize) -> Result<NeighborGraph, String>;
+    fn get(&self, id: &str) -> Option<&[f32]>;
+    fn sorted_entries(&self) -> Vec<(&str, &[f32])>;
+}
+
+impl Reference for FlatIndex {
+    fn metric(&self) -> Metric {
+        FlatIndex::metric(self)
+    }
+
+    fn search(&self, query: &[f32], limit: usize) -> Result<Vec<(String, f32)>, String> {
+        FlatIndex::search(self, query, limit)
+    }
+
+    fn knn_graph(&self, k: usize) -> Result<NeighborGraph, String> {
+        FlatIndex::knn_graph(self, k)
+    }
+
+    fn get(&self, id: &str) -> Option<&[f32]> {
+        FlatIndex::get(self, id)
+    }
+
+    fn sorted_entries(&self) -> Vec<(&str, &[f32])> {
+        FlatIndex::sorted_entries(self)
+    }
+}
+
+impl Reference for HnswIndex {
+    fn metric(&self) -> Metric {
+        HnswIndex::metric(self)
+    }
+
+    fn search(&self, query: &[f32], limit: usize) -> Result<Vec<(String, f32)>, String> {
+        HnswIndex::search(self, query, limit)
+    }
+
+    fn knn_graph(&self, k: usize) -> Result<NeighborGraph, String> {
+        HnswIndex::knn_graph(self, k)
+    }
+
+    fn get(&self, id: &str) -> Option<&[f32]> {
+        HnswIndex::get(self, id)
+    }
+
+    fn sorted_entries(&self) -> Vec<(&str, &[f32])> {
+        HnswIndex::sorted_entries(self)
+    }
+}
+
+fn scores<R: Reference>(
+    index: &R,
+    queries: &[Vec<f32>],
+    params: Params,
+) -> Result<Vec<Scores>, String> {
+    let entries = index.sorted_entries();
+    validate(index.metric(), entries.len(), params)?;
+    let forest = Forest::build(&entries, params);
+    let mut neighbourhoods = Neighbourhoods::new(index, params.k);
+
+    queries
+        .iter()
+        .map(|query| {
+            let neighbours = distances(index.metric(), index.search(query, params.k)?);
+            Ok(Scores {
+                knn: mean(&neighbours) as f32,
+                lof: neighbourhoods.lof(&neighbours)? as f32,
+                isolation: forest.score(query) as f32,
+            })
+        })
+        .collect()
+}
+
+fn report<R: Reference>(index: &R, params: Params) -> Result<Report, String> {
+    let entries = index.sorted_entries();
+    validate(index.metric(), entries.len(), params)?;
+    let forest = Forest::build(&entries, params);
+    let mut neighbourhoods = Neighbourhoods::new(index, params.k);
+    for (id, neighbours) in index.knn_graph(params.k)? {
+        neighbourhoods
+            .cache
+            .insert(id, distances(index.metric(), neighbours));
+    }
+
+    entries
+        .iter()
+        .map(|(id, vector)| {
+            let neighbours = neighbourhoods.of(id)?.clone();
+            Ok((
+                id.to_string(),
+                Scores {
+                    knn: mean(&neighbours) as f32,
+                    lof: neighbourhoods.lof(&neighbours)? as f32,
+                    isolation: forest.score(vector) as f32,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn validate(metric: Metric, stored: usize, params: Params) -> Result<(), String> {
+    if matches!(metric, Metric::InnerProduct | Metric::NegativeInnerProduct) {
+        return Err("anomaly scoring needs a distance metric".to_string());
+    }
+    if params.k == 0 {
+        return Err("k must be positive".to_string());
+    }
+    if params.trees == 0 || params.sample_size < 2 {
+        return Err("isolation forest needs trees and a sample size of at least 2".to_string());
+    }
+    if stored <= params.k {
+        return Err("anomaly scoring needs more than k stored vectors".to_string());
+    }
+    Ok(())
+}
+
+/// Raw metric hits as non-negative rank distances, nearest first.
+fn distances(metric: Metric, hits: Vec<(String, f32)>) -> Vec<(String, f64)> {
+    hits.into_iter()
+        .map(|(id, raw)| {
+            let distance = crate::distances::rank_value(metric, raw).max(0.0);
+            (id, f64::from(distance))
+        })
+        .collect()
+}
+
+fn mean(neighbours: &[(String, f64)]) -> f64 {
+    neighbours.iter().map(|(_, distance)| distance).sum::<f64>() / neighbours.len().max(1) as f64
+}
+
+/// Memoized stored-vector neighbourhoods for LOF's two-hop densities.
+struct Neighbourhoods<'a, R> {
+    index: &'a R,
+    k: usize,
+    cache: HashMap<String, Vec<(String, f64)>>,
+}
+
+impl<'a, R: Reference> Neighbourhoods<'a, R> {
+    fn new(index: &'a R, k: usize) -> Self {
+        Self {
+            index,
+            k,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The `k` nearest other stored vectors of a stored id.
+    fn of(&mut self, id: &str) -> Result<&Vec<(String, f64)>, String> {
+        if !self.cache.contains_key(id) {
+            let vector = self
+                .index
+                .get(id)
+                .ok_or_else(|| "missing neighbour vector".to_string())?;
+            let mut hits = self.index.search(vector, self.k + 1)?;
+            hits.retain(|(neighbour, _)| neighbour != id);
+            hits.truncate(self.k);
+            let neighbours = distances(self.index.metric(), hits);
+            self.cache.insert(id.to_string(), neighbours);
+        }
+        Ok(&self.cache[id])
+    }
+
+    fn k_distance(&mut self, id: &str) -> Result<f64, String> {
+        Ok(self.of(id)?.last().map_or(0.0, |(_, distance)| *distance))
+    }
+
+    /// Local reachability density from a point's neighbours and distances.
+    fn density(&mut self, neighbours: &[(String, f64)]) -> Result<f64, String> {
+        let mut reach = 0.0f64;
+        for (id, distance) in neighbours {
+            reach += self.k_distance(id)?.max(*distance);
+        }
+        Ok(1.0 / (reach / neighbours.len().max(1) as f64 + DENSITY_EPSILON))
+    }
+
+    fn lof(&mut self, neighbours: &[(String, f64)]) -> Result<f64, String> {
+        let own = self.density(neighbours)?;
+        let mut total = 0.0f64;
+        for (id, _) in neighbours {
+            let theirs = self.of(id)?.clone();
+            total += self.density(&theirs)?;
+        }
+        Ok(total / neighbours.len().max(1) as f64 / own)
+    }
+}
+
+enum Node {
+    Split {
+        dimension: usize,
+        value: f32,
+        left: usize,
+        right: usize,
+    },
+    Leaf {
+        size: usize,
+    },
+}
+
+/// Seeded isolation forest over subsamples of the stored vectors.
+struct Forest {
+    trees: Vec<Vec<Node>>,
+    sample_size: usize,
+}
+
+impl Forest {
+    fn build(entries: &[(&str, &[f32])], params: Params) -> Self {
+        let sample_size = usize::min(params.sample_size, entries.len());
+        let height = (sample_size as f64).log2().ceil() as usize;
+        let trees = (0..params.trees)
+            .map(|tree| {
+                // Partial Fisher-Yates shuffle picks the tree's subsample.
+                let mut positions: Vec<usize> = (0..entries.len()).collect();
+                for slot in 0..sample_size {
+                    let draw = hash4(params.seed, ISOLATION_STREAM, tree as u64, slot as u64);
+                    let other = slot + (draw % (entries.len() - slot) as u64) as usize;
+                    positions.swap(slot, other);
+                }
+                let sample: Vec<&[f32]> = positions[..sample_size]
+                    .iter()
+                    .map(|position| entries[*position].1)
+                    .collect();
+                let mut nodes = Vec::new();
+                let mut builder = TreeBuilder {
+                    nodes: &mut nodes,
+                    seed: params.seed,
+                    tree: tree as u64,
+                    height,
+                };
+                builder.grow(sample, 0);
+                nodes
+            })
+            .collect();
+        Self { trees, sample_size }
+    }
+
+    fn score(&self, vector: &[f32]) -> f64 {
+        let total: f64 = self
+            .trees
+            .iter()
+            .map(|nodes| {
+                let (mut node, mut depth) = (0, 0.0f64);
+                loop {
+                    match &nodes[node] {
+                        Node::Split {
+                            dimension,
+                            value,
+                            left,
+                            right,
+                        } => {
+                            node = if vector[*dimension] < *value {
+                                *left
+                            } else {
+                                *right
+                            };
+                            depth += 1.0;
+                        }
+                        Node::Leaf { size } => break depth + average_path(*size),
+                    }
+                }
+            })
+            .sum();
+        let expected = total / self.trees.len() as f64;
+        let normalizer = average_path(self.sample_size);
+        if normalizer > 0.0 {
+            2f64.powf(-expected / normalizer)
+        } else {
+            0.5
+        }
+    }
+}
+
+struct TreeBuilder<'a> {
+    nodes: &'a mut Vec<Node>,
+    seed: u64,
+    tree: u64,
+    height: usize,
+}
+
+impl TreeBuilder<'_> {
+    /// Appends the subtree for `points` and returns its node position.
+    fn grow(&mut self, points: Vec<&[f32]>, depth: usize) -> usize {
+        let position = self.nodes.len();
+        self.nodes.push(Node::Leaf { size: points.len() });
+        if depth >= self.height || points.len() <= 1 {
+            return position;
+        }
+
+        let dimensions = points[0].len();
+        let draw = |salt: u64| {
+            hash4(
+                self.seed,
+                ISOLATION_STREAM ^ salt,
+                self.tree,
+                position as u64,
+            )
+        };
+        let start = (draw(1) % dimensions as u64) as usize;
+        // Try dimensions from a random offset until one still varies.
+        let Some((dimension, low, high)) = (0..dimensions).find_map(|offset| {
+            let dimension = (start + offset) % dimensions;
+            let (low, high) = points
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(low, high), point| {
+                    (low.min(point[dimension]), high.max(point[dimension]))
+                });
+            (high > low).then_some((dimension, low, high))
+        }) else {
+            return position;
+        };
+        let fraction = (draw(2) >> 11) as f64 / (1u64 << 53) as f64;
+        let value = (f64::from(low) + fraction * (f64::from(high) - f64::from(low))) as f32;
+        let value = if value > low { value } else { high };
+
+        let (below, above): (Vec<&[f32]>, Vec<&[f32]>) = points
+            .into_iter()
+            .partition(|point| point[dimension] < value);
+        let left = self.grow(below, depth + 1);
+        let right = self.grow(above, depth + 1);
+        self.nodes[position] = Node::Split {
+            dimension,
+            value,
+            left,
+            right,
+        };
+        position
+    }
+}
+
+/// Average unsuccessful-search path length `c(n)` of a binary search tree.
+fn average_path(size: usize) -> f64 {
+    match size {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        _ => {
+            let size = size as f64;
+            2.0 * ((size - 1.0).ln() + 0.577_215_664_901_532_9) - 2.0 * (size - 1.0) / size
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hnsw::HnswParams;
+
+    fn params() -> Params {
+        Params {
+            k: 5,
+            trees: 100,
+            sample_size: 64,
+            seed: 3,
+        }
+    }
+
+    /// A jittered 8x8 grid plus one far-away point.
+    fn points() -> Vec<(String, Vec<f32>)> {
+        let mut points: Vec<(String, Vec<f32>)> = (0..64u64)
+            .map(|index| {
+                let jitter = |salt| (hash4(index, salt, 5, 0) % 100) as f32 / 1_000.0;
+                (
+                    format!("p{index:02}"),
+                    vec![
+                        (index % 8) as f32 + jitter(0),
+                        (index / 8) as f32 + jitter(1),
+                    ],
+                )
+            })
+            .collect();
+        points.push(("outlier".into(), vec![20.0, 20.0]));
+        points
+    }
+
+    fn flat() -> FlatIndex {
+        let mut index = FlatIndex::new(Metric::L2);
+        index.insert_many(points()).unwrap();
+        index
+    }
+
+    fn hnsw() -> HnswIndex {
+        let mut index = HnswIndex::new(
+            Metric::L2,
+            HnswParams {
+                m: 8,
+                m0: 16,
+                ef_construction: 128,
+                ef_search: 128,
+                max_level: 8,
+            },
+        )
+        .unwrap();
+        index.insert_many(points()).unwrap();
+        index
+    }
+
+    /// Textbook LOF over exact neighbour lists.
+    fn naive_lof(points: &[(String, Vec<f32>)], k: usize) -> HashMap<String, f64> {
+        let neighbours: HashMap<&str, Vec<(&str, f64)>> = points
+            .iter()
+            .map(|(id, vector)| {
+                let mut others: Vec<(&str, f64)> = points
+                    .iter()
+                    .filter(|(other, _)| other != id)
+                    .map(|(other, right)| {
+                        (
+                            other.as_str(),
+                            f64::from(crate::distances::l2(vector, right)),
+                        )
+                    })
+                    .collect();
+                others.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+                others.truncate(k);
+                (id.as_str(), others)
+            })
+            .collect();
+        let k_distance = |id: &str| neighbours[id].last().unwrap().1;
+        let density = |id: &str| {
+            let reach: f64 = neighbours[id]
+                .iter()
+                .map(|(other, distance)| k_distance(other).max(*distance))
+                .sum();
+            1.0 / (reach / k as f64 + DENSITY_EPSILON)
+        };
+        points
+            .iter()
+            .map(|(id, _)| {
+                let mean: f64 = neighbours[id.as_str()]
+                    .iter()
+                    .map(|(other, _)| density(other))
+                    .sum::<f64>()
+                    / k as f64;
+                (id.clone(), mean / density(id))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn report_isolates_the_outlier_and_matches_textbook_lof() {
+        let report = flat_report(&flat(), params()).unwrap();
+        assert_eq!(report.len(), 65);
+        let expected = naive_lof(&points(), 5);
+        for (id, scores) in &report {
+            assert!((f64::from(scores.lof) - expected[id]).abs() < 1.0e-4 * expected[id]);
+        }
+
+        let (outlier, inliers): (Vec<_>, Vec<_>) =
+            report.iter().partition(|(id, _)| id == "outlier");
+        let outlier = outlier[0].1;
+        assert!(outlier.lof > 5.0, "{outlier:?}");
+        assert!(outlier.isolation > 0.6, "{outlier:?}");
+        for (_, scores) in inliers {
+            assert!(scores.knn < outlier.knn / 5.0);
+            assert!(scores.lof < 2.0, "{scores:?}");
+            assert!(scores.isolation < outlier.isolation);
+        }
+    }
+
+    #[test]
+    fn query_scores_rank_far_queries_above_inliers() {
+        let index = flat();
+        let scores = flat_scores(
+            &index,
+            &[vec![3.5, 3.5], vec![12.0, -6.0], vec![20.0, 20.5]],
+            params(),
+        )
+        .unwrap();
+        let (inside, far, near_outlier) = (scores[0], scores[1], scores[2]);
+        assert!(far.knn > 5.0 * inside.knn);
+        assert!(far.lof > 3.0 && inside.lof < 1.5, "{inside:?} {far:?}");
+        assert!(far.isolation > inside.isolation);
+        // Next to the lone outlier it is sparse but not the most isolated.
+        assert!(near_outlier.lof > inside.lof);
+
+        let mut exact: Vec<f32> = points()
+            .iter()
+            .map(|(_, vector)| crate::distances::l2(&[12.0, -6.0], vector))
+            .collect();
+        exact.sort_by(f32::total_cmp);
+        let expected = exact[..5].iter().map(|d| f64::from(*d)).sum::<f64>() / 5.0;
+        assert!((f64::from(far.knn) - expected).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn hnsw_scores_agree_with_flat_on_small_indexes() {
+        let queries = vec![vec![3.5, 3.5], vec![12.0, -6.0]];
+        let flat_scores = flat_scores(&flat(), &queries, params()).unwrap();
+        let hnsw_scores = hnsw_scores(&hnsw(), &queries, params()).unwrap();
+        for (flat, hnsw) in flat_scores.iter().zip(&hnsw_scores) {
+            assert!((flat.knn - hnsw.knn).abs() < 1.0e-5);
+            assert!((flat.lof - hnsw.lof).abs() < 1.0e-4);
+            assert_eq!(flat.isolation, hnsw.isolation);
+        }
+        let flat_report = flat_report(&flat(), params()).unwrap();
+        let hnsw_report = hnsw_report(&hnsw(), params()).unwrap();
+        assert_eq!(
+            flat_report.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            hnsw_report.iter().map(|(id, _)| id).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn isolation_scores_are_seeded_and_bounded() {
+        let index = flat();
+        let queries = vec![vec![3.5, 3.5], vec![30.0, 0.0]];
+        let first = flat_scores(&index, &queries, params()).unwrap();
+        let again = flat_scores(&index, &queries, params()).unwrap();
+        let other = flat_scores(
+            &index,
+            &queries,
+            Params {
+                seed: 4,
+                ..params()
+            },
+        )
+        .unwrap();
+        assert_eq!(first, again);
+        assert_ne!(first[0].isolation, other[0].isolation);
+        for scores in first.iter().chain(&other) {
+            assert!(scores.isolation > 0.0 && scores.isolation < 1.0);
+        }
+        assert_eq!(average_path(2), 1.0);
+        assert!((average_path(256) - 10.244).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn rejects_invalid_params_and_metrics() {
+        let index = flat();
+        for invalid in [
+            Params { k: 0, ..params() },
+            Params {
+                trees: 0,
+                ..params()
+            },
+            Params {
+                sample_size: 1,
+                ..params()
+            },
+            Params { k: 65, ..params() },
+        ] {
+            assert!(flat_report(&index, invalid).is_err());
+        }
+        assert!(flat_scores(&index, &[vec![1.0]], params()).is_err());
+        assert_eq!(flat_scores(&index, &[], params()), Ok(vec![]));
+
+        let mut inner = FlatIndex::new(Metric::InnerProduct);
+        inner.insert_many(points()).unwrap();
+        assert_eq!(
+            flat_report(&inner, params()).err(),
+            Some("anomaly scoring needs a distance metric".into())
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! Native binary-quantized index resource.
+//!
+//! Sign-bit codes are packed into one contiguous `u64` table so a query scans
+//! them with hardware popcount and no per-query copy of the codes. Indexes
+//! created with `keep_vectors` also hold full f32 vectors contiguously and
+//! rerank the Hamming candidates exactly; code-only indexes return Hamming
+//! candidates so callers can rerank with supplied vectors via `vector_top_k`.
+
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::RwLock;
+
+use crate::distances::Metric;
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+pub struct BinaryIndex {
+    metric: Metric,
+    keep_vectors: bool,
+    dimensions: Option<usize>,
+    ids: Vec<String>,
+    positions: HashMap<String, usize>,
+    /// Row-major packed codes, `ids.len() * words` long.
+    codes: Vec<u64>,
+    /// Row-major full vectors, empty unless `keep_vectors` is set.
+    vectors: Vec<f32>,
+}
+
+impl BinaryIndex {
+    /// Creates an empty binary index that reranks by `metric`.
+    pub fn new(metric: Metric, keep_vectors: bool) -> Self {
+        Self {
+            metric,
+            keep_vectors,
+            dimensions: None,
+            ids: Vec::new(),
+            positions: HashMap::new(),
+            codes: Vec::new(),
+            vectors: Vec::new(),
+        }
+    }
+
+    /// Inserts or replaces one vector, storing its packed sign bits.
+    pub fn insert(&mut self, id: String, vector: Vec<f32>) -> Result<(), String> {
+        self.insert_many(vec![(id, vector)])
+    }
+
+    /// Inserts or replaces a batch of vectors.
+    pub fn insert_many(&mut self, vectors: Vec<(String, Vec<f32>)>) -> Result<(), String> {
+        let expected = self
+            .dimensions
+            .or_else(|| vectors.first().map(|(_, vector)| vector.len()));
+        for (_, vector) in &vectors {
+            if vector.is_empty() {
+                return Err("vector must not be empty".to_string());
+            }
+            if expected.is_some_and(|dimensions| vector.len() != dimensions) {
+                return Err("dimension mismatch".to_string());
+            }
+            crate::distances::validate_finite_vector(vector)?;
+        }
+
+        self.dimensions = expected;
+        for (id, vector) in vectors {
+            let code = crate::distances::compress_sign_bits(&vector);
+            self.put(id, &code, Some(&vector));
+        }
+        Ok(())
+    }
+
+    /// Inserts or replaces precomputed packed codes in a code-only index.
+    ///
+    /// Padding bits past `dimensions` are cleared so scans need no masking.
+    pub fn insert_codes(
+        &mut self,
+        codes: Vec<(String, Vec<u64>)>,
+        dimensions: usize,
+    ) -> Result<(), String> {
+        if self.keep_vectors {
+            return Err("binary index keeps vectors; insert full vectors".to_string());
+        }
+        if self
+            .dimensions
+            .is_some_and(|expected| expected != dimensions)
+        {
+            return Err("dimension mismatch".to_string());
+        }
+        for (_, code) in &codes {
+            crate::distances::packed_hamming(code, code, dimensions)?;
+        }
+
+        self.dimensions = Some(dimensions);
+        for (id, mut code) in codes {
+            for (index, word) in code.iter_mut().enumerate() {
+                *word &= crate::distances::word_mask(index, dimensions);
+            }
+            self.put(id, &code, None);
+        }
+        Ok(())
+    }
+
+    /// Deletes one entry by external id, moving the last row into its slot.
+    pub fn delete(&mut self, id: &str) {
+        let Some(position) = self.positions.remove(id) else {
+            return;
+        };
+        let last = self.ids.len() - 1;
+        let words = self.words();
+        let dimensions = self.dimensions.unwrap_or(0);
+
+        self.ids.swap_remove(position);
+        if position != last {
+            self.positions.insert(self.ids[position].clone(), position);
+            self.codes
+                .copy_within(last * words..(last + 1) * words, position * words);
+            if self.keep_vectors {
+                self.vectors.copy_within(
+                    last * dimensions..(last + 1) * dimensions,
+                    position * dimensions,
+                );
+            }
+        }
+        self.codes.truncate(last * words);
+        if self.keep_vectors {
+            self.vectors.truncate(last * dimensions);
+        }
+        if self.ids.is_empty() {
+            self.dimensions = None;
+        }
+    }
+
+    /// Returns the ids nearest to a packed query code by Hamming distance.
+    pub fn hamming_search(
+        &self,
+        query: &[u64],
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        Ok(self
+            .hamming_candidates(query, limit)?
+            .into_iter()
+            .map(|(distance, position)| (self.ids[position].clone(), distance as f32))
+            .collect())
+    }
+
+    /// Selects Hamming candidates for a float query and reranks them exactly.
+    ///
+    /// Returns ids with raw metric values, computed with true cosine for the
+    /// cosine metric like the Elixir exact rerank.
+    pub fn search(
+        &self,
+        query: &[f32],
+        candidates: usize,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        if !self.keep_vectors {
+            return Err("binary index does not keep vectors".to_string());
+        }
+        if candidates < limit {
+            return Err("candidates must be at least limit".to_string());
+        }
+        if query.is_empty() {
+            return Err("vector must not be empty".to_string());
+        }
+        crate::distances::validate_finite_vector(query)?;
+        let Some(dimensions) = self.dimensions else {
+            return Ok(Vec::new());
+        };
+        if query.len() != dimensions {
+            return Err("dimension mismatch".to_string());
+        }
+
+        let code = crate::distances::compress_sign_bits(query);
+        let rows = self
+            .hamming_candidates(&code, candidates)?
+            .into_iter()
+            .map(|(_, position)| {
+                (
+                    self.ids[position].as_str(),
+                    &self.vectors[position * dimensions..(position + 1) * dimensions],
+                )
+            });
+        Ok(
+            crate::search::prefix_top_k(rows, query, self.metric, dimensions, limit)?
+                .into_iter()
+                .map(|(id, raw)| (id.to_string(), raw))
+                .collect(),
+        )
+    }
+
+    /// Scans the contiguous code table and returns `(distance, row)` pairs,
+    /// best first with ties broken by external id.
+    fn hamming_candidates(&self, query: &[u64], limit: usize) -> Result<Vec<(u32, usize)>, String> {
+        let Some(dimensions) = self.dimensions else {
+            return Ok(Vec::new());
+        };
+        crate::distances::packed_hamming(query, query, dimensions)?;
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let masked: Vec<u64> = query
+            .iter()
+            .enumerate()
+            .map(|(index, word)| word & crate::distances::word_mask(index, dimensions))
+            .collect();
+        let mut heap = BinaryHeap::with_capacity(usize::min(limit, self.ids.len()));
+        for (position, code) in self.codes.chunks_exact(masked.len()).enumerate() {
+            let distance = code
+                .iter()
+                .zip(&masked)
+                .map(|(left, right)| (left ^ right).count_ones())
+                .sum::<u32>();
+            // The exact distance leads the id, so hits whose f32 ranks round
+            // together still order by distance before external id.
+            let hit = Hit {
+                id: (distance, self.ids[position].as_str(), position),
+                raw: distance as f32,
+                rank: distance as f32,
+            };
+            push_top_k(&mut heap, hit, limit);
+        }
+
+        Ok(sorted_hits(heap)
+            .into_iter()
+            .map(|((distance, _, position), _)| (distance, position))
+            .collect())
+    }
+
+    fn put(&mut self, id: String, code: &[u64], vector: Option<&[f32]>) {
+        let words = code.len();
+        match self.positions.get(&id) {
+            Some(position) => {
+                let position = *position;
+                self.codes[position * words..(position + 1) * words].copy_from_slice(code);
+                if let (true, Some(vector)) = (self.keep_vectors, vector) {
+                    let dimensions = vector.len();
+                    self.vectors[position * dimensions..(position + 1) * dimensions]
+                        .copy_from_slice(vector);
+                }
+            }
+            None => {
+                self.positions.insert(id.clone(), self.ids.len());
+                self.ids.push(id);
+                self.codes.extend_from_slice(code);
+                if let (true, Some(vector)) = (self.keep_vectors, vector) {
+                    self.vectors.extend_from_slice(vector);
+                }
+            }
+        }
+    }
+
+    fn words(&self) -> usize {
+        self.dimensions.unwrap_or(0).div_ceil(64)
+    }
+}
+
+pub struct BinaryResource(pub RwLock<BinaryIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for BinaryResource {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vectors(count: usize, dimensions: usize) -> Vec<(String, Vec<f32>)> {
+        (0..count)
+            .map(|index| {
+                (
+                    format!("v-{index:03}"),
+                    (0..dimensions)
+                        .map(|dimension| {
+                            ((index * 31 + dimension * 17) % 23) as f32 - 11.0
+                                + dimension as f32 * 0.1
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hamming_search_matches_binary_top_k_across_word_boundaries() {
+        let vectors = vectors(150, 70);
+        let mut index = BinaryIndex::new(Metric::L2, false);
+        index.insert_many(vectors.clone()).unwrap();
+
+        let codes: Vec<_> = vectors
+            .iter()
+            .map(|(id, vector)| (id.clone(), crate::distances::compress_sign_bits(vector)))
+            .collect();
+        for (_, query) in vectors.iter().step_by(17) {
+            let query = crate::distances::compress_sign_bits(query);
+            for limit in [0, 1, 10, 200] {
+                assert_eq!(
+                    index.hamming_search(&query, limit).unwrap(),
+                    crate::search::binary_top_k(codes.clone(), &query, 70, limit).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn search_reranks_candidates_with_held_vectors() {
+        let vectors = vectors(120, 16);
+        let query: Vec<f32> = (0..16).map(|dimension| dimension as f32 - 7.5).collect();
+        let query_code = crate::distances::compress_sign_bits(&query);
+        let codes: Vec<_> = vectors
+            .iter()
+            .map(|(id, vector)| (id.clone(), crate::distances::compress_sign_bits(vector)))
+            .collect();
+        let lookup: HashMap<_, _> = vectors.iter().cloned().collect();
+
+        for metric in [Metric::L2, Metric::Cosine, Metric::InnerProduct] {
+            let mut index = BinaryIndex::new(metric, true);
+            index.insert_many(vectors.clone()).unwrap();
+
+            let candidates: Vec<_> =
+                crate::search::binary_top_k(codes.clone(), &query_code, 16, 30)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(id, _)| (id.clone(), lookup[&id].clone()))
+                    .collect();
+            assert_eq!(
+                index.search(&query, 30, 5).unwrap(),
+                crate::search::vector_top_k(candidates, &query, metric, 16, 5).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn replacements_and_swap_deletes_keep_rows_aligned() {
+        let mut index = BinaryIndex::new(Metric::L2, true);
+        index
+            .insert_many(vec![
+                ("a".into(), vec![1.0, 1.0]),
+                ("b".into(), vec![-1.0, -1.0]),
+                ("c".into(), vec![1.0, -1.0]),
+            ])
+            .unwrap();
+        index.insert("b".into(), vec![2.0, 2.0]).unwrap();
+        index.delete("a");
+        index.delete("missing");
+
+        assert_eq!(index.ids, vec!["c".to_string(), "b".into()]);
+        assert_eq!(index.positions["c"], 0);
+        assert_eq!(index.vectors, vec![1.0, -1.0, 2.0, 2.0]);
+        assert_eq!(
+            index.search(&[2.0, 2.0], 2, 2).unwrap(),
+            vec![("b".into(), 0.0), ("c".into(), 3.1622777)]
+        );
+
+        index.delete("c");
+        index.delete("b");
+        assert_eq!(index.dimensions, None);
+        assert!(index.codes.is_empty() && index.vectors.is_empty());
+        index.insert("wide".into(), vec![1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(index.dimensions, Some(3));
+    }
+
+    #[test]
+    fn code_only_indexes_mask_padding_and_reject_float_search() {
+        let mut index = BinaryIndex::new(Metric::L2, false);
+        index
+            .insert_codes(vec![("a".into(), vec![u64::MAX]), ("b".into(), vec![0])], 3)
+            .unwrap();
+        assert_eq!(index.codes, vec![0b111, 0]);
+        assert_eq!(
+            index.hamming_search(&[0b101], 2).unwrap(),
+            vec![("a".into(), 1.0), ("b".into(), 2.0)]
+        );
+        assert!(index.hamming_search(&[0, 0], 2).is_err());
+        assert!(index.search(&[1.0, 1.0, 1.0], 2, 1).is_err());
+        assert!(index.insert_codes(vec![("c".into(), vec![0])], 4).is_err());
+        assert!(index
+            .insert_codes(vec![("c".into(), vec![0, 0])], 3)
+            .is_err());
+
+        let mut full = BinaryIndex::new(Metric::L2, true);
+        assert!(full.insert_codes(vec![("a".into(), vec![0])], 3).is_err());
+        assert!(full.insert("a".into(), vec![]).is_err());
+        assert!(full.insert("a".into(), vec![f32::NAN]).is_err());
+        full.insert("a".into(), vec![1.0]).unwrap();
+        assert!(full.insert("b".into(), vec![1.0, 2.0]).is_err());
+        assert!(full.search(&[1.0, 2.0], 2, 1).is_err());
+        assert!(full.search(&[1.0], 1, 2).is_err());
+        assert_eq!(full.search(&[1.0], 1, 0), Ok(vec![]));
+    }
+}
@@ -0,0 +1,485 @@
+//! Native nearest-neighbour and nearest-centroid classification.
+//!
+//! Labelled examples live in an exact `FlatIndex`; a second flat index holds
+//! one centroid per label, either set explicitly or kept as the running mean
+//! of that label's examples. Cosine classifiers normalize examples, centroids
+//! and queries, matching how collections prepare vectors for cosine search.
+//!
+//! `classify` scans for the `k` nearest examples (or centroids) and turns each
+//! neighbour's `distances::similarity_value` into a vote:
+//!
+//! - `Majority`: one vote per neighbour; the score is the vote count.
+//! - `Weighted`: the score is the summed similarity, and each vote weighs
+//!   `exp((similarity - best) / temperature)`.
+//! - `Centroid`: the score is the centroid similarity, weighted as above.
+//!
+//! Confidences are each label's share of the total vote weight, so they sum
+//! to one; `temperature` is the calibration knob for the weighted votes.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::distances::Metric;
+use crate::flat::FlatIndex;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Vote {
+    Majority,
+    Weighted,
+    Centroid,
+}
+
+impl Vote {
+    /// Decodes the compact vote selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Majority),
+            1 => Ok(Self::Weighted),
+            2 => Ok(Self::Centroid),
+            _ => Err("unknown vote".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Prediction {
+    pub label: String,
+    /// Vote count, summed similarity or centroid similarity, by vote.
+    pub score: f32,
+    /// Share of the total vote weight, in `[0, 1]`.
+    pub confidence: f32,
+}
+
+pub struct Classifier {
+    metric: Metric,
+    examples: FlatIndex,
+    labels: HashMap<String, String>,
+    /// Per-label f64 vector sums and example counts for running means.
+    sums: HashMap<String, (Vec<f64>, usize)>,
+    centroids: FlatIndex,
+    /// Labels whose centroid was set explicitly rather than averaged.
+    explicit: HashSet<String>,
+}
+
+impl Classifier {
+    /// Creates an empty classifier ranking by `metric`.
+    pub fn new(metric: Metric) -> Self {
+        Self {
+            metric,
+            examples: FlatIndex::new(metric),
+            labels: HashMap::new(),
+            sums: HashMap::new(),
+            centroids: FlatIndex::new(metric),
+            explicit: HashSet::new(),
+        }
+    }
+
+    /// Inserts or replaces one labelled example.
+    pub fn insert(&mut self, id: String, label: String, vector: Vec<f32>) -> Result<(), String> {
+        self.insert_many(vec![(id, label, vector)])
+    }
+
+    /// Inserts or replaces a batch of labelled examples; the last occurrence
+    /// of a repeated id wins.
+    pub fn insert_many(&mut self, examples: Vec<(String, String, Vec<f32>)>) -> Result<(), String> {
+        let mut examples = examples
+            .into_iter()
+            .map(|(id, label, vector)| {
+                let vector = self.prepare(vector)?;
+                self.check_dimension(&vector)?;
+                Ok((id, label, vector))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if examples
+            .windows(2)
+            .any(|pair| pair[0].2.len() != pair[1].2.len())
+        {
+            return Err("dimension mismatch".to_string());
+        }
+        let mut seen = HashSet::new();
+        examples.reverse();
+        examples.retain(|(id, _, _)| seen.insert(id.clone()));
+        examples.reverse();
+
+        let mut touched = HashSet::new();
+        for (id, _, _) in &examples {
+            if let Some(previous) = self.forget(id)? {
+                touched.insert(previous);
+            }
+        }
+        self.examples.insert_many(
+            examples
+                .iter()
+                .map(|(id, _, vector)| (id.clone(), vector.clone()))
+                .collect(),
+        )?;
+        for (id, label, vector) in examples {
+            self.accumulate(&label, &vector, 1.0);
+            self.labels.insert(id, label.clone());
+            touched.insert(label);
+        }
+        for label in touched {
+            self.refresh_centroid(&label)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes one labelled example.
+    pub fn delete(&mut self, id: &str) -> Result<(), String> {
+        let Some(label) = self.forget(id)? else {
+            return Ok(());
+        };
+        self.examples.delete(id);
+        self.refresh_centroid(&label)
+    }
+
+    /// Pins a label's centroid, or with `None` returns it to the example mean.
+    pub fn set_centroid(&mut self, label: String, vector: Option<Vec<f32>>) -> Result<(), String> {
+        match vector {
+            Some(vector) => {
+                let vector = self.prepare(vector)?;
+                self.check_dimension(&vector)?;
+                self.centroids.insert(label.clone(), vector)?;
+                self.explicit.insert(label);
+                Ok(())
+            }
+            None => {
+                self.explicit.remove(&label);
+                self.refresh_centroid(&label)
+            }
+        }
+    }
+
+    /// Classifies `query` from its `k` nearest examples or centroids.
+    pub fn classify(
+        &self,
+        query: Vec<f32>,
+        k: usize,
+        vote: Vote,
+        temperature: f32,
+    ) -> Result<Vec<Prediction>, String> {
+        if !temperature.is_finite() || temperature <= 0.0 {
+            return Err("temperature must be finite and positive".to_string());
+        }
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let query = self.prepare(query)?;
+        let neighbours: Vec<(String, f32)> = match vote {
+            Vote::Centroid => self.centroids.search(&query, k)?,
+            Vote::Majority | Vote::Weighted => self
+                .examples
+                .search(&query, k)?
+                .into_iter()
+                .map(|(id, raw)| (self.labels[&id].clone(), raw))
+                .collect(),
+        }
+        .into_iter()
+        .map(|(label, raw)| (label, crate::distances::similarity_value(self.metric, raw)))
+        .collect();
+        let Some(best) = neighbours
+            .iter()
+            .map(|(_, similarity)| *similarity)
+            .reduce(f32::max)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut totals: HashMap<&str, (f64, f64)> = HashMap::new();
+        for (label, similarity) in &neighbours {
+            let (score, weight) = match vote {
+                Vote::Majority => (1.0, 1.0),
+                Vote::Weighted | Vote::Centroid => (
+                    f64::from(*similarity),
+                    (f64::from(*similarity - best) / f64::from(temperature)).exp(),
+                ),
+            };
+            let total = totals.entry(label.as_str()).or_insert((0.0, 0.0));
+            total.0 += score;
+            total.1 += weight;
+        }
+        let weight_sum: f64 = totals.values().map(|(_, weight)| weight).sum();
+
+        let mut predictions: Vec<Prediction> = totals
+            .into_iter()
+            .map(|(label, (score, weight))| Prediction {
+                label: label.to_string(),
+                score: score as f32,
+                confidence: (weight / weight_sum) as f32,
+            })
+            .collect();
+        predictions.sort_by(|left, right| {
+            right
+                .confidence
+                .total_cmp(&left.confidence)
+                .then_with(|| left.label.cmp(&right.label))
+        });
+        Ok(predictions)
+    }
+
+    /// Removes an existing example's label and its contribution to the
+    /// label's sum, returning the label.
+    fn forget(&mut self, id: &str) -> Result<Option<String>, String> {
+        let Some(label) = self.labels.remove(id) else {
+            return Ok(None);
+        };
+        let Some(vector) = self.examples.get(id).map(<[f32]>::to_vec) else {
+            return Err("missing classifier example".to_string());
+        };
+        self.accumulate(&label, &vector, -1.0);
+        Ok(Some(label))
+    }
+
+    fn accumulate(&mut self, label: &str, vector: &[f32], sign: f64) {
+        let entry = self
+            .sums
+            .entry(label.to_string())
+            .or_insert_with(|| (vec![0.0; vector.len()], 0));
+        for (sum, value) in entry.0.iter_mut().zip(vector) {
+            *sum += sign * f64::from(*value);
+        }
+        if sign > 0.0 {
+            entry.1 += 1;
+        } else {
+            entry.1 -= 1;
+        }
+        if entry.1 == 0 {
+            self.sums.remove(label);
+        }
+    }
+
+    /// Recomputes an averaged label centroid after its examples change.
+    fn refresh_centroid(&mut self, label: &str) -> Result<(), String> {
+        if self.explicit.contains(label) {
+            return Ok(());
+        }
+        let Some((sum, count)) = self.sums.get(label) else {
+            self.centroids.delete(label);
+            return Ok(());
+        };
+        let mean: Vec<f32> = sum.iter().map(|sum| (sum / *count as f64) as f32).collect();
+        let mean = if self.metric == Metric::Cosine {
+            if mean.iter().all(|value| *value == 0.0) {
+                // Opposing examples can cancel out; such a label has no direction.
+                self.centroids.delete(label);
+                return Ok(());
+            }
+            crate::distances::normalize_l2(mean)?
+        } else {
+            mean
+        };
+        self.centroids.insert(label.to_string(), mean)
+    }
+
+    fn prepare(&self, vector: Vec<f32>) -> Result<Vec<f32>, String> {
+        if self.metric == Metric::Cosine {
+            crate::distances::normalize_l2(vector)
+        } else {
+            crate::distances::validate_finite_vector(&vector)?;
+            Ok(vector)
+        }
+    }
+
+    /// Keeps examples and centroids in one vector space even while one is empty.
+    fn check_dimension(&self, vector: &[f32]) -> Result<(), String> {
+        let stored = self
+            .labels
+            .keys()
+            .next()
+            .and_then(|id| self.examples.get(id))
+            .or_else(|| {
+                self.explicit
+                    .iter()
+                    .next()
+                    .and_then(|label| self.centroids.get(label))
+            });
+        match stored {
+            Some(stored) if stored.len() != vector.len() => Err("dimension mismatch".to_string()),
+            _ if vector.is_empty() => Err("vector must not be empty".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct ClassifierResource(pub RwLock<Classifier>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for ClassifierResource {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classifier(metric: Metric) -> Classifier {
+        let mut classifier = Classifier::new(metric);
+        classifier
+            .insert_many(vec![
+                ("a1".into(), "alpha".into(), vec![0.0, 0.0]),
+                ("a2".into(), "alpha".into(), vec![0.0, 2.0]),
+                ("b1".into(), "beta".into(), vec![10.0, 0.0]),
+                ("b2".into(), "beta".into(), vec![10.0, 2.0]),
+                ("b3".into(), "beta".into(), vec![12.0, 1.0]),
+            ])
+            .unwrap();
+        classifier
+    }
+
+    fn labels(predictions: &[Prediction]) -> Vec<&str> {
+        predictions
+            .iter()
+            .map(|prediction| prediction.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn majority_votes_count_neighbours_and_confidences_sum_to_one() {
+        let classifier = classifier(Metric::L2);
+        let predictions = classifier
+            .classify(vec![1.0, 1.0], 4, Vote::Majority, 1.0)
+            .unwrap();
+        assert_eq!(labels(&predictions), vec!["alpha", "beta"]);
+        assert_eq!(predictions[0].score, 2.0);
+        assert_eq!(predictions[1].score, 2.0);
+        assert_eq!(predictions[0].confidence, 0.5);
+
+        let predictions = classifier
+            .classify(vec![11.0, 1.0], 5, Vote::Majority, 1.0)
+            .unwrap();
+        assert_eq!(labels(&predictions), vec!["beta", "alpha"]);
+        assert!((predictions[0].confidence - 0.6).abs() < 1.0e-6);
+        let total: f32 = predictions.iter().map(|p| p.confidence).sum();
+        assert!((total - 1.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn weighted_votes_favour_close_neighbours_and_temperature_calibrates() {
+        let mut classifier = Classifier::new(Metric::InnerProduct);
+        classifier
+            .insert_many(vec![
+                ("near".into(), "x".into(), vec![1.0, 0.0]),
+                ("far1".into(), "y".into(), vec![0.5, 0.1]),
+                ("far2".into(), "y".into(), vec![0.5, -0.1]),
+            ])
+            .unwrap();
+        let majority = classifier
+            .classify(vec![1.0, 0.0], 3, Vote::Majority, 1.0)
+            .unwrap();
+        assert_eq!(majority[0].label, "y");
+
+        let sharp = classifier
+            .classify(vec![1.0, 0.0], 3, Vote::Weighted, 0.05)
+            .unwrap();
+        assert_eq!(sharp[0].label, "x");
+        assert!((sharp[1].score - 1.0).abs() < 1.0e-6);
+        assert!(sharp[0].confidence > 0.99);
+
+        let flat = classifier
+            .classify(vec![1.0, 0.0], 3, Vote::Weighted, 1.0e6)
+            .unwrap();
+        assert_eq!(flat[0].label, "y");
+        assert!((flat[0].confidence - 2.0 / 3.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn centroids_track_example_means_and_explicit_overrides() {
+        let mut classifier = classifier(Metric::L2);
+        assert_eq!(classifier.centroids.get("alpha"), Some(&[0.0, 1.0][..]));
+        assert_eq!(
+            classifier.centroids.get("beta"),
+            Some(&[32.0 / 3.0, 1.0][..])
+        );
+
+        classifier
+            .insert("a2".into(), "beta".into(), vec![14.0, 0.0])
+            .unwrap();
+        classifier.delete("b3").unwrap();
+        classifier.delete("missing").unwrap();
+        assert_eq!(classifier.centroids.get("alpha"), Some(&[0.0, 0.0][..]));
+        assert_eq!(
+            classifier.centroids.get("beta"),
+            Some(&[34.0 / 3.0, 2.0 / 3.0][..])
+        );
+
+        let predictions = classifier
+            .classify(vec![9.0, 0.0], 2, Vote::Centroid, 1.0)
+            .unwrap();
+        assert_eq!(labels(&predictions), vec!["beta", "alpha"]);
+
+        classifier
+            .set_centroid("alpha".into(), Some(vec![9.0, 0.5]))
+            .unwrap();
+        classifier
+            .set_centroid("gamma".into(), Some(vec![-5.0, 0.0]))
+            .unwrap();
+        let predictions = classifier
+            .classify(vec![9.0, 0.0], 3, Vote::Centroid, 1.0)
+            .unwrap();
+        assert_eq!(labels(&predictions), vec!["alpha", "beta", "gamma"]);
+        assert_eq!(predictions[0].score, 1.0 / 1.5);
+
+        classifier.set_centroid("alpha".into(), None).unwrap();
+        classifier.set_centroid("gamma".into(), None).unwrap();
+        assert_eq!(classifier.centroids.get("alpha"), Some(&[0.0, 0.0][..]));
+        assert_eq!(classifier.centroids.get("gamma"), None);
+        classifier.delete("a1").unwrap();
+        assert_eq!(classifier.centroids.get("alpha"), None);
+    }
+
+    #[test]
+    fn cosine_classifiers_ignore_vector_scale() {
+        let mut classifier = Classifier::new(Metric::Cosine);
+        classifier
+            .insert_many(vec![
+                ("a".into(), "east".into(), vec![10.0, 1.0]),
+                ("b".into(), "east".into(), vec![1.0, 0.0]),
+                ("c".into(), "north".into(), vec![0.0, 3.0]),
+            ])
+            .unwrap();
+        for scale in [0.01, 1.0, 100.0] {
+            for vote in [Vote::Majority, Vote::Weighted, Vote::Centroid] {
+                let predictions = classifier
+                    .classify(vec![scale, 0.2 * scale], 1, vote, 0.1)
+                    .unwrap();
+                assert_eq!(labels(&predictions), vec!["east"]);
+                assert_eq!(predictions[0].confidence, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_inputs_without_partial_updates() {
+        assert_eq!(Vote::from_code(3).err(), Some("unknown vote".into()));
+        let mut classifier = classifier(Metric::L2);
+        assert!(classifier
+            .insert_many(vec![
+                ("c1".into(), "gamma".into(), vec![1.0, 1.0]),
+                ("c2".into(), "gamma".into(), vec![1.0]),
+            ])
+            .is_err());
+        assert!(classifier
+            .insert("c1".into(), "gamma".into(), vec![f32::NAN, 1.0])
+            .is_err());
+        assert!(classifier
+            .set_centroid("gamma".into(), Some(vec![1.0, 2.0, 3.0]))
+            .is_err());
+        assert!(!classifier.labels.contains_key("c1"));
+        assert_eq!(classifier.centroids.get("gamma"), None);
+
+        for temperature in [0.0, -1.0, f32::NAN] {
+            assert!(classifier
+                .classify(vec![1.0, 1.0], 3, Vote::Weighted, temperature)
+                .is_err());
+        }
+        assert!(classifier
+            .classify(vec![1.0], 3, Vote::Majority, 1.0)
+            .is_err());
+        assert_eq!(
+            classifier.classify(vec![1.0, 1.0], 0, Vote::Majority, 1.0),
+            Ok(vec![])
+        );
+        assert_eq!(
+            Classifier::new(Metric::L2).classify(vec![1.0], 3, Vote::Centroid, 1.0),
+            Ok(vec![])
+        );
+    }
+}
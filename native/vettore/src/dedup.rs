@@ -0,0 +1,333 @@
+//! Native near-duplicate detection over stored index vectors.
+//!
+//! Two ids are linked when their raw metric value passes a threshold under the
+//! index metric: at most the threshold for distances, at least the threshold
+//! for similarities. Linked ids are merged with union-find, so clusters are the
+//! connected components of that threshold graph.
+
+use std::collections::HashMap;
+
+use crate::distances::Metric;
+use crate::flat::FlatIndex;
+use crate::hnsw::HnswIndex;
+
+/// Rows and columns compared together by the blocked brute-force pass.
+const BLOCK_SIZE: usize = 256;
+
+/// One representative id plus every member id of a duplicate cluster.
+pub type Cluster = (String, Vec<String>);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Representative {
+    SmallestId,
+    Medoid,
+}
+
+impl Representative {
+    /// Decodes the compact representative selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::SmallestId),
+            1 => Ok(Self::Medoid),
+            _ => Err("unknown representative".to_string()),
+        }
+    }
+}
+
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut node: usize) -> usize {
+        while self.parents[node] != node {
+            self.parents[node] = self.parents[self.parents[node]];
+            node = self.parents[node];
+        }
+        node
+    }
+
+    fn union(&mut self, left: usize, right: usize) {
+        let (mut left, mut right) = (self.find(left), self.find(right));
+        if left == right {
+            return;
+        }
+        if self.sizes[left] < self.sizes[right] {
+            std::mem::swap(&mut left, &mut right);
+        }
+        self.parents[right] = left;
+        self.sizes[left] += self.sizes[right];
+    }
+}
+
+/// Clusters flat-index vectors with an exact blocked all-pairs scan.
+pub fn flat_clusters(
+    index: &FlatIndex,
+    threshold: f32,
+    representative: Representative,
+) -> Result<Vec<Cluster>, String> {
+    validate_threshold(threshold)?;
+    let metric = index.metric();
+    let entries = index.sorted_entries();
+    let mut components = UnionFind::new(entries.len());
+
+    for (row_block, rows) in entries.chunks(BLOCK_SIZE).enumerate() {
+        let row_offset = row_block * BLOCK_SIZE;
+        for (column_block, columns) in entries.chunks(BLOCK_SIZE).enumerate().skip(row_block) {
+            let column_offset = column_block * BLOCK_SIZE;
+            for (row, (_, left)) in rows.iter().enumerate() {
+                for (column, (_, right)) in columns.iter().enumerate() {
+                    let (left_index, right_index) = (row_offset + row, column_offset + column);
+                    if right_index <= left_index {
+                        continue;
+                    }
+                    let raw = crate::distances::compute(metric, left, right)?;
+                    if passes(metric, raw, threshold) {
+                        components.union(left_index, right_index);
+                    }
+                }
+            }
+        }
+    }
+
+    collect_clusters(&entries, metric, components, representative)
+}
+
+/// Clusters HNSW vectors by linking each node to its approximate range hits.
+pub fn hnsw_clusters(
+    index: &HnswIndex,
+    threshold: f32,
+    representative: Representative,
+) -> Result<Vec<Cluster>, String> {
+    validate_threshold(threshold)?;
+    let metric = index.metric();
+    let entries = index.sorted_entries();
+    let positions: HashMap<&str, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(position, (id, _))| (*id, position))
+        .collect();
+    let mut components = UnionFind::new(entries.len());
+
+    for (position, (_, vector)) in entries.iter().enumerate() {
+        for (id, _) in index.range_search(vector, threshold)? {
+            if let Some(other) = positions.get(id.as_str()) {
+                components.union(position, *other);
+            }
+        }
+    }
+
+    collect_clusters(&entries, metric, components, representative)
+}
+
+fn validate_threshold(threshold: f32) -> Result<(), String> {
+    if threshold.is_finite() {
+        Ok(())
+    } else {
+        Err("threshold must be finite".to_string())
+    }
+}
+
+fn passes(metric: Metric, raw: f32, threshold: f32) -> bool {
+    crate::distances::rank_value(metric, raw) <= crate::distances::rank_value(metric, threshold)
+}
+
+/// Groups components with at least two members, ordered by smallest member id.
+fn collect_clusters(
+    entries: &[(&str, &[f32])],
+    metric: Metric,
+    mut components: UnionFind,
+    representative: Representative,
+) -> Result<Vec<Cluster>, String> {
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for position in 0..entries.len() {
+        groups
+            .entry(components.find(position))
+            .or_default()
+            .push(position);
+    }
+
+    let mut groups: Vec<Vec<usize>> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .collect();
+    // Entries are id-sorted, so the first member position is the smallest id.
+    groups.sort_unstable_by_key(|members| members[0]);
+
+    groups
+        .into_iter()
+        .map(|members| {
+            let chosen = match representative {
+                Representative::SmallestId => members[0],
+                Representative::Medoid => medoid(entries, metric, &members)?,
+            };
+            Ok((
+                entries[chosen].0.to_string(),
+                members
+                    .into_iter()
+                    .map(|position| entries[position].0.to_string())
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Picks the member with the lowest summed rank distance to the others.
+fn medoid(entries: &[(&str, &[f32])], metric: Metric, members: &[usize]) -> Result<usize, String> {
+    let mut best = (members[0], f64::INFINITY);
+    for candidate in members {
+        let mut total = 0.0f64;
+        for other in members {
+            if other != candidate {
+                total += f64::from(crate::distances::rank_distance(
+                    metric,
+                    entries[*candidate].1,
+                    entries[*other].1,
+                )?);
+            }
+        }
+        if total < best.1 {
+            best = (*candidate, total);
+        }
+    }
+    Ok(best.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hnsw::HnswParams;
+
+    fn hnsw_params() -> HnswParams {
+        HnswParams {
+            m: 8,
+            m0: 16,
+            ef_construction: 64,
+            ef_search: 4,
+            max_level: 12,
+        }
+    }
+
+    fn vectors() -> Vec<(String, Vec<f32>)> {
+        vec![
+            ("d".into(), vec![0.0, 0.0]),
+            ("a".into(), vec![0.05, 0.0]),
+            ("b".into(), vec![0.1, 0.0]),
+            ("x".into(), vec![10.0, 10.0]),
+            ("y".into(), vec![10.0, 10.02]),
+            ("lonely".into(), vec![-20.0, 5.0]),
+        ]
+    }
+
+    #[test]
+    fn flat_clusters_are_connected_components_with_stable_representatives() {
+        let mut index = FlatIndex::new(Metric::L2);
+        index.insert_many(vectors()).unwrap();
+
+        let expected_members = [
+            vec!["a".to_string(), "b".into(), "d".into()],
+            vec!["x".to_string(), "y".into()],
+        ];
+        let clusters = flat_clusters(&index, 0.06, Representative::SmallestId).unwrap();
+        assert_eq!(
+            clusters,
+            vec![
+                ("a".into(), expected_members[0].clone()),
+                ("x".into(), expected_members[1].clone()),
+            ]
+        );
+
+        let medoids = flat_clusters(&index, 0.06, Representative::Medoid).unwrap();
+        assert_eq!(medoids[0], ("a".into(), expected_members[0].clone()));
+        assert!(flat_clusters(&index, 0.01, Representative::SmallestId)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn similarity_thresholds_link_pairs_at_or_above_the_threshold() {
+        let mut index = FlatIndex::new(Metric::Cosine);
+        index
+            .insert_many(vec![
+                ("a".into(), vec![1.0, 0.0]),
+                ("b".into(), vec![0.0, 1.0]),
+                ("c".into(), vec![1.0, 0.0]),
+            ])
+            .unwrap();
+        assert_eq!(
+            flat_clusters(&index, 0.99, Representative::SmallestId).unwrap(),
+            vec![("a".into(), vec!["a".into(), "c".into()])]
+        );
+        assert_eq!(
+            flat_clusters(&index, 0.0, Representative::SmallestId)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn blocked_scan_matches_a_naive_pair_oracle_across_block_boundaries() {
+        let vectors: Vec<_> = (0..700)
+            .map(|index| (format!("v-{index:03}"), vec![(index / 3) as f32 * 10.0]))
+            .collect();
+        let mut index = FlatIndex::new(Metric::L2);
+        index.insert_many(vectors).unwrap();
+
+        let clusters = flat_clusters(&index, 0.5, Representative::SmallestId).unwrap();
+        assert_eq!(clusters.len(), 700 / 3);
+        assert!(clusters.iter().all(|(_, members)| members.len() == 3));
+        assert_eq!(
+            clusters[100].1,
+            vec!["v-300".to_string(), "v-301".into(), "v-302".into()]
+        );
+    }
+
+    #[test]
+    fn hnsw_range_search_finds_groups_larger_than_ef_search() {
+        let mut vectors = vectors();
+        vectors.extend((0..20).map(|index| (format!("dup-{index:02}"), vec![50.0, 50.0])));
+
+        let mut flat = FlatIndex::new(Metric::L2);
+        flat.insert_many(vectors.clone()).unwrap();
+        let mut hnsw = HnswIndex::new(Metric::L2, hnsw_params()).unwrap();
+        hnsw.insert_many(vectors).unwrap();
+
+        for representative in [Representative::SmallestId, Representative::Medoid] {
+            assert_eq!(
+                hnsw_clusters(&hnsw, 0.06, representative).unwrap(),
+                flat_clusters(&flat, 0.06, representative).unwrap()
+            );
+        }
+
+        let hits = hnsw.range_search(&[50.0, 50.0], 0.0).unwrap();
+        assert!(hits.len() > hnsw_params().ef_search);
+        assert!(hits
+            .iter()
+            .all(|(id, raw)| id.starts_with("dup-") && *raw == 0.0));
+    }
+
+    #[test]
+    fn rejects_unknown_representatives_and_non_finite_thresholds() {
+        assert_eq!(Representative::from_code(0), Ok(Representative::SmallestId));
+        assert_eq!(Representative::from_code(1), Ok(Representative::Medoid));
+        assert!(Representative::from_code(2).is_err());
+
+        let index = FlatIndex::new(Metric::L2);
+        assert!(flat_clusters(&index, f32::NAN, Representative::SmallestId).is_err());
+        assert_eq!(
+            flat_clusters(&index, 1.0, Representative::SmallestId),
+            Ok(vec![])
+        );
+        let hnsw = HnswIndex::new(Metric::L2, hnsw_params()).unwrap();
+        assert!(hnsw_clusters(&hnsw, f32::INFINITY, Representative::Medoid).is_err());
+    }
+}
@@ -89,7 +89,8 @@ fn recover_metric_overflow(metric: Metric, left: &[f32], right: &[f32]) -> Optio
     f64_to_f32(recovered)
 }
 
-fn f64_to_f32(value: f64) -> Option<f32> {
+/// Narrows an f64 result to f32, or `None` when it is not a finite f32.
+pub(crate) fn f64_to_f32(value: f64) -> Option<f32> {
     if value.is_finite() && value >= f64::from(f32::MIN) && value <= f64::from(f32::MAX) {
         Some(value as f32)
     } else {
@@ -176,18 +177,28 @@ pub fn cosine(left: &[f32], right: &[f32]) -> Result<f32, String> {
     }
 }
 
-fn f64_dot(left: &[f32], right: &[f32]) -> f64 {
+/// Dot product accumulated in f64 so f32 inputs cannot overflow midway.
+pub(crate) fn f64_dot<L, R>(left: &[L], right: &[R]) -> f64
+where
+    L: Copy + Into<f64>,
+    R: Copy + Into<f64>,
+{
     left.iter()
         .zip(right)
-        .map(|(left, right)| f64::from(*left) * f64::from(*right))
+        .map(|(left, right)| (*left).into() * (*right).into())
         .sum()
 }
 
-fn f64_l2_squared(left: &[f32], right: &[f32]) -> f64 {
+/// Squared L2 distance accumulated in f64 so f32 inputs cannot overflow midway.
+pub(crate) fn f64_l2_squared<L, R>(left: &[L], right: &[R]) -> f64
+where
+    L: Copy + Into<f64>,
+    R: Copy + Into<f64>,
+{
     left.iter()
         .zip(right)
         .map(|(left, right)| {
-            let difference = f64::from(*left) - f64::from(*right);
+            let difference = (*left).into() - (*right).into();
             difference * difference
         })
         .sum()
@@ -469,7 +480,7 @@ fn validate_packed_pair(left: &[u64], right: &[u64], dimensions: usize) -> Resul
     Ok(())
 }
 
-fn word_mask(index: usize, dimensions: usize) -> u64 {
+pub(crate) fn word_mask(index: usize, dimensions: usize) -> u64 {
     let words = dimensions.div_ceil(64);
     let remainder = dimensions % 64;
 
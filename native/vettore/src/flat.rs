@@ -4,45 +4,24 @@
 //! dense vectors so exact scans happen in one native call instead of one NIF
 //! metric call per stored row.
 
-use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
 use std::sync::RwLock;
 
 use crate::distances::Metric;
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+/// Rows and columns scored together by blocked all-pairs scans.
+const KNN_BLOCK_SIZE: usize = 256;
+
+/// Each external id paired with its nearest neighbours and raw metric values.
+pub type NeighborGraph = Vec<(String, Vec<(String, f32)>)>;
 
 pub struct FlatIndex {
     metric: Metric,
     vectors: HashMap<String, Vec<f32>>,
     dimension: Option<usize>,
-}
-
-#[derive(Debug)]
-struct FlatHit {
-    id: String,
-    raw: f32,
-    rank: f32,
-}
-
-impl Eq for FlatHit {}
-
-impl PartialEq for FlatHit {
-    fn eq(&self, other: &Self) -> bool {
-        self.rank.total_cmp(&other.rank) == Ordering::Equal && self.id == other.id
-    }
-}
-
-impl Ord for FlatHit {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.rank
-            .total_cmp(&other.rank)
-            .then_with(|| self.id.cmp(&other.id))
-    }
-}
-
-impl PartialOrd for FlatHit {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+    /// MUVERA config fingerprint of stored FDEs, if any were written natively.
+    fingerprint: Option<u64>,
 }
 
 impl FlatIndex {
@@ -52,11 +31,48 @@ impl FlatIndex {
             metric,
             vectors: HashMap::new(),
             dimension: None,
+            fingerprint: None,
         }
     }
 
+    /// Inserts or replaces one MUVERA FDE written under a config fingerprint.
+    pub fn insert_fde(
+        &mut self,
+        id: String,
+        vector: Vec<f32>,
+        fingerprint: u64,
+    ) -> Result<(), String> {
+        self.insert_many_fde(vec![(id, vector)], fingerprint)
+    }
+
+    /// Inserts MUVERA FDEs, binding their config fingerprint once they are stored.
+    pub fn insert_many_fde(
+        &mut self,
+        vectors: Vec<(String, Vec<f32>)>,
+        fingerprint: u64,
+    ) -> Result<(), String> {
+        self.check_insert(Some(fingerprint))?;
+        self.store_many(vectors)?;
+        if !self.vectors.is_empty() {
+            self.fingerprint = Some(fingerprint);
+        }
+        Ok(())
+    }
+
+    /// Searches stored FDEs after checking the query's MUVERA fingerprint.
+    pub fn search_fde(
+        &self,
+        query: &[f32],
+        fingerprint: u64,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        crate::muvera::check_fingerprint(self.fingerprint, fingerprint)?;
+        self.search(query, limit)
+    }
+
     /// Inserts or replaces one vector by external id.
     pub fn insert(&mut self, id: String, vector: Vec<f32>) -> Result<(), String> {
+        self.check_insert(None)?;
         self.validate_vector(&vector)?;
         if self.dimension.is_none() {
             self.dimension = Some(vector.len());
@@ -67,6 +83,19 @@ impl FlatIndex {
 
     /// Inserts or replaces a batch of vectors.
     pub fn insert_many(&mut self, vectors: Vec<(String, Vec<f32>)>) -> Result<(), String> {
+        self.check_insert(None)?;
+        self.store_many(vectors)
+    }
+
+    fn check_insert(&self, fingerprint: Option<u64>) -> Result<(), String> {
+        crate::muvera::check_insert_fingerprint(
+            self.fingerprint,
+            fingerprint,
+            self.vectors.is_empty(),
+        )
+    }
+
+    fn store_many(&mut self, vectors: Vec<(String, Vec<f32>)>) -> Result<(), String> {
         let expected = self
             .dimension
             .or_else(|| vectors.first().map(|(_, vector)| vector.len()));
@@ -89,6 +118,7 @@ impl FlatIndex {
         self.vectors.remove(id);
         if self.vectors.is_empty() {
             self.dimension = None;
+            self.fingerprint = None;
         }
     }
 
@@ -103,24 +133,133 @@ impl FlatIndex {
         let mut hits = BinaryHeap::with_capacity(usize::min(limit, self.vectors.len()));
         for (id, vector) in &self.vectors {
             let raw = crate::distances::compute(self.metric, query, vector)?;
-            let hit = FlatHit {
-                id: id.clone(),
-                raw,
-                rank: crate::distances::rank_value(self.metric, raw),
-            };
-
-            if hits.len() < limit {
-                hits.push(hit);
-            } else if hits.peek().is_some_and(|worst| hit < *worst) {
-                hits.pop();
-                hits.push(hit);
+            push_top_k(
+                &mut hits,
+                Hit {
+                    id: id.clone(),
+                    raw,
+                    rank: crate::distances::rank_value(self.metric, raw),
+                },
+                limit,
+            );
+        }
+
+        Ok(sorted_hits(hits))
+    }
+
+    /// Runs a progressive-prefix (Matryoshka) cascade over stored vectors.
+    ///
+    /// Each stage keeps the best `candidates` rows scored on its prefix length,
+    /// then the survivors are reranked on the full query to return `limit`
+    /// hits. Only the final ids and raw metric values cross the NIF boundary.
+    pub fn funnel_search(
+        &self,
+        query: &[f32],
+        stages: &[usize],
+        candidates: usize,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        if stages.is_empty() {
+            return Err("funnel stages must not be empty".to_string());
+        }
+        if candidates < limit {
+            return Err("candidates must be at least limit".to_string());
+        }
+        validate_vector(query, self.dimension)?;
+        for dimensions in stages {
+            crate::search::validate_prefix_query(query, *dimensions)?;
+        }
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut survivors: Vec<&str> = self.vectors.keys().map(String::as_str).collect();
+        for dimensions in stages {
+            let rows = survivors
+                .iter()
+                .map(|id| (*id, self.vectors[*id].as_slice()));
+            survivors =
+                crate::search::prefix_top_k(rows, query, self.metric, *dimensions, candidates)?
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect();
+        }
+
+        let rows = survivors
+            .iter()
+            .map(|id| (*id, self.vectors[*id].as_slice()));
+        Ok(
+            crate::search::prefix_top_k(rows, query, self.metric, query.len(), limit)?
+                .into_iter()
+                .map(|(id, raw)| (id.to_string(), raw))
+                .collect(),
+        )
+    }
+
+    /// Builds the exact k-nearest-neighbour list of every stored vector.
+    ///
+    /// Rows and columns are scanned in fixed-size blocks so each block of
+    /// stored vectors stays cache-resident while it is compared against every
+    /// other block. A vector never appears in its own neighbour list.
+    pub fn knn_graph(&self, k: usize) -> Result<NeighborGraph, String> {
+        let entries = self.sorted_entries();
+        let mut graph = Vec::with_capacity(entries.len());
+
+        for (block_index, rows) in entries.chunks(KNN_BLOCK_SIZE).enumerate() {
+            let row_offset = block_index * KNN_BLOCK_SIZE;
+            let mut heaps: Vec<BinaryHeap<Hit>> = rows
+                .iter()
+                .map(|_| BinaryHeap::with_capacity(usize::min(k, entries.len())))
+                .collect();
+
+            for (column_block, columns) in entries.chunks(KNN_BLOCK_SIZE).enumerate() {
+                let column_offset = column_block * KNN_BLOCK_SIZE;
+                for (row, ((_, left), heap)) in rows.iter().zip(&mut heaps).enumerate() {
+                    for (column, (id, right)) in columns.iter().enumerate() {
+                        if row_offset + row == column_offset + column {
+                            continue;
+                        }
+                        let raw = crate::distances::compute(self.metric, left, right)?;
+                        push_top_k(
+                            heap,
+                            Hit {
+                                id: (*id).to_string(),
+                                raw,
+                                rank: crate::distances::rank_value(self.metric, raw),
+                            },
+                            k,
+                        );
+                    }
+                }
+            }
+
+            for ((id, _), heap) in rows.iter().zip(heaps) {
+                graph.push(((*id).to_string(), sorted_hits(heap)));
             }
         }
 
-        let mut hits = hits.into_vec();
-        hits.sort();
+        Ok(graph)
+    }
+
+    /// Returns the stored vector for one external id.
+    pub fn get(&self, id: &str) -> Option<&[f32]> {
+        self.vectors.get(id).map(Vec::as_slice)
+    }
+
+    /// Returns the metric this index ranks by.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
 
-        Ok(hits.into_iter().map(|hit| (hit.id, hit.raw)).collect())
+    /// Returns stored entries ordered by external id for deterministic scans.
+    pub(crate) fn sorted_entries(&self) -> Vec<(&str, &[f32])> {
+        let mut entries: Vec<_> = self
+            .vectors
+            .iter()
+            .map(|(id, vector)| (id.as_str(), vector.as_slice()))
+            .collect();
+        entries.sort_unstable_by(|left, right| left.0.cmp(right.0));
+        entries
     }
 
     fn validate_vector(&self, vector: &[f32]) -> Result<(), String> {
@@ -161,6 +300,56 @@ mod tests {
         ]
     }
 
+    #[test]
+    fn fde_searches_require_the_bound_muvera_fingerprint() {
+        let mut index = FlatIndex::new(Metric::InnerProduct);
+        assert!(index
+            .insert_many_fde(
+                vec![("a".into(), vec![1.0, 0.0]), ("b".into(), vec![1.0])],
+                7
+            )
+            .is_err());
+        index.insert_many_fde(Vec::new(), 7).unwrap();
+        assert!(index.search_fde(&[1.0, 0.0], 7, 1).is_err());
+
+        index
+            .insert_many_fde(vec![("a".into(), vec![1.0, 0.0])], 7)
+            .unwrap();
+        index.insert_many_fde(Vec::new(), 7).unwrap();
+        assert!(index.insert_many_fde(Vec::new(), 8).is_err());
+        assert_eq!(
+            index.search_fde(&[1.0, 0.0], 7, 1),
+            Ok(vec![("a".into(), 1.0)])
+        );
+        assert_eq!(
+            index.search_fde(&[1.0, 0.0], 8, 1),
+            Err("muvera fingerprint mismatch".into())
+        );
+
+        index.insert_fde("b".into(), vec![0.0, 1.0], 7).unwrap();
+        assert_eq!(
+            index.insert("c".into(), vec![1.0, 1.0]),
+            Err("index holds muvera fdes; insert with a fingerprint".into())
+        );
+        assert!(index
+            .insert_many(vec![("c".into(), vec![1.0, 1.0])])
+            .is_err());
+        assert!(index.insert_fde("c".into(), vec![1.0, 1.0], 8).is_err());
+
+        index.delete("a");
+        index.delete("b");
+        index
+            .insert_many_fde(vec![("a".into(), vec![1.0, 0.0])], 8)
+            .unwrap();
+
+        let mut plain = FlatIndex::new(Metric::InnerProduct);
+        plain.insert("a".into(), vec![1.0, 0.0]).unwrap();
+        assert_eq!(
+            plain.insert_fde("b".into(), vec![0.0, 1.0], 7),
+            Err("index holds vectors without a muvera fingerprint".into())
+        );
+    }
+
     #[test]
     fn inserts_replaces_deletes_and_returns_stable_top_k() {
         let mut index = FlatIndex::new(Metric::L2);
@@ -281,24 +470,136 @@ mod tests {
     }
 
     #[test]
-    fn heap_hit_equality_and_partial_order_include_the_external_id() {
-        let first = FlatHit {
-            id: "a".into(),
-            raw: 1.0,
-            rank: 1.0,
-        };
-        let equal = FlatHit {
-            id: "a".into(),
-            raw: 99.0,
-            rank: 1.0,
-        };
-        let other_id = FlatHit {
-            id: "b".into(),
-            raw: 1.0,
-            rank: 1.0,
-        };
-        assert_eq!(first, equal);
-        assert_ne!(first, other_id);
-        assert_eq!(first.partial_cmp(&other_id), Some(Ordering::Less));
+    fn funnel_search_matches_chained_vector_top_k_stages() {
+        let vectors: Vec<_> = (0..300)
+            .map(|index| {
+                (
+                    format!("v-{index:03}"),
+                    (0..8)
+                        .map(|dimension| {
+                            ((index * (dimension + 3) * 7 % 41) as f32 - 20.0)
+                                / (dimension + 1) as f32
+                        })
+                        .collect::<Vec<f32>>(),
+                )
+            })
+            .collect();
+        let query: Vec<f32> = (0..8)
+            .map(|dimension| dimension as f32 / 4.0 - 1.0)
+            .collect();
+
+        for metric in [Metric::L2, Metric::Cosine, Metric::InnerProduct] {
+            let mut index = FlatIndex::new(metric);
+            index.insert_many(vectors.clone()).unwrap();
+
+            for stages in [vec![2], vec![2, 4], vec![1, 4, 8]] {
+                let mut expected = vectors.clone();
+                for dimensions in &stages {
+                    let kept = crate::search::vector_top_k(
+                        expected.clone(),
+                        &query,
+                        metric,
+                        *dimensions,
+                        40,
+                    )
+                    .unwrap();
+                    expected = kept
+                        .into_iter()
+                        .map(|(id, _)| (id.clone(), index.vectors[&id].clone()))
+                        .collect();
+                }
+                let expected =
+                    crate::search::vector_top_k(expected, &query, metric, query.len(), 10).unwrap();
+
+                assert_eq!(
+                    index.funnel_search(&query, &stages, 40, 10).unwrap(),
+                    expected,
+                    "metric {metric:?} stages {stages:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn funnel_search_validates_stages_candidates_and_queries() {
+        let mut index = FlatIndex::new(Metric::L2);
+        assert_eq!(index.funnel_search(&[1.0, 0.0], &[1], 5, 2), Ok(vec![]));
+        index
+            .insert_many(vec![
+                ("a".into(), vec![0.0, 5.0]),
+                ("b".into(), vec![0.0, 1.0]),
+                ("c".into(), vec![3.0, 0.0]),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            index.funnel_search(&[0.0, 0.0], &[1], 2, 1).unwrap(),
+            vec![("b".into(), 1.0)]
+        );
+        assert_eq!(index.funnel_search(&[0.0, 0.0], &[1], 2, 0), Ok(vec![]));
+        assert!(index.funnel_search(&[], &[1], 2, 0).is_err());
+        assert!(index.funnel_search(&[f32::NAN, 0.0], &[1], 2, 0).is_err());
+        assert!(index.funnel_search(&[0.0], &[1], 2, 0).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[], 2, 0).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[3], 2, 0).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[], 2, 1).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[0], 2, 1).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[3], 2, 1).is_err());
+        assert!(index.funnel_search(&[0.0, 0.0], &[1], 1, 2).is_err());
+        assert!(index.funnel_search(&[0.0], &[1], 2, 1).is_err());
+        assert!(index.funnel_search(&[f32::NAN, 0.0], &[1], 2, 1).is_err());
+    }
+
+    #[test]
+    fn knn_graph_matches_per_vector_search_and_excludes_self() {
+        let vectors: Vec<_> = (0..600)
+            .map(|index| {
+                (
+                    format!("v-{index:03}"),
+                    vec![
+                        ((index * 37 % 101) as f32 - 50.0) / 9.0,
+                        ((index * 13 % 61) as f32 - 30.0) / 7.0,
+                    ],
+                )
+            })
+            .collect();
+
+        for metric in [Metric::L2, Metric::InnerProduct, Metric::Manhattan] {
+            let mut index = FlatIndex::new(metric);
+            index.insert_many(vectors.clone()).unwrap();
+            let graph = index.knn_graph(5).unwrap();
+
+            assert_eq!(graph.len(), vectors.len());
+            assert!(graph.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            for (id, neighbors) in &graph {
+                let query = &index.vectors[id];
+                let mut expected = index.search(query, 6).unwrap();
+                expected.retain(|(neighbor, _)| neighbor != id);
+                expected.truncate(5);
+                assert_eq!(neighbors, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn knn_graph_handles_empty_indexes_zero_k_and_small_collections() {
+        let mut index = FlatIndex::new(Metric::L2);
+        assert_eq!(index.knn_graph(3), Ok(vec![]));
+
+        index.insert("a".into(), vec![0.0]).unwrap();
+        assert_eq!(index.knn_graph(3), Ok(vec![("a".into(), vec![])]));
+
+        index.insert("b".into(), vec![2.0]).unwrap();
+        assert_eq!(
+            index.knn_graph(3).unwrap(),
+            vec![
+                ("a".into(), vec![("b".into(), 2.0)]),
+                ("b".into(), vec![("a".into(), 2.0)]),
+            ]
+        );
+        assert_eq!(
+            index.knn_graph(0).unwrap(),
+            vec![("a".into(), vec![]), ("b".into(), vec![])]
+        );
     }
 }
@@ -0,0 +1,445 @@
+//! Native fusion of independently ranked result lists.
+//!
+//! Reciprocal Rank Fusion uses only positions, so it can merge lists whose
+//! scores live on unrelated scales. CombSUM and CombMNZ first normalize each
+//! list's scores, then add them with per-list weights. Every input list must
+//! already be ordered best-first. Lists of raw metric values, such as L2
+//! distances from a flat or HNSW search, name their metric so lower-is-better
+//! values are flipped before normalization; other lists must carry
+//! higher-is-better scores.
+
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::distances::Metric;
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Normalization {
+    None,
+    MinMax,
+    ZScore,
+}
+
+impl Normalization {
+    /// Decodes the compact normalization selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::None),
+            1 => Ok(Self::MinMax),
+            2 => Ok(Self::ZScore),
+            _ => Err("unknown normalization".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Combination {
+    Sum,
+    Mnz,
+}
+
+impl Combination {
+    /// Decodes the compact combination selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Sum),
+            1 => Ok(Self::Mnz),
+            _ => Err("unknown combination".to_string()),
+        }
+    }
+}
+
+/// Fuses lists with weighted Reciprocal Rank Fusion: `Σ w / (k + rank)`.
+///
+/// Ranks are 1-based. Repeated ids inside one list keep their best rank.
+pub fn reciprocal_rank(
+    lists: &[Vec<(String, f32)>],
+    weights: Option<&[f32]>,
+    k: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    if !k.is_finite() || k < 0.0 {
+        return Err("k must be a non-negative finite number".to_string());
+    }
+    let weights = validate_weights(lists.len(), weights)?;
+
+    let mut fused: HashMap<&str, f64> = HashMap::new();
+    for (list, weight) in lists.iter().zip(weights) {
+        let mut seen = HashMap::with_capacity(list.len());
+        for (position, (id, _score)) in list.iter().enumerate() {
+            if seen.insert(id.as_str(), ()).is_some() {
+                continue;
+            }
+            let rank = position as f64 + 1.0;
+            *fused.entry(id.as_str()).or_default() += weight / (f64::from(k) + rank);
+        }
+    }
+
+    top_k(fused, limit)
+}
+
+/// Fuses normalized scores with weighted CombSUM or CombMNZ.
+///
+/// CombMNZ multiplies the CombSUM score by the number of lists containing the
+/// id. Repeated ids inside one list keep their first (best) score. A list with
+/// a metric holds raw metric values and is mapped to `-rank_value` first, so
+/// distances rank the same way as similarities.
+pub fn combine_scores(
+    lists: &[Vec<(String, f32)>],
+    weights: Option<&[f32]>,
+    metrics: Option<&[Option<Metric>]>,
+    normalization: Normalization,
+    combination: Combination,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let weights = validate_weights(lists.len(), weights)?;
+    let metrics = match metrics {
+        Some(metrics) if metrics.len() != lists.len() => {
+            return Err("metrics must match list count".to_string());
+        }
+        Some(metrics) => metrics.to_vec(),
+        None => vec![None; lists.len()],
+    };
+
+    let mut fused: HashMap<&str, (f64, usize)> = HashMap::new();
+    for ((list, weight), metric) in lists.iter().zip(weights).zip(metrics) {
+        let mut first_scores: Vec<(&str, f64)> = Vec::with_capacity(list.len());
+        let mut seen = HashMap::with_capacity(list.len());
+        for (id, score) in list {
+            if !score.is_finite() {
+                return Err("scores must be finite".to_string());
+            }
+            if seen.insert(id.as_str(), ()).is_none() {
+                let score = match metric {
+                    Some(metric) => -crate::distances::rank_value(metric, *score),
+                    None => *score,
+                };
+                first_scores.push((id.as_str(), f64::from(score)));
+            }
+        }
+
+        for (id, score) in normalize(first_scores, normalization) {
+            let entry = fused.entry(id).or_default();
+            entry.0 += weight * score;
+            entry.1 += 1;
+        }
+    }
+
+    top_k(
+        fused
+            .into_iter()
+            .map(|(id, (sum, hits))| match combination {
+                Combination::Sum => (id, sum),
+                Combination::Mnz => (id, sum * hits as f64),
+            }),
+        limit,
+    )
+}
+
+fn validate_weights(lists: usize, weights: Option<&[f32]>) -> Result<Vec<f64>, String> {
+    let Some(weights) = weights else {
+        return Ok(vec![1.0; lists]);
+    };
+    if weights.len() != lists {
+        return Err("weights must match list count".to_string());
+    }
+    weights
+        .iter()
+        .map(|weight| {
+            if weight.is_finite() && *weight >= 0.0 {
+                Ok(f64::from(*weight))
+            } else {
+                Err("weights must be non-negative finite numbers".to_string())
+            }
+        })
+        .collect()
+}
+
+/// Rescales one list. Constant lists map to `1.0` under min-max and `0.0`
+/// under z-score so a lone hit still contributes a full min-max score.
+fn normalize(scores: Vec<(&str, f64)>, normalization: Normalization) -> Vec<(&str, f64)> {
+    if scores.is_empty() {
+        return scores;
+    }
+    match normalization {
+        Normalization::None => scores,
+        Normalization::MinMax => {
+            let min = scores.iter().map(|(_, s)| *s).fold(f64::INFINITY, f64::min);
+            let max = scores
+                .iter()
+                .map(|(_, s)| *s)
+                .fold(f64::NEG_INFINITY, f64::max);
+            scores
+                .into_iter()
+                .map(|(id, score)| {
+                    let scaled = if max == min {
+                        1.0
+                    } else {
+                        (score - min) / (max - min)
+                    };
+                    (id, scaled)
+                })
+                .collect()
+        }
+        Normalization::ZScore => {
+            let count = scores.len() as f64;
+            let mean = scores.iter().map(|(_, s)| s).sum::<f64>() / count;
+            let variance = scores
+                .iter()
+                .map(|(_, s)| (s - mean) * (s - mean))
+                .sum::<f64>()
+                / count;
+            let stddev = variance.sqrt();
+            scores
+                .into_iter()
+                .map(|(id, score)| {
+                    let scaled = if stddev == 0.0 {
+                        0.0
+                    } else {
+                        (score - mean) / stddev
+                    };
+                    (id, scaled)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Keeps the best `limit` fused scores with a bounded heap. A score outside
+/// the f32 range fails even if it would not have made the cut.
+fn top_k<'a>(
+    fused: impl IntoIterator<Item = (&'a str, f64)>,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let mut heap = BinaryHeap::new();
+    for (id, score) in fused {
+        let score = score as f32;
+        if !score.is_finite() {
+            return Err("score overflow".to_string());
+        }
+        push_top_k(&mut heap, Hit::scored(id, score), limit);
+    }
+    Ok(sorted_hits(heap)
+        .into_iter()
+        .map(|(id, score)| (id.to_string(), score))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(entries: &[(&str, f32)]) -> Vec<(String, f32)> {
+        entries
+            .iter()
+            .map(|(id, score)| (id.to_string(), *score))
+            .collect()
+    }
+
+    fn assert_close(actual: &[(String, f32)], expected: &[(&str, f32)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for ((id, score), (expected_id, expected_score)) in actual.iter().zip(expected) {
+            assert_eq!(id, expected_id);
+            assert!((score - expected_score).abs() < 1.0e-6, "{actual:?}");
+        }
+    }
+
+    #[test]
+    fn reciprocal_rank_fusion_rewards_agreement_across_lists() {
+        let lists = vec![
+            list(&[("a", 9.0), ("b", 5.0), ("c", 1.0)]),
+            list(&[("b", 0.9), ("c", 0.8), ("d", 0.1)]),
+        ];
+        let fused = reciprocal_rank(&lists, None, 60.0, 10).unwrap();
+        assert_close(
+            &fused,
+            &[
+                ("b", 1.0 / 62.0 + 1.0 / 61.0),
+                ("c", 1.0 / 63.0 + 1.0 / 62.0),
+                ("a", 1.0 / 61.0),
+                ("d", 1.0 / 63.0),
+            ],
+        );
+
+        let weighted = reciprocal_rank(&lists, Some(&[3.0, 1.0]), 0.0, 1).unwrap();
+        assert_close(&weighted, &[("a", 3.0)]);
+        assert_eq!(reciprocal_rank(&lists, None, 60.0, 0), Ok(vec![]));
+        assert_eq!(reciprocal_rank(&[], None, 60.0, 5), Ok(vec![]));
+    }
+
+    #[test]
+    fn repeated_ids_keep_their_best_position_and_ties_order_by_id() {
+        let lists = vec![list(&[("b", 1.0), ("a", 1.0), ("b", 1.0)])];
+        assert_close(
+            &reciprocal_rank(&lists, None, 0.0, 5).unwrap(),
+            &[("b", 1.0), ("a", 0.5)],
+        );
+
+        let tied = vec![list(&[("z", 1.0)]), list(&[("a", 1.0)])];
+        assert_close(
+            &reciprocal_rank(&tied, None, 1.0, 5).unwrap(),
+            &[("a", 0.5), ("z", 0.5)],
+        );
+        assert_close(
+            &reciprocal_rank(&tied, None, 1.0, 1).unwrap(),
+            &[("a", 0.5)],
+        );
+    }
+
+    #[test]
+    fn comb_sum_and_mnz_combine_normalized_scores() {
+        let lists = vec![
+            list(&[("a", 10.0), ("b", 6.0), ("c", 2.0)]),
+            list(&[("c", 0.5), ("d", 0.375), ("b", 0.25)]),
+        ];
+
+        let sum = combine_scores(
+            &lists,
+            None,
+            None,
+            Normalization::MinMax,
+            Combination::Sum,
+            10,
+        )
+        .unwrap();
+        assert_close(&sum, &[("a", 1.0), ("c", 1.0), ("b", 0.5), ("d", 0.5)]);
+
+        let mnz = combine_scores(
+            &lists,
+            None,
+            None,
+            Normalization::MinMax,
+            Combination::Mnz,
+            2,
+        )
+        .unwrap();
+        assert_close(&mnz, &[("c", 2.0), ("a", 1.0)]);
+
+        let raw = combine_scores(
+            &lists,
+            Some(&[1.0, 10.0]),
+            None,
+            Normalization::None,
+            Combination::Sum,
+            1,
+        )
+        .unwrap();
+        assert_close(&raw, &[("a", 10.0)]);
+    }
+
+    #[test]
+    fn z_score_normalization_centres_each_list() {
+        let lists = vec![
+            list(&[("a", 3.0), ("b", 2.0), ("c", 1.0)]),
+            list(&[("x", 7.0)]),
+        ];
+        let fused = combine_scores(
+            &lists,
+            None,
+            None,
+            Normalization::ZScore,
+            Combination::Sum,
+            10,
+        )
+        .unwrap();
+        let deviation = (2.0f32 / 3.0).sqrt();
+        assert_close(
+            &fused,
+            &[
+                ("a", 1.0 / deviation),
+                ("b", 0.0),
+                ("x", 0.0),
+                ("c", -1.0 / deviation),
+            ],
+        );
+
+        let single = vec![list(&[("only", 4.0)])];
+        assert_close(
+            &combine_scores(
+                &single,
+                None,
+                None,
+                Normalization::MinMax,
+                Combination::Sum,
+                1,
+            )
+            .unwrap(),
+            &[("only", 1.0)],
+        );
+    }
+
+    #[test]
+    fn distance_lists_are_flipped_before_fusing_with_bm25_scores() {
+        let lists = vec![
+            list(&[("a", 0.0), ("b", 2.0), ("c", 4.0)]),
+            list(&[("a", 7.5), ("c", 3.0), ("b", 1.5)]),
+        ];
+        let metrics = [Some(Metric::L2), None];
+
+        let fused = combine_scores(
+            &lists,
+            None,
+            Some(&metrics),
+            Normalization::MinMax,
+            Combination::Sum,
+            10,
+        )
+        .unwrap();
+        assert_close(&fused, &[("a", 2.0), ("b", 0.5), ("c", 0.25)]);
+
+        let unflipped = combine_scores(
+            &lists,
+            None,
+            None,
+            Normalization::MinMax,
+            Combination::Sum,
+            1,
+        )
+        .unwrap();
+        assert_close(&unflipped, &[("c", 1.25)]);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters_and_scores() {
+        let lists = vec![list(&[("a", 1.0)])];
+        assert!(reciprocal_rank(&lists, None, -1.0, 1).is_err());
+        assert!(reciprocal_rank(&lists, None, f32::NAN, 1).is_err());
+        assert!(reciprocal_rank(&lists, Some(&[]), 60.0, 1).is_err());
+        assert!(reciprocal_rank(&lists, Some(&[-1.0]), 60.0, 1).is_err());
+        assert!(reciprocal_rank(&lists, Some(&[f32::INFINITY]), 60.0, 1).is_err());
+
+        let bad = vec![list(&[("a", f32::NAN)])];
+        assert!(
+            combine_scores(&bad, None, None, Normalization::None, Combination::Sum, 1).is_err()
+        );
+        let overflow = vec![list(&[("a", f32::MAX)]), list(&[("a", f32::MAX)])];
+        assert_eq!(
+            combine_scores(
+                &overflow,
+                None,
+                None,
+                Normalization::None,
+                Combination::Sum,
+                1
+            ),
+            Err("score overflow".into())
+        );
+
+        assert_eq!(
+            combine_scores(
+                &lists,
+                None,
+                Some(&[]),
+                Normalization::None,
+                Combination::Sum,
+                1
+            ),
+            Err("metrics must match list count".into())
+        );
+
+        assert_eq!(Normalization::from_code(2), Ok(Normalization::ZScore));
+        assert!(Normalization::from_code(3).is_err());
+        assert_eq!(Combination::from_code(1), Ok(Combination::Mnz));
+        assert!(Combination::from_code(2).is_err());
+    }
+}
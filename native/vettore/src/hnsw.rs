@@ -10,6 +10,7 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::sync::RwLock;
 
 use crate::distances::Metric;
+use crate::flat::NeighborGraph;
 
 #[derive(Clone, Copy)]
 pub struct HnswParams {
@@ -130,6 +131,8 @@ pub struct HnswIndex {
     entry: Option<usize>,
     next: usize,
     dimension: Option<usize>,
+    /// MUVERA config fingerprint of stored FDEs, if any were written natively.
+    fingerprint: Option<u64>,
 }
 
 impl HnswIndex {
@@ -145,11 +148,60 @@ impl HnswIndex {
             entry: None,
             next: 0,
             dimension: None,
+            fingerprint: None,
         })
     }
 
+    /// Inserts or replaces one MUVERA FDE written under a config fingerprint.
+    pub fn insert_fde(
+        &mut self,
+        external_id: String,
+        vector: Vec<f32>,
+        fingerprint: u64,
+    ) -> Result<(), String> {
+        self.insert_many_fde(vec![(external_id, vector)], fingerprint)
+    }
+
+    /// Inserts MUVERA FDEs, binding their config fingerprint once they are stored.
+    pub fn insert_many_fde(
+        &mut self,
+        vectors: Vec<(String, Vec<f32>)>,
+        fingerprint: u64,
+    ) -> Result<(), String> {
+        self.check_insert(Some(fingerprint))?;
+        self.store_many(vectors)?;
+        if !self.nodes.is_empty() {
+            self.fingerprint = Some(fingerprint);
+        }
+        Ok(())
+    }
+
+    /// Searches stored FDEs after checking the query's MUVERA fingerprint.
+    pub fn search_fde(
+        &self,
+        query: &[f32],
+        fingerprint: u64,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        crate::muvera::check_fingerprint(self.fingerprint, fingerprint)?;
+        self.search(query, limit)
+    }
+
     /// Inserts or replaces one external id in the graph.
     pub fn insert(&mut self, external_id: String, vector: Vec<f32>) -> Result<(), String> {
+        self.check_insert(None)?;
+        self.store(external_id, vector)
+    }
+
+    fn check_insert(&self, fingerprint: Option<u64>) -> Result<(), String> {
+        crate::muvera::check_insert_fingerprint(
+            self.fingerprint,
+            fingerprint,
+            self.nodes.is_empty(),
+        )
+    }
+
+    fn store(&mut self, external_id: String, vector: Vec<f32>) -> Result<(), String> {
         validate_vector(&vector, self.dimension)?;
 
         if self.external_to_internal.contains_key(&external_id) {
@@ -247,6 +299,11 @@ impl HnswIndex {
     /// Validates a whole batch before mutating the graph, then inserts it while
     /// holding a single resource lock at the NIF boundary.
     pub fn insert_many(&mut self, vectors: Vec<(String, Vec<f32>)>) -> Result<(), String> {
+        self.check_insert(None)?;
+        self.store_many(vectors)
+    }
+
+    fn store_many(&mut self, vectors: Vec<(String, Vec<f32>)>) -> Result<(), String> {
         let expected = self
             .dimension
             .or_else(|| vectors.first().map(|(_, vector)| vector.len()));
@@ -254,7 +311,7 @@ impl HnswIndex {
             validate_vector(vector, expected)?;
         }
         for (id, vector) in vectors {
-            self.insert(id, vector)?;
+            self.store(id, vector)?;
         }
         Ok(())
     }
@@ -285,6 +342,7 @@ impl HnswIndex {
         }
         if self.nodes.is_empty() {
             self.dimension = None;
+            self.fingerprint = None;
         }
     }
 
@@ -332,6 +390,76 @@ impl HnswIndex {
             .collect()
     }
 
+    /// Returns every reachable vector whose raw metric value passes `threshold`.
+    ///
+    /// The graph search is widened geometrically until the worst returned hit
+    /// falls outside the threshold, so dense duplicate groups are not cut off
+    /// at `ef_search` results.
+    pub fn range_search(
+        &self,
+        query: &[f32],
+        threshold: f32,
+    ) -> Result<Vec<(String, f32)>, String> {
+        let bound = crate::distances::rank_value(self.metric, threshold);
+        let mut limit = usize::max(self.params.ef_search, 1);
+        loop {
+            let mut hits = self.search(query, limit)?;
+            let exhausted = hits.len() < limit
+                || hits.last().is_some_and(|(_, raw)| {
+                    crate::distances::rank_value(self.metric, *raw) > bound
+                });
+            if exhausted || limit >= self.nodes.len() {
+                hits.retain(|(_, raw)| crate::distances::rank_value(self.metric, *raw) <= bound);
+                return Ok(hits);
+            }
+            limit = limit.saturating_mul(2);
+        }
+    }
+
+    /// Returns the stored vector for one external id.
+    pub fn get(&self, external_id: &str) -> Option<&[f32]> {
+        self.external_to_internal
+            .get(external_id)
+            .and_then(|internal_id| self.nodes.get(internal_id))
+            .map(|node| node.vector.as_slice())
+    }
+
+    /// Returns the metric this graph ranks by.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Returns stored entries ordered by external id for deterministic scans.
+    pub(crate) fn sorted_entries(&self) -> Vec<(&str, &[f32])> {
+        let mut entries: Vec<_> = self
+            .nodes
+            .values()
+            .map(|node| (node.external_id.as_str(), node.vector.as_slice()))
+            .collect();
+        entries.sort_unstable_by(|left, right| left.0.cmp(right.0));
+        entries
+    }
+
+    /// Builds an approximate k-nearest-neighbour list for every stored vector.
+    ///
+    /// Each node queries the graph with its own vector, so recall follows
+    /// `ef_search`. The node itself is dropped from its own neighbour list.
+    pub fn knn_graph(&self, k: usize) -> Result<NeighborGraph, String> {
+        let entries = self.sorted_entries();
+        let mut graph = Vec::with_capacity(entries.len());
+        for (id, vector) in entries {
+            let mut neighbors = if k == 0 {
+                Vec::new()
+            } else {
+                self.search(vector, k.saturating_add(1))?
+            };
+            neighbors.retain(|(neighbor, _)| neighbor != id);
+            neighbors.truncate(k);
+            graph.push((id.to_string(), neighbors));
+        }
+        Ok(graph)
+    }
+
     /// Descends one graph layer until no neighbor improves the rank distance.
     fn greedy_closest(
         &self,
@@ -607,6 +735,34 @@ mod tests {
         assert_eq!(index.dimension, None);
     }
 
+    #[test]
+    fn fde_inserts_keep_one_muvera_fingerprint_per_graph() {
+        let mut index = HnswIndex::new(Metric::InnerProduct, params()).unwrap();
+        assert!(index
+            .insert_many_fde(
+                vec![("a".into(), vec![1.0]), ("b".into(), vec![1.0, 0.0])],
+                7
+            )
+            .is_err());
+        assert_eq!(
+            index.search_fde(&[1.0, 0.0], 7, 1),
+            Err("index has no muvera fingerprint".into())
+        );
+
+        index.insert_fde("a".into(), vec![1.0, 0.0], 7).unwrap();
+        index.insert_fde("a".into(), vec![0.0, 1.0], 7).unwrap();
+        assert_eq!(
+            index.search_fde(&[0.0, 1.0], 7, 1),
+            Ok(vec![("a".into(), 1.0)])
+        );
+        assert!(index.insert("b".into(), vec![1.0, 0.0]).is_err());
+        assert!(index.insert_fde("b".into(), vec![1.0, 0.0], 8).is_err());
+
+        index.delete("a");
+        index.insert("b".into(), vec![1.0, 0.0]).unwrap();
+        assert!(index.insert_fde("c".into(), vec![1.0, 0.0], 7).is_err());
+    }
+
     #[test]
     fn rejects_non_finite_and_mismatched_vectors() {
         let mut index = HnswIndex::new(Metric::Cosine, params()).unwrap();
@@ -783,6 +939,50 @@ mod tests {
         assert_eq!(index.search(&[0.0], 0), Ok(vec![]));
     }
 
+    #[test]
+    fn knn_graph_recalls_exact_grid_neighbours_and_excludes_self() {
+        let mut index = HnswIndex::new(Metric::L2, params()).unwrap();
+        let vectors: Vec<_> = (0..12)
+            .flat_map(|x| {
+                (0..12).map(move |y| (format!("{x:02}-{y:02}"), vec![x as f32, y as f32 * 1.01]))
+            })
+            .collect();
+        index.insert_many(vectors.clone()).unwrap();
+
+        let graph = index.knn_graph(4).unwrap();
+        assert_eq!(graph.len(), vectors.len());
+        assert!(graph.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        for ((id, neighbors), (expected_id, vector)) in graph.iter().zip(&vectors) {
+            assert_eq!(id, expected_id);
+            let mut expected: Vec<_> = vectors
+                .iter()
+                .filter(|(other, _)| other != id)
+                .map(|(other, candidate)| {
+                    (
+                        other.clone(),
+                        crate::distances::compute(Metric::L2, vector, candidate).unwrap(),
+                    )
+                })
+                .collect();
+            expected.sort_by(|left, right| {
+                left.1
+                    .total_cmp(&right.1)
+                    .then_with(|| left.0.cmp(&right.0))
+            });
+            expected.truncate(4);
+            assert_eq!(neighbors, &expected);
+        }
+
+        assert!(index
+            .knn_graph(0)
+            .unwrap()
+            .iter()
+            .all(|(_, neighbors)| neighbors.is_empty()));
+        let empty = HnswIndex::new(Metric::L2, params()).unwrap();
+        assert_eq!(empty.knn_graph(3), Ok(vec![]));
+    }
+
     #[test]
     fn deterministic_level_assignment_is_bounded_and_seedless() {
         let first = HnswIndex::new(Metric::L2, params()).unwrap();
@@ -0,0 +1,516 @@
+//! Native k-means clustering.
+//!
+//! Seeding uses k-means++ driven by the same deterministic hash as MUVERA, so
+//! a fixed seed always yields the same centroids. Full-batch Lloyd iterations
+//! and Sculley-style mini-batch updates share one assignment kernel. Cosine
+//! runs spherical k-means over unit vectors; L2 runs the classic variant.
+
+use crate::distances::Metric;
+use crate::flat::FlatIndex;
+use crate::muvera::hash4;
+
+#[derive(Clone, Copy)]
+pub struct Params {
+    pub k: usize,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+    pub seed: u64,
+    pub batch_size: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Model {
+    pub centroids: Vec<Vec<f32>>,
+    pub assignments: Vec<usize>,
+    pub inertia: f64,
+    pub iterations: usize,
+}
+
+/// Distinct hash streams so seeding and mini-batch sampling never correlate.
+const SEED_STREAM: u64 = 0x6B6D_6561_6E73_2B2B;
+const BATCH_STREAM: u64 = 0x6D69_6E69_6261_7463;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Geometry {
+    Euclidean,
+    Spherical,
+}
+
+impl Geometry {
+    fn for_metric(metric: Metric) -> Result<Self, String> {
+        match metric {
+            Metric::L2 | Metric::L2Squared => Ok(Self::Euclidean),
+            Metric::Cosine => Ok(Self::Spherical),
+            _ => Err("unsupported kmeans metric".to_string()),
+        }
+    }
+
+    /// Per-point cost minimized by the clustering: squared L2 or `1 - cos`.
+    fn cost(self, point: &[f32], centroid: &[f32]) -> f64 {
+        match self {
+            Self::Euclidean => f64::from(crate::distances::l2_squared(point, centroid)),
+            Self::Spherical => 1.0 - f64::from(crate::distances::dot(point, centroid)),
+        }
+    }
+}
+
+/// Clusters validated vectors and returns centroids plus per-vector labels.
+pub fn fit(vectors: &[&[f32]], metric: Metric, params: Params) -> Result<Model, String> {
+    let geometry = Geometry::for_metric(metric)?;
+    validate(vectors, params)?;
+
+    let normalized;
+    let points: Vec<&[f32]> = if geometry == Geometry::Spherical {
+        normalized = vectors
+            .iter()
+            .map(|vector| crate::distances::normalize_l2(vector.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        normalized.iter().map(Vec::as_slice).collect()
+    } else {
+        vectors.to_vec()
+    };
+
+    let mut centroids = seed_centroids(&points, geometry, params);
+    let mut assignments = vec![0usize; points.len()];
+    let mut costs = vec![0.0f64; points.len()];
+    let iterations = match params.batch_size {
+        None => lloyd(
+            &points,
+            geometry,
+            params,
+            &mut centroids,
+            &mut assignments,
+            &mut costs,
+        ),
+        Some(batch_size) => mini_batch(&points, geometry, params, batch_size, &mut centroids),
+    };
+
+    // Reseeding after the final assignment keeps every returned cluster
+    // non-empty even when duplicate points tie on the same centroid.
+    assign(&points, geometry, &centroids, &mut assignments, &mut costs);
+    reseed_empty(&points, &mut centroids, &mut assignments, &mut costs);
+
+    Ok(Model {
+        centroids,
+        assignments,
+        inertia: costs.iter().sum(),
+        iterations,
+    })
+}
+
+/// Clusters every vector stored in a flat index without copying it to Erlang.
+///
+/// Assignments are paired with external ids in ascending id order.
+pub fn fit_flat(index: &FlatIndex, params: Params) -> Result<(Model, Vec<String>), String> {
+    let entries = index.sorted_entries();
+    let vectors: Vec<&[f32]> = entries.iter().map(|(_, vector)| *vector).collect();
+    let model = fit(&vectors, index.metric(), params)?;
+    Ok((
+        model,
+        entries.into_iter().map(|(id, _)| id.to_string()).collect(),
+    ))
+}
+
+fn validate(vectors: &[&[f32]], params: Params) -> Result<(), String> {
+    if params.k == 0 {
+        return Err("k must be positive".to_string());
+    }
+    if params.max_iterations == 0 {
+        return Err("max_iterations must be positive".to_string());
+    }
+    if !params.tolerance.is_finite() || params.tolerance < 0.0 {
+        return Err("tolerance must be a non-negative finite number".to_string());
+    }
+    if params.batch_size == Some(0) {
+        return Err("batch_size must be positive".to_string());
+    }
+    let Some(first) = vectors.first() else {
+        return Err("empty vectors".to_string());
+    };
+    if first.is_empty() {
+        return Err("vectors must not be empty".to_string());
+    }
+    if params.k > vectors.len() {
+        return Err("k exceeds vector count".to_string());
+    }
+    for vector in vectors {
+        if vector.len() != first.len() {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+    }
+    Ok(())
+}
+
+/// Picks initial centroids with k-means++ D² sampling.
+fn seed_centroids(points: &[&[f32]], geometry: Geometry, params: Params) -> Vec<Vec<f32>> {
+    let first = (hash4(params.seed, SEED_STREAM, 0, 0) % points.len() as u64) as usize;
+    let mut chosen = vec![first];
+    let mut nearest: Vec<f64> = points
+        .iter()
+        .map(|point| geometry.cost(point, points[first]).max(0.0))
+        .collect();
+
+    while chosen.len() < params.k {
+        let total: f64 = nearest.iter().sum();
+        let next = if total > 0.0 {
+            let target = unit(hash4(params.seed, SEED_STREAM, chosen.len() as u64, 1)) * total;
+            let mut cumulative = 0.0;
+            nearest
+                .iter()
+                .position(|cost| {
+                    cumulative += cost;
+                    *cost > 0.0 && cumulative >= target
+                })
+                .unwrap_or_else(|| nearest.iter().rposition(|cost| *cost > 0.0).unwrap_or(0))
+        } else {
+            // Every remaining point duplicates a chosen centroid; fall back to
+            // the first unused index so k distinct seeds are still produced.
+            (0..points.len())
+                .find(|index| !chosen.contains(index))
+                .unwrap_or(0)
+        };
+
+        chosen.push(next);
+        for (cost, point) in nearest.iter_mut().zip(points) {
+            *cost = cost.min(geometry.cost(point, points[next]).max(0.0));
+        }
+    }
+
+    chosen
+        .into_iter()
+        .map(|index| points[index].to_vec())
+        .collect()
+}
+
+/// Runs full-batch Lloyd iterations until centroids move less than `tolerance`.
+fn lloyd(
+    points: &[&[f32]],
+    geometry: Geometry,
+    params: Params,
+    centroids: &mut [Vec<f32>],
+    assignments: &mut [usize],
+    costs: &mut [f64],
+) -> usize {
+    let dimension = points[0].len();
+    for iteration in 1..=params.max_iterations {
+        assign(points, geometry, centroids, assignments, costs);
+        reseed_empty(points, centroids, assignments, costs);
+
+        let mut sums = vec![vec![0.0f64; dimension]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for (point, cluster) in points.iter().zip(assignments.iter()) {
+            counts[*cluster] += 1;
+            for (sum, value) in sums[*cluster].iter_mut().zip(point.iter()) {
+                *sum += f64::from(*value);
+            }
+        }
+
+        let mut shift = 0.0f64;
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count == 0 {
+                continue;
+            }
+            let mean: Vec<f64> = sum.into_iter().map(|value| value / count as f64).collect();
+            let next = finish_centroid(&mean, geometry, centroid);
+            shift = shift.max(f64::from(crate::distances::l2_squared(centroid, &next)));
+            *centroid = next;
+        }
+
+        if shift <= params.tolerance {
+            return iteration;
+        }
+    }
+    params.max_iterations
+}
+
+/// Runs mini-batch updates with per-centroid learning rates `1 / count`.
+fn mini_batch(
+    points: &[&[f32]],
+    geometry: Geometry,
+    params: Params,
+    batch_size: usize,
+    centroids: &mut [Vec<f32>],
+) -> usize {
+    let mut counts = vec![0usize; centroids.len()];
+    let mut working: Vec<Vec<f64>> = centroids
+        .iter()
+        .map(|centroid| centroid.iter().map(|value| f64::from(*value)).collect())
+        .collect();
+
+    for iteration in 1..=params.max_iterations {
+        let previous = centroids.to_vec();
+        for sample in 0..batch_size {
+            let index = (hash4(params.seed, BATCH_STREAM, iteration as u64, sample as u64)
+                % points.len() as u64) as usize;
+            let point = points[index];
+            let cluster = nearest(point, geometry, centroids).0;
+
+            counts[cluster] += 1;
+            let rate = 1.0 / counts[cluster] as f64;
+            for (slot, value) in working[cluster].iter_mut().zip(point.iter()) {
+                *slot += (f64::from(*value) - *slot) * rate;
+            }
+            centroids[cluster] = finish_centroid(&working[cluster], geometry, &centroids[cluster]);
+        }
+
+        let shift = previous
+            .iter()
+            .zip(centroids.iter())
+            .map(|(before, after)| f64::from(crate::distances::l2_squared(before, after)))
+            .fold(0.0f64, f64::max);
+        if shift <= params.tolerance {
+            return iteration;
+        }
+    }
+    params.max_iterations
+}
+
+/// Converts an f64 running mean into a stored centroid for the geometry.
+fn finish_centroid(mean: &[f64], geometry: Geometry, previous: &[f32]) -> Vec<f32> {
+    match geometry {
+        Geometry::Euclidean => mean.iter().map(|value| *value as f32).collect(),
+        Geometry::Spherical => {
+            let norm = mean.iter().map(|value| value * value).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                previous.to_vec()
+            } else {
+                mean.iter().map(|value| (value / norm) as f32).collect()
+            }
+        }
+    }
+}
+
+fn assign(
+    points: &[&[f32]],
+    geometry: Geometry,
+    centroids: &[Vec<f32>],
+    assignments: &mut [usize],
+    costs: &mut [f64],
+) {
+    for ((point, assignment), cost) in points.iter().zip(assignments).zip(costs) {
+        (*assignment, *cost) = nearest(point, geometry, centroids);
+    }
+}
+
+/// Returns the closest centroid; ties resolve to the lower cluster index.
+fn nearest(point: &[f32], geometry: Geometry, centroids: &[Vec<f32>]) -> (usize, f64) {
+    let mut best = (0usize, f64::INFINITY);
+    for (cluster, centroid) in centroids.iter().enumerate() {
+        let cost = geometry.cost(point, centroid).max(0.0);
+        if cost < best.1 {
+            best = (cluster, cost);
+        }
+    }
+    best
+}
+
+/// Moves empty centroids onto the worst-served points, one point per cluster.
+fn reseed_empty(
+    points: &[&[f32]],
+    centroids: &mut [Vec<f32>],
+    assignments: &mut [usize],
+    costs: &mut [f64],
+) {
+    let mut sizes = vec![0usize; centroids.len()];
+    for cluster in assignments.iter() {
+        sizes[*cluster] += 1;
+    }
+
+    for cluster in 0..centroids.len() {
+        if sizes[cluster] != 0 {
+            continue;
+        }
+        let donor = (0..points.len())
+            .filter(|index| sizes[assignments[*index]] > 1)
+            .max_by(|left, right| costs[*left].total_cmp(&costs[*right]).then(right.cmp(left)));
+        let Some(donor) = donor else {
+            break;
+        };
+
+        sizes[assignments[donor]] -= 1;
+        sizes[cluster] = 1;
+        assignments[donor] = cluster;
+        costs[donor] = 0.0;
+        centroids[cluster] = points[donor].to_vec();
+    }
+}
+
+fn unit(hash: u64) -> f64 {
+    hash as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(k: usize) -> Params {
+        Params {
+            k,
+            max_iterations: 50,
+            tolerance: 1.0e-8,
+            seed: 7,
+            batch_size: None,
+        }
+    }
+
+    fn blobs() -> Vec<Vec<f32>> {
+        let centres = [[0.0, 0.0], [10.0, 10.0], [-10.0, 10.0]];
+        (0..90)
+            .map(|index| {
+                let centre = centres[index % 3];
+                let jitter = ((index * 37 % 17) as f32 - 8.0) / 16.0;
+                vec![centre[0] + jitter, centre[1] - jitter / 2.0]
+            })
+            .collect()
+    }
+
+    fn slices(vectors: &[Vec<f32>]) -> Vec<&[f32]> {
+        vectors.iter().map(Vec::as_slice).collect()
+    }
+
+    #[test]
+    fn lloyd_recovers_separated_blobs_deterministically() {
+        let vectors = blobs();
+        let model = fit(&slices(&vectors), Metric::L2, params(3)).unwrap();
+        let again = fit(&slices(&vectors), Metric::L2, params(3)).unwrap();
+
+        assert_eq!(model.assignments, again.assignments);
+        assert_eq!(model.centroids, again.centroids);
+        assert!(model.iterations >= 1 && model.iterations <= 50);
+        for (index, cluster) in model.assignments.iter().enumerate() {
+            assert_eq!(*cluster, model.assignments[index % 3]);
+        }
+        let mut distinct = model.assignments[..3].to_vec();
+        distinct.sort_unstable();
+        assert_eq!(distinct, vec![0, 1, 2]);
+        assert!(model.inertia < 90.0 * 0.5);
+    }
+
+    #[test]
+    fn mini_batch_converges_to_the_same_partition_on_easy_data() {
+        let vectors = blobs();
+        let full = fit(&slices(&vectors), Metric::L2, params(3)).unwrap();
+        let batch = fit(
+            &slices(&vectors),
+            Metric::L2,
+            Params {
+                batch_size: Some(16),
+                ..params(3)
+            },
+        )
+        .unwrap();
+
+        for left in 0..vectors.len() {
+            for right in 0..vectors.len() {
+                assert_eq!(
+                    full.assignments[left] == full.assignments[right],
+                    batch.assignments[left] == batch.assignments[right]
+                );
+            }
+        }
+        assert!(batch.inertia <= full.inertia * 1.5);
+    }
+
+    #[test]
+    fn cosine_clusters_by_direction_and_returns_unit_centroids() {
+        let vectors: Vec<Vec<f32>> = (1..=20)
+            .map(|scale| {
+                let scale = scale as f32;
+                if (scale as usize).is_multiple_of(2) {
+                    vec![scale, 0.1]
+                } else {
+                    vec![0.1, scale * 3.0]
+                }
+            })
+            .collect();
+        let model = fit(&slices(&vectors), Metric::Cosine, params(2)).unwrap();
+
+        for centroid in &model.centroids {
+            let norm = centroid.iter().map(|value| value * value).sum::<f32>();
+            assert!((norm - 1.0).abs() < 1.0e-5);
+        }
+        for (index, cluster) in model.assignments.iter().enumerate() {
+            assert_eq!(*cluster, model.assignments[index % 2]);
+        }
+        assert_ne!(model.assignments[0], model.assignments[1]);
+    }
+
+    #[test]
+    fn duplicate_points_never_leave_clusters_empty() {
+        let vectors = vec![vec![1.0, 1.0]; 4]
+            .into_iter()
+            .chain([vec![5.0, 5.0]])
+            .collect::<Vec<_>>();
+        for batch_size in [None, Some(2)] {
+            let model = fit(
+                &slices(&vectors),
+                Metric::L2,
+                Params {
+                    batch_size,
+                    ..params(3)
+                },
+            )
+            .unwrap();
+            let mut sizes = [0usize; 3];
+            for cluster in &model.assignments {
+                sizes[*cluster] += 1;
+            }
+            assert!(sizes.iter().all(|size| *size > 0), "{sizes:?}");
+        }
+    }
+
+    #[test]
+    fn fits_directly_from_a_flat_index_in_id_order() {
+        let mut index = FlatIndex::new(Metric::L2);
+        index
+            .insert_many(vec![
+                ("c".into(), vec![10.0]),
+                ("a".into(), vec![0.0]),
+                ("b".into(), vec![0.5]),
+            ])
+            .unwrap();
+        let (model, ids) = fit_flat(&index, params(2)).unwrap();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(model.assignments[0], model.assignments[1]);
+        assert_ne!(model.assignments[0], model.assignments[2]);
+
+        assert_eq!(
+            fit_flat(&FlatIndex::new(Metric::InnerProduct), params(1)).unwrap_err(),
+            "unsupported kmeans metric"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_parameters_and_vectors() {
+        let vectors = blobs();
+        let points = slices(&vectors);
+        for invalid in [
+            Params { k: 0, ..params(1) },
+            Params { k: 91, ..params(1) },
+            Params {
+                max_iterations: 0,
+                ..params(1)
+            },
+            Params {
+                tolerance: f64::NAN,
+                ..params(1)
+            },
+            Params {
+                tolerance: -1.0,
+                ..params(1)
+            },
+            Params {
+                batch_size: Some(0),
+                ..params(1)
+            },
+        ] {
+            assert!(fit(&points, Metric::L2, invalid).is_err());
+        }
+        assert!(fit(&[], Metric::L2, params(1)).is_err());
+        assert!(fit(&[&[]], Metric::L2, params(1)).is_err());
+        assert!(fit(&[&[1.0], &[1.0, 2.0]], Metric::L2, params(1)).is_err());
+        assert!(fit(&[&[f32::NAN]], Metric::L2, params(1)).is_err());
+        assert!(fit(&points, Metric::Manhattan, params(1)).is_err());
+    }
+}
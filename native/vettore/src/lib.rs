@@ -1,9 +1,27 @@
 #![allow(non_local_definitions)]
 
+mod aggregate;
+mod anomaly;
+mod binary;
+mod classifier;
+mod dedup;
 mod distances;
 mod flat;
+mod fusion;
 mod hnsw;
+mod kmeans;
+mod mmr;
 mod multi_vector;
+mod multi_vector_index;
 mod muvera;
+mod muvera_index;
 pub mod nifs;
+mod pca;
+mod plaid;
+mod quantization;
+mod rotation;
 mod search;
+mod sparse;
+mod stats;
+mod text;
+mod token_pooling;
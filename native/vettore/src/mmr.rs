@@ -0,0 +1,319 @@
+//! Native maximal marginal relevance (MMR) reranking.
+//!
+//! Each step selects the candidate maximizing
+//! `lambda * relevance - (1 - lambda) * max_similarity_to_selected`. The
+//! maximum similarity of every remaining candidate is cached and refreshed
+//! only against the newly selected item, so a rerank costs `O(n * limit)`
+//! metric calls instead of `O(n * limit²)`. Ties keep the candidate that
+//! appeared first, matching `Vettore.Distance.mmr_rerank/5`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::distances::Metric;
+use crate::flat::FlatIndex;
+use crate::hnsw::HnswIndex;
+
+/// Reranks candidates whose vectors are supplied alongside them.
+pub fn rerank(
+    candidates: Vec<(String, f32)>,
+    vectors: Vec<(String, Vec<f32>)>,
+    metric: Metric,
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let mut by_id = HashMap::with_capacity(vectors.len());
+    let mut dimension = None;
+    for (id, vector) in &vectors {
+        if vector.is_empty() {
+            return Err("vectors must not be empty".to_string());
+        }
+        if *dimension.get_or_insert(vector.len()) != vector.len() {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+        if by_id.insert(id.as_str(), vector.as_slice()).is_some() {
+            return Err("duplicate id".to_string());
+        }
+    }
+
+    let scored = candidates
+        .iter()
+        .map(|(id, relevance)| {
+            by_id
+                .get(id.as_str())
+                .map(|vector| (id.as_str(), *relevance, *vector))
+                .ok_or_else(|| "missing candidate vector".to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    select(&scored, metric, lambda, limit)
+}
+
+/// Reranks candidates using vectors already held by a flat index.
+///
+/// Candidates whose ids are no longer stored are skipped, mirroring how index
+/// search results ignore stale native ids.
+pub fn rerank_flat(
+    index: &FlatIndex,
+    candidates: &[(String, f32)],
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let scored: Vec<_> = candidates
+        .iter()
+        .filter_map(|(id, relevance)| {
+            index
+                .get(id)
+                .map(|vector| (id.as_str(), *relevance, vector))
+        })
+        .collect();
+    select(&scored, index.metric(), lambda, limit)
+}
+
+/// Reranks candidates using vectors already held by an HNSW graph.
+pub fn rerank_hnsw(
+    index: &HnswIndex,
+    candidates: &[(String, f32)],
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let scored: Vec<_> = candidates
+        .iter()
+        .filter_map(|(id, relevance)| {
+            index
+                .get(id)
+                .map(|vector| (id.as_str(), *relevance, vector))
+        })
+        .collect();
+    select(&scored, index.metric(), lambda, limit)
+}
+
+/// Greedily selects up to `limit` candidates and returns ids with relevance.
+fn select(
+    candidates: &[(&str, f32, &[f32])],
+    metric: Metric,
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    if !lambda.is_finite() || !(0.0..=1.0).contains(&lambda) {
+        return Err("lambda must be within 0.0..=1.0".to_string());
+    }
+    let mut seen = HashSet::with_capacity(candidates.len());
+    for (id, relevance, _) in candidates {
+        if !relevance.is_finite() {
+            return Err("relevance must be finite".to_string());
+        }
+        if !seen.insert(*id) {
+            return Err("duplicate id".to_string());
+        }
+    }
+
+    let lambda = f64::from(lambda);
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+    let mut redundancy: Vec<Option<f64>> = vec![None; candidates.len()];
+    let mut selected = Vec::with_capacity(usize::min(limit, candidates.len()));
+
+    while selected.len() < limit && !remaining.is_empty() {
+        let mut best: Option<(usize, f64)> = None;
+        for (position, candidate) in remaining.iter().enumerate() {
+            let score = lambda * f64::from(candidates[*candidate].1)
+                - (1.0 - lambda) * redundancy[*candidate].unwrap_or(0.0);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((position, score));
+            }
+        }
+        let Some((position, _)) = best else {
+            break;
+        };
+
+        let chosen = remaining.remove(position);
+        let (id, relevance, vector) = candidates[chosen];
+        for candidate in &remaining {
+            let similarity = similarity(metric, candidates[*candidate].2, vector)?;
+            let cached = &mut redundancy[*candidate];
+            *cached = Some(cached.map_or(similarity, |maximum| maximum.max(similarity)));
+        }
+        selected.push((id.to_string(), relevance));
+    }
+
+    Ok(selected)
+}
+
+fn similarity(metric: Metric, left: &[f32], right: &[f32]) -> Result<f64, String> {
+    let raw = if metric == Metric::Cosine {
+        crate::distances::cosine(left, right)?
+    } else {
+        crate::distances::compute(metric, left, right)?
+    };
+    Ok(f64::from(crate::distances::similarity_value(metric, raw)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hnsw::HnswParams;
+
+    fn embeddings() -> Vec<(String, Vec<f32>)> {
+        vec![
+            ("a".into(), vec![1.0, 0.0]),
+            ("b".into(), vec![1.0, 0.0]),
+            ("c".into(), vec![0.0, 1.0]),
+        ]
+    }
+
+    fn initial() -> Vec<(String, f32)> {
+        vec![("a".into(), 0.9), ("b".into(), 0.8), ("c".into(), 0.1)]
+    }
+
+    /// Direct port of the Elixir reranker without the similarity cache.
+    fn naive(
+        candidates: &[(String, f32)],
+        vectors: &[(String, Vec<f32>)],
+        metric: Metric,
+        lambda: f64,
+        limit: usize,
+    ) -> Vec<(String, f32)> {
+        let lookup: HashMap<_, _> = vectors.iter().map(|(id, v)| (id.clone(), v)).collect();
+        let mut remaining = candidates.to_vec();
+        let mut selected: Vec<(String, f32)> = Vec::new();
+        while selected.len() < limit && !remaining.is_empty() {
+            let scores: Vec<f64> = remaining
+                .iter()
+                .map(|(id, relevance)| {
+                    let redundancy = selected
+                        .iter()
+                        .map(|(other, _)| similarity(metric, lookup[id], lookup[other]).unwrap())
+                        .fold(None, |max: Option<f64>, value| {
+                            Some(max.map_or(value, |max| max.max(value)))
+                        })
+                        .unwrap_or(0.0);
+                    lambda * f64::from(*relevance) - (1.0 - lambda) * redundancy
+                })
+                .collect();
+            let mut best = 0;
+            for (index, score) in scores.iter().enumerate() {
+                if *score > scores[best] {
+                    best = index;
+                }
+            }
+            selected.push(remaining.remove(best));
+        }
+        selected
+    }
+
+    #[test]
+    fn matches_the_elixir_doctest_and_preserves_relevance_scores() {
+        assert_eq!(
+            rerank(initial(), embeddings(), Metric::Cosine, 0.5, 2).unwrap(),
+            vec![("a".into(), 0.9), ("c".into(), 0.1)]
+        );
+        assert_eq!(
+            rerank(initial(), embeddings(), Metric::Cosine, 1.0, 3).unwrap(),
+            initial()
+        );
+        assert_eq!(
+            rerank(initial(), embeddings(), Metric::Cosine, 0.5, 0).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn cached_selection_matches_the_naive_reranker_for_every_metric() {
+        let vectors: Vec<_> = (0..40)
+            .map(|index| {
+                (
+                    format!("id-{index:02}"),
+                    vec![
+                        ((index * 7 % 11) as f32 - 5.0) / 3.0,
+                        ((index * 5 % 13) as f32 - 6.0) / 4.0,
+                        if index % 3 == 0 { 0.0 } else { 1.0 },
+                    ],
+                )
+            })
+            .collect();
+        let candidates: Vec<_> = vectors
+            .iter()
+            .enumerate()
+            .map(|(index, (id, _))| (id.clone(), ((index * 17 % 23) as f32) / 23.0))
+            .collect();
+
+        for metric in [
+            Metric::L2,
+            Metric::Cosine,
+            Metric::InnerProduct,
+            Metric::NegativeInnerProduct,
+            Metric::Manhattan,
+            Metric::Jaccard,
+        ] {
+            for lambda in [0.0f32, 0.3, 0.7, 1.0] {
+                assert_eq!(
+                    rerank(candidates.clone(), vectors.clone(), metric, lambda, 12).unwrap(),
+                    naive(&candidates, &vectors, metric, f64::from(lambda), 12),
+                    "metric {metric:?} lambda {lambda}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn index_variants_read_stored_vectors_and_skip_stale_ids() {
+        let mut flat = FlatIndex::new(Metric::Cosine);
+        flat.insert_many(embeddings()).unwrap();
+        let mut hnsw = HnswIndex::new(
+            Metric::Cosine,
+            HnswParams {
+                m: 4,
+                m0: 8,
+                ef_construction: 16,
+                ef_search: 16,
+                max_level: 4,
+            },
+        )
+        .unwrap();
+        hnsw.insert_many(embeddings()).unwrap();
+
+        let mut candidates = initial();
+        candidates.insert(1, ("deleted".into(), 5.0));
+        let expected = vec![("a".into(), 0.9), ("c".into(), 0.1)];
+        assert_eq!(rerank_flat(&flat, &candidates, 0.5, 2).unwrap(), expected);
+        assert_eq!(rerank_hnsw(&hnsw, &candidates, 0.5, 2).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_malformed_inputs() {
+        assert!(rerank(initial(), embeddings(), Metric::Cosine, 1.5, 1).is_err());
+        assert!(rerank(initial(), embeddings(), Metric::Cosine, f32::NAN, 1).is_err());
+        assert!(rerank(
+            vec![("a".into(), f32::INFINITY)],
+            embeddings(),
+            Metric::Cosine,
+            0.5,
+            1
+        )
+        .is_err());
+        assert!(rerank(
+            vec![("a".into(), 1.0), ("a".into(), 0.5)],
+            embeddings(),
+            Metric::Cosine,
+            0.5,
+            1
+        )
+        .is_err());
+        assert!(rerank(
+            vec![("missing".into(), 1.0)],
+            embeddings(),
+            Metric::Cosine,
+            0.5,
+            1
+        )
+        .is_err());
+
+        let mut duplicated = embeddings();
+        duplicated.push(("a".into(), vec![0.0, 1.0]));
+        assert!(rerank(initial(), duplicated, Metric::Cosine, 0.5, 1).is_err());
+        for bad in [vec![], vec![1.0], vec![f32::NAN, 0.0]] {
+            let mut vectors = embeddings();
+            vectors.push(("bad".into(), bad));
+            assert!(rerank(initial(), vectors, Metric::Cosine, 0.5, 1).is_err());
+        }
+    }
+}
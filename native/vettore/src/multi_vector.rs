@@ -1,39 +1,24 @@
 //! Native MaxSim/ColBERT scoring and batched top-k selection.
 
-use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
 use crate::distances::Metric;
-
-#[derive(Debug)]
-struct Hit {
-    id: String,
-    score: f32,
-}
-
-impl Eq for Hit {}
-
-impl PartialEq for Hit {
-    fn eq(&self, other: &Self) -> bool {
-        self.score.total_cmp(&other.score) == Ordering::Equal && self.id == other.id
-    }
-}
-
-impl Ord for Hit {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // Reverse score ordering so BinaryHeap::peek exposes the worst retained
-        // result. For equal scores, lexicographically larger ids are worse.
-        other
-            .score
-            .total_cmp(&self.score)
-            .then_with(|| self.id.cmp(&other.id))
-    }
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+/// Best document vector for one query vector, as reported by `explain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenMatch {
+    /// Position of the best document vector; `None` for empty documents.
+    pub document_index: Option<usize>,
+    /// Weighted similarity this query vector adds to the total.
+    pub contribution: f32,
 }
 
-impl PartialOrd for Hit {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+/// MaxSim total plus one `TokenMatch` per query vector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    pub score: f32,
+    pub matches: Vec<TokenMatch>,
 }
 
 /// Computes one MaxSim score: each query vector contributes its best document match.
@@ -42,43 +27,78 @@ pub fn score(
     document_vectors: &[Vec<f32>],
     metric: Metric,
 ) -> Result<f32, String> {
-    if query_vectors.is_empty() {
-        validate_standalone_vectors(document_vectors)?;
+    score_weighted(query_vectors, document_vectors, None, metric)
+}
+
+/// Computes one MaxSim score with each best match scaled by its query weight.
+pub fn score_weighted(
+    query_vectors: &[Vec<f32>],
+    document_vectors: &[Vec<f32>],
+    weights: Option<&[f32]>,
+    metric: Metric,
+) -> Result<f32, String> {
+    if !validate_pair(query_vectors, document_vectors, weights)? {
         return Ok(0.0);
     }
+    score_validated_weighted(query_vectors, document_vectors, weights, metric)
+}
 
-    let dimension = query_vectors[0].len();
-    if dimension == 0 {
-        return Err("vectors must not be empty".to_string());
+/// Scores like `score_weighted` and reports every query vector's best match.
+pub fn explain(
+    query_vectors: &[Vec<f32>],
+    document_vectors: &[Vec<f32>],
+    weights: Option<&[f32]>,
+    metric: Metric,
+) -> Result<Explanation, String> {
+    if !validate_pair(query_vectors, document_vectors, weights)? {
+        return Ok(Explanation {
+            score: 0.0,
+            matches: vec![
+                TokenMatch {
+                    document_index: None,
+                    contribution: 0.0,
+                };
+                query_vectors.len()
+            ],
+        });
     }
-    validate_vectors(query_vectors, dimension)?;
 
-    if document_vectors.is_empty() {
-        return Ok(0.0);
+    let mut score = 0.0f32;
+    let mut matches = Vec::with_capacity(query_vectors.len());
+    for (position, query) in query_vectors.iter().enumerate() {
+        let (document_index, best) = best_match(query, document_vectors, metric)?;
+        let contribution = weight_at(weights, position) * best;
+        score += contribution;
+        if !score.is_finite() {
+            return Err("score overflow".to_string());
+        }
+        matches.push(TokenMatch {
+            document_index: Some(document_index),
+            contribution,
+        });
     }
+    Ok(Explanation { score, matches })
+}
 
-    validate_vectors(document_vectors, dimension)?;
-
-    score_validated(query_vectors, document_vectors, metric)
+/// Scores already validated vectors; documents may be owned or borrowed rows.
+pub(crate) fn score_validated<D: AsRef<[f32]>>(
+    query_vectors: &[Vec<f32>],
+    document_vectors: &[D],
+    metric: Metric,
+) -> Result<f32, String> {
+    score_validated_weighted(query_vectors, document_vectors, None, metric)
 }
 
-fn score_validated(
+fn score_validated_weighted<D: AsRef<[f32]>>(
     query_vectors: &[Vec<f32>],
-    document_vectors: &[Vec<f32>],
+    document_vectors: &[D],
+    weights: Option<&[f32]>,
     metric: Metric,
 ) -> Result<f32, String> {
     let mut total = 0.0;
-    for query in query_vectors {
-        let mut best = f32::NEG_INFINITY;
-        for document in document_vectors {
-            let raw = if metric == Metric::Cosine {
-                crate::distances::cosine(query, document)?
-            } else {
-                crate::distances::compute(metric, query, document)?
-            };
-            best = best.max(crate::distances::similarity_value(metric, raw));
-        }
-        total += best;
+    for (position, query) in query_vectors.iter().enumerate() {
+        let (_, best) = best_match(query, document_vectors, metric)?;
+        total += weight_at(weights, position) * best;
         if !total.is_finite() {
             return Err("score overflow".to_string());
         }
@@ -86,14 +106,52 @@ fn score_validated(
     Ok(total)
 }
 
+/// Returns the first document vector with the highest similarity to `query`.
+fn best_match<D: AsRef<[f32]>>(
+    query: &[f32],
+    document_vectors: &[D],
+    metric: Metric,
+) -> Result<(usize, f32), String> {
+    let mut best = (0, f32::NEG_INFINITY);
+    for (index, document) in document_vectors.iter().enumerate() {
+        let document = document.as_ref();
+        let raw = if metric == Metric::Cosine {
+            crate::distances::cosine(query, document)?
+        } else {
+            crate::distances::compute(metric, query, document)?
+        };
+        let similarity = crate::distances::similarity_value(metric, raw);
+        if similarity > best.1 {
+            best = (index, similarity);
+        }
+    }
+    Ok(best)
+}
+
+fn weight_at(weights: Option<&[f32]>, position: usize) -> f32 {
+    weights.map_or(1.0, |weights| weights[position])
+}
+
 /// Scores a document batch and retains the highest-scoring records.
 pub fn top_k(
     documents: Vec<(String, Vec<Vec<f32>>)>,
     query_vectors: &[Vec<f32>],
     metric: Metric,
     limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    top_k_weighted(documents, query_vectors, None, metric, limit)
+}
+
+/// Scores a document batch with per-query-vector weights and keeps the best.
+pub fn top_k_weighted(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    query_vectors: &[Vec<f32>],
+    weights: Option<&[f32]>,
+    metric: Metric,
+    limit: usize,
 ) -> Result<Vec<(String, f32)>, String> {
     validate_standalone_vectors(query_vectors)?;
+    validate_weights(query_vectors, weights)?;
     let query_dimension = query_vectors.first().map(Vec::len);
 
     let mut heap = BinaryHeap::with_capacity(usize::min(limit, documents.len()));
@@ -106,32 +164,58 @@ pub fn top_k(
             Some(_dimension) if vectors.is_empty() => 0.0,
             Some(dimension) => {
                 validate_vectors(&vectors, dimension)?;
-                score_validated(query_vectors, &vectors, metric)?
+                score_validated_weighted(query_vectors, &vectors, weights, metric)?
             }
         };
-        let hit = Hit { id, score };
-        if limit == 0 {
-            continue;
-        }
-        if heap.len() < limit {
-            heap.push(hit);
-        } else if heap.peek().is_some_and(|worst| hit < *worst) {
-            heap.pop();
-            heap.push(hit);
-        }
+        push_top_k(&mut heap, Hit::scored(id, score), limit);
     }
 
-    let mut hits = heap.into_vec();
-    hits.sort_by(|left, right| {
-        right
-            .score
-            .total_cmp(&left.score)
-            .then_with(|| left.id.cmp(&right.id))
-    });
-    Ok(hits.into_iter().map(|hit| (hit.id, hit.score)).collect())
+    Ok(sorted_hits(heap))
 }
 
-fn validate_standalone_vectors(vectors: &[Vec<f32>]) -> Result<(), String> {
+/// Validates both sides and the weights; `false` means the score is zero.
+fn validate_pair(
+    query_vectors: &[Vec<f32>],
+    document_vectors: &[Vec<f32>],
+    weights: Option<&[f32]>,
+) -> Result<bool, String> {
+    validate_weights(query_vectors, weights)?;
+    if query_vectors.is_empty() {
+        validate_standalone_vectors(document_vectors)?;
+        return Ok(false);
+    }
+
+    let dimension = query_vectors[0].len();
+    if dimension == 0 {
+        return Err("vectors must not be empty".to_string());
+    }
+    validate_vectors(query_vectors, dimension)?;
+
+    if document_vectors.is_empty() {
+        return Ok(false);
+    }
+
+    validate_vectors(document_vectors, dimension)?;
+    Ok(true)
+}
+
+fn validate_weights(query_vectors: &[Vec<f32>], weights: Option<&[f32]>) -> Result<(), String> {
+    let Some(weights) = weights else {
+        return Ok(());
+    };
+    if weights.len() != query_vectors.len() {
+        return Err("query weights must match query vectors".to_string());
+    }
+    if weights
+        .iter()
+        .any(|weight| !weight.is_finite() || *weight < 0.0)
+    {
+        return Err("query weights must be finite and non-negative".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_standalone_vectors(vectors: &[Vec<f32>]) -> Result<(), String> {
     let Some(first) = vectors.first() else {
         return Ok(());
     };
@@ -305,21 +389,91 @@ mod tests {
     }
 
     #[test]
-    fn heap_hit_equality_and_partial_order_include_the_external_id() {
-        let first = Hit {
-            id: "a".into(),
-            score: 1.0,
-        };
-        let equal = Hit {
-            id: "a".into(),
-            score: 1.0,
-        };
-        let other_id = Hit {
-            id: "b".into(),
-            score: 1.0,
-        };
-        assert_eq!(first, equal);
-        assert_ne!(first, other_id);
-        assert_eq!(first.partial_cmp(&other_id), Some(Ordering::Less));
+    fn weights_scale_and_mask_query_vectors() {
+        let query = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+        let document = vec![vec![1.0, 0.0], vec![0.0, 2.0]];
+        let unweighted = score(&query, &document, Metric::InnerProduct).unwrap();
+        assert_eq!(
+            score_weighted(
+                &query,
+                &document,
+                Some(&[1.0, 1.0, 1.0]),
+                Metric::InnerProduct
+            ),
+            Ok(unweighted)
+        );
+        assert_eq!(
+            score_weighted(
+                &query,
+                &document,
+                Some(&[0.5, 2.0, 0.0]),
+                Metric::InnerProduct
+            ),
+            Ok(4.5)
+        );
+
+        let documents = vec![
+            ("first".into(), vec![vec![3.0, 0.0]]),
+            ("second".into(), vec![vec![0.0, 2.0]]),
+        ];
+        assert_eq!(
+            top_k_weighted(
+                documents.clone(),
+                &query[..2],
+                Some(&[0.0, 1.0]),
+                Metric::InnerProduct,
+                1
+            ),
+            Ok(vec![("second".into(), 2.0)])
+        );
+        assert_eq!(
+            top_k_weighted(documents, &query[..2], None, Metric::InnerProduct, 1),
+            Ok(vec![("first".into(), 3.0)])
+        );
+
+        assert!(score_weighted(&query, &document, Some(&[1.0]), Metric::L2).is_err());
+        assert!(score_weighted(&query, &document, Some(&[1.0, -1.0, 1.0]), Metric::L2).is_err());
+        assert!(score_weighted(&query, &[], Some(&[1.0, f32::NAN, 1.0]), Metric::L2).is_err());
+        assert!(top_k_weighted(vec![], &query, Some(&[]), Metric::L2, 1).is_err());
+    }
+
+    #[test]
+    fn explain_reports_best_positions_and_contributions() {
+        let query = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let document = vec![vec![0.0, 3.0], vec![2.0, 0.0], vec![2.0, 0.0]];
+        let explanation =
+            explain(&query, &document, Some(&[1.0, 0.5]), Metric::InnerProduct).unwrap();
+        assert_eq!(
+            explanation,
+            Explanation {
+                score: 3.5,
+                matches: vec![
+                    TokenMatch {
+                        document_index: Some(1),
+                        contribution: 2.0,
+                    },
+                    TokenMatch {
+                        document_index: Some(0),
+                        contribution: 1.5,
+                    },
+                ],
+            }
+        );
+
+        for metric in all_metrics() {
+            let explanation = explain(&query, &document, None, metric).unwrap();
+            assert_eq!(Ok(explanation.score), score(&query, &document, metric));
+        }
+
+        let empty = explain(&query, &[], None, Metric::L2).unwrap();
+        assert_eq!(empty.score, 0.0);
+        assert!(empty
+            .matches
+            .iter()
+            .all(|token| token.document_index.is_none() && token.contribution == 0.0));
+        assert_eq!(
+            explain(&[], &document, None, Metric::L2).unwrap().matches,
+            vec![]
+        );
     }
 }
@@ -0,0 +1,412 @@
+//! Native multi-vector (late interaction) index resource.
+//!
+//! Every document token vector lives once in a token-level HNSW graph keyed by
+//! the document's internal number and token position. A query gathers
+//! candidate documents from each query token's approximate neighbours, then
+//! scores only those documents with exact MaxSim, so token matrices never
+//! cross the NIF boundary at query time. Cosine indexes store unit-length
+//! tokens, matching how collections normalize vectors before cosine search.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::distances::Metric;
+use crate::hnsw::{HnswIndex, HnswParams};
+use crate::search::{push_top_k, sorted_hits, Hit};
+use crate::token_pooling::Reduction;
+
+struct Document {
+    id: String,
+    tokens: usize,
+}
+
+pub struct MultiVectorIndex {
+    metric: Metric,
+    tokens: HnswIndex,
+    reduction: Option<Reduction>,
+    ids: HashMap<String, u32>,
+    documents: HashMap<u32, Document>,
+    next_document: u32,
+}
+
+impl MultiVectorIndex {
+    /// Creates an empty index whose token graph and MaxSim share one metric.
+    pub fn new(metric: Metric, params: HnswParams) -> Result<Self, String> {
+        Ok(Self {
+            metric,
+            tokens: HnswIndex::new(metric, params)?,
+            reduction: None,
+            ids: HashMap::new(),
+            documents: HashMap::new(),
+            next_document: 0,
+        })
+    }
+
+    /// Sets the token reduction applied to documents inserted from now on.
+    pub fn set_reduction(&mut self, reduction: Option<Reduction>) -> Result<(), String> {
+        if let Some(reduction) = &reduction {
+            reduction.validate()?;
+        }
+        self.reduction = reduction;
+        Ok(())
+    }
+
+    /// Inserts or replaces one document's token vectors.
+    pub fn insert(&mut self, id: String, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+        self.insert_many(vec![(id, vectors)])
+    }
+
+    /// Validates and reduces a whole batch before mutating the index, then
+    /// inserts or replaces every document.
+    pub fn insert_many(&mut self, documents: Vec<(String, Vec<Vec<f32>>)>) -> Result<(), String> {
+        let mut expected = self.dimension();
+        let mut prepared = Vec::with_capacity(documents.len());
+        for (id, vectors) in documents {
+            let vectors = self.prepare_document(vectors, expected)?;
+            expected = expected.or(Some(vectors[0].len()));
+            prepared.push((id, vectors));
+        }
+        u32::try_from(prepared.len())
+            .ok()
+            .and_then(|count| self.next_document.checked_add(count))
+            .ok_or_else(|| "multi-vector document numbers exhausted".to_string())?;
+
+        for (id, vectors) in prepared {
+            self.store(id, vectors)?;
+        }
+        Ok(())
+    }
+
+    /// Checks one document against the index dimension and returns the token
+    /// vectors that would be stored after reduction and normalization.
+    fn prepare_document(
+        &self,
+        vectors: Vec<Vec<f32>>,
+        expected: Option<usize>,
+    ) -> Result<Vec<Vec<f32>>, String> {
+        if vectors.is_empty() {
+            return Err("document must have at least one vector".to_string());
+        }
+        crate::multi_vector::validate_standalone_vectors(&vectors)?;
+        if expected.is_some_and(|expected| vectors[0].len() != expected) {
+            return Err("dimension mismatch".to_string());
+        }
+        let vectors = match &self.reduction {
+            Some(reduction) => reduction.apply(vectors, self.metric)?,
+            None => vectors,
+        };
+        vectors
+            .into_iter()
+            .map(|vector| self.prepare(vector))
+            .collect()
+    }
+
+    fn store(&mut self, id: String, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+        self.delete(&id);
+        let document = self.next_document;
+        self.next_document = document
+            .checked_add(1)
+            .ok_or_else(|| "multi-vector document numbers exhausted".to_string())?;
+
+        let tokens = vectors.len();
+        let vectors = vectors
+            .into_iter()
+            .enumerate()
+            .map(|(token, vector)| (token_key(document, token), vector))
+            .collect();
+        self.tokens.insert_many(vectors)?;
+        self.ids.insert(id.clone(), document);
+        self.documents.insert(document, Document { id, tokens });
+        Ok(())
+    }
+
+    /// Deletes one document and all of its token vectors.
+    pub fn delete(&mut self, id: &str) {
+        let Some(document) = self.ids.remove(id) else {
+            return;
+        };
+        let Some(stored) = self.documents.remove(&document) else {
+            return;
+        };
+        for token in 0..stored.tokens {
+            self.tokens.delete(&token_key(document, token));
+        }
+    }
+
+    /// Gathers candidates from each query token's `token_candidates` nearest
+    /// tokens and returns the best documents by exact MaxSim score.
+    pub fn search(
+        &self,
+        query_vectors: &[Vec<f32>],
+        token_candidates: usize,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        crate::multi_vector::validate_standalone_vectors(query_vectors)?;
+        if query_vectors.is_empty() || limit == 0 || token_candidates == 0 {
+            return Ok(Vec::new());
+        }
+
+        let query_vectors = query_vectors
+            .iter()
+            .map(|vector| self.prepare(vector.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut candidates = HashSet::new();
+        for query in &query_vectors {
+            for (key, _) in self.tokens.search(query, token_candidates)? {
+                candidates.insert(document_of(&key)?);
+            }
+        }
+
+        let mut hits = BinaryHeap::with_capacity(usize::min(limit, candidates.len()));
+        for document in candidates {
+            let id = self.documents[&document].id.clone();
+            let score = self.score_document(&query_vectors, document)?;
+            push_top_k(&mut hits, Hit::scored(id, score), limit);
+        }
+        Ok(sorted_hits(hits))
+    }
+
+    fn score_document(&self, query_vectors: &[Vec<f32>], document: u32) -> Result<f32, String> {
+        let vectors = self.document_vectors(document)?;
+        crate::multi_vector::score_validated(query_vectors, &vectors, self.metric)
+    }
+
+    fn document_vectors(&self, document: u32) -> Result<Vec<&[f32]>, String> {
+        (0..self.documents[&document].tokens)
+            .map(|token| {
+                self.tokens
+                    .get(&token_key(document, token))
+                    .ok_or_else(|| "missing token vector".to_string())
+            })
+            .collect()
+    }
+
+    fn prepare(&self, vector: Vec<f32>) -> Result<Vec<f32>, String> {
+        if self.metric == Metric::Cosine {
+            crate::distances::normalize_l2(vector)
+        } else {
+            Ok(vector)
+        }
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        let document = self.documents.keys().next()?;
+        self.tokens.get(&token_key(*document, 0)).map(<[f32]>::len)
+    }
+}
+
+pub struct MultiVectorResource(pub RwLock<MultiVectorIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for MultiVectorResource {}
+
+fn token_key(document: u32, token: usize) -> String {
+    format!("{document}:{token}")
+}
+
+fn document_of(key: &str) -> Result<u32, String> {
+    key.split_once(':')
+        .and_then(|(document, _)| document.parse().ok())
+        .ok_or_else(|| "malformed token key".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> HnswParams {
+        HnswParams {
+            m: 8,
+            m0: 16,
+            ef_construction: 64,
+            ef_search: 64,
+            max_level: 8,
+        }
+    }
+
+    fn documents(count: usize) -> Vec<(String, Vec<Vec<f32>>)> {
+        (0..count)
+            .map(|index| {
+                let tokens = 2 + index % 4;
+                (
+                    format!("doc-{index:03}"),
+                    (0..tokens)
+                        .map(|token| {
+                            (0..6)
+                                .map(|dimension| {
+                                    let hash = crate::muvera::hash4(
+                                        index as u64,
+                                        token as u64,
+                                        dimension as u64,
+                                        0,
+                                    );
+                                    (hash % 1_000) as f32 / 100.0 - 5.0
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exhaustive_candidates_match_multi_vector_top_k() {
+        let documents = documents(60);
+        let total_tokens: usize = documents.iter().map(|(_, vectors)| vectors.len()).sum();
+        let query = vec![
+            vec![1.0, -2.0, 0.5, 3.0, 0.0, -1.0],
+            vec![-4.0, 1.0, 2.0, 0.0, 1.5, 2.0],
+        ];
+
+        for metric in [Metric::InnerProduct, Metric::L2] {
+            let mut index = MultiVectorIndex::new(metric, params()).unwrap();
+            index.insert_many(documents.clone()).unwrap();
+            assert_eq!(
+                index.search(&query, total_tokens, 10).unwrap(),
+                crate::multi_vector::top_k(documents.clone(), &query, metric, 10).unwrap(),
+                "metric {metric:?}"
+            );
+        }
+
+        // Cosine stores unit tokens, so scores agree up to normalization rounding.
+        let mut index = MultiVectorIndex::new(Metric::Cosine, params()).unwrap();
+        index.insert_many(documents.clone()).unwrap();
+        let hits = index.search(&query, total_tokens, 10).unwrap();
+        let expected = crate::multi_vector::top_k(documents, &query, Metric::Cosine, 10).unwrap();
+        for ((id, score), (expected_id, expected_score)) in hits.iter().zip(&expected) {
+            assert_eq!(id, expected_id);
+            assert!((score - expected_score).abs() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn token_ann_finds_documents_owning_matching_tokens() {
+        let mut index = MultiVectorIndex::new(Metric::Cosine, params()).unwrap();
+        index.insert_many(documents(200)).unwrap();
+        index
+            .insert(
+                "needle".into(),
+                vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0], vec![1.0; 6]],
+            )
+            .unwrap();
+
+        let hits = index
+            .search(&[vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0]], 4, 1)
+            .unwrap();
+        assert_eq!(hits, vec![("needle".into(), 1.0)]);
+    }
+
+    #[test]
+    fn replacements_and_deletes_remove_every_token() {
+        let mut index = MultiVectorIndex::new(Metric::L2, params()).unwrap();
+        index
+            .insert("a".into(), vec![vec![0.0, 0.0], vec![1.0, 1.0]])
+            .unwrap();
+        index.insert("b".into(), vec![vec![5.0, 5.0]]).unwrap();
+        index.insert("a".into(), vec![vec![9.0, 9.0]]).unwrap();
+
+        assert_eq!(index.tokens.sorted_entries().len(), 2);
+        assert_eq!(
+            index.search(&[vec![5.0, 5.0]], 10, 5).unwrap()[0],
+            ("b".into(), 1.0)
+        );
+
+        index.delete("a");
+        index.delete("missing");
+        assert_eq!(index.tokens.sorted_entries().len(), 1);
+        assert_eq!(
+            index
+                .search(&[vec![9.0, 9.0]], 10, 5)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec!["b".to_string()]
+        );
+    }
+
+    #[test]
+    fn batch_validation_is_atomic() {
+        let mut index = MultiVectorIndex::new(Metric::L2, params()).unwrap();
+        index
+            .insert("existing".into(), vec![vec![1.0, 0.0]])
+            .unwrap();
+
+        for invalid in [vec![], vec![vec![1.0]], vec![vec![f32::NAN, 0.0]]] {
+            assert!(index
+                .insert_many(vec![
+                    ("existing".into(), vec![vec![0.0, 1.0]]),
+                    ("valid".into(), vec![vec![0.0, 1.0], vec![1.0, 1.0]]),
+                    ("invalid".into(), invalid),
+                ])
+                .is_err());
+        }
+        assert_eq!(index.documents.len(), 1);
+        assert!(!index.ids.contains_key("valid"));
+        assert_eq!(
+            index.tokens.sorted_entries(),
+            vec![("0:0", [1.0, 0.0].as_slice())]
+        );
+
+        index
+            .insert_many(vec![
+                ("a".into(), vec![vec![0.0, 1.0]]),
+                ("b".into(), vec![vec![1.0, 1.0]]),
+            ])
+            .unwrap();
+        assert_eq!(index.documents.len(), 3);
+    }
+
+    #[test]
+    fn insert_time_reduction_shrinks_stored_tokens() {
+        let mut index = MultiVectorIndex::new(Metric::L2, params()).unwrap();
+        index
+            .set_reduction(Some(Reduction {
+                dedup_threshold: None,
+                max_tokens: None,
+                pool_factor: 2,
+            }))
+            .unwrap();
+        index
+            .insert(
+                "a".into(),
+                vec![
+                    vec![0.0, 0.0],
+                    vec![10.0, 0.0],
+                    vec![0.0, 1.0],
+                    vec![10.0, 2.0],
+                ],
+            )
+            .unwrap();
+        assert_eq!(index.tokens.sorted_entries().len(), 2);
+        assert_eq!(
+            index.search(&[vec![0.0, 0.5]], 10, 1).unwrap(),
+            vec![("a".into(), 1.0)]
+        );
+        assert!(index
+            .set_reduction(Some(Reduction {
+                dedup_threshold: None,
+                max_tokens: Some(0),
+                pool_factor: 1,
+            }))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_documents_and_queries() {
+        let mut index = MultiVectorIndex::new(Metric::Cosine, params()).unwrap();
+        assert!(index.insert("a".into(), vec![]).is_err());
+        assert!(index.insert("a".into(), vec![vec![]]).is_err());
+        assert!(index
+            .insert("a".into(), vec![vec![1.0, 0.0], vec![1.0]])
+            .is_err());
+        assert!(index.insert("a".into(), vec![vec![f32::NAN]]).is_err());
+        index.insert("a".into(), vec![vec![1.0, 0.0]]).unwrap();
+        assert!(index.insert("b".into(), vec![vec![1.0, 0.0, 0.0]]).is_err());
+        assert!(index.search(&[vec![1.0]], 4, 1).is_err());
+        assert_eq!(index.search(&[], 4, 1), Ok(vec![]));
+        assert_eq!(index.search(&[vec![1.0, 0.0]], 4, 0), Ok(vec![]));
+        assert!(MultiVectorIndex::new(Metric::Cosine, HnswParams { m: 0, ..params() }).is_err());
+    }
+}
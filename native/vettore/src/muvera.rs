@@ -3,6 +3,11 @@
 //! Query encodings sum projected vectors per partition. Document encodings
 //! average projected vectors per partition. Both use deterministic hashing from
 //! the shared config seed so query and document FDEs are comparable.
+//! `Encoder` materialises those hashed weights once per config, so repeated
+//! encodes skip the per-token hashing while producing bit-identical FDEs.
+//! Empty-partition filling, Gaussian SimHash and AMS-sketch projections from
+//! the MUVERA paper are opt-in config flags; `Config::new` keeps them off so
+//! existing encodings are unchanged.
 
 #[derive(Clone, Copy)]
 pub enum Mode {
@@ -10,6 +15,58 @@ pub enum Mode {
     Document,
 }
 
+/// Distribution of the SimHash hyperplane weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimHash {
+    /// Uniform weights in `[-1, 1]`, the original encoding.
+    Uniform,
+    /// Standard Gaussian weights, as in the MUVERA paper.
+    Gaussian,
+}
+
+impl SimHash {
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Uniform),
+            1 => Ok(Self::Gaussian),
+            _ => Err("unknown simhash distribution".to_string()),
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Uniform => 0,
+            Self::Gaussian => 1,
+        }
+    }
+}
+
+/// Inner projection applied when `projection_dimension != dimension`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Dense random signs, the original encoding.
+    Rademacher,
+    /// AMS sketch: every input coordinate lands in one signed output row.
+    Ams,
+}
+
+impl Projection {
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Rademacher),
+            1 => Ok(Self::Ams),
+            _ => Err("unknown muvera projection".to_string()),
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Rademacher => 0,
+            Self::Ams => 1,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Config {
     pub dimension: usize,
@@ -18,66 +75,436 @@ pub struct Config {
     pub seed: u64,
     pub projection_dimension: usize,
     pub final_projection_dimension: Option<usize>,
+    /// Copies the SimHash-nearest token into document partitions left empty.
+    pub fill_empty_partitions: bool,
+    pub simhash: SimHash,
+    pub projection: Projection,
+}
+
+impl Config {
+    /// The original encoding: no filling, uniform SimHash, Rademacher projection.
+    pub fn new(
+        dimension: usize,
+        num_repetitions: usize,
+        num_simhash_projections: usize,
+        seed: u64,
+        projection_dimension: usize,
+        final_projection_dimension: Option<usize>,
+    ) -> Self {
+        Self {
+            dimension,
+            num_repetitions,
+            num_simhash_projections,
+            seed,
+            projection_dimension,
+            final_projection_dimension,
+            fill_empty_partitions: false,
+            simhash: SimHash::Uniform,
+            projection: Projection::Rademacher,
+        }
+    }
+
+    /// Stable identity of every setting that changes FDE coordinates, mixed
+    /// with `ALGORITHM_VERSION`. Query and document FDEs are comparable only
+    /// when their fingerprints are equal.
+    pub fn fingerprint(&self) -> u64 {
+        let fields = [
+            ALGORITHM_VERSION,
+            self.dimension as u64,
+            self.num_repetitions as u64,
+            self.num_simhash_projections as u64,
+            self.seed,
+            self.projection_dimension as u64,
+            self.final_projection_dimension
+                .map_or(0, |dimension| dimension as u64 + 1),
+            u64::from(self.fill_empty_partitions),
+            u64::from(self.simhash.code()),
+            u64::from(self.projection.code()),
+        ];
+        fields
+            .iter()
+            .enumerate()
+            .fold(FINGERPRINT_STREAM, |state, (field, value)| {
+                hash4(state, field as u64, *value, FINGERPRINT_STREAM)
+            })
+    }
+}
+
+/// Rejects FDEs whose config fingerprint differs from the one an index stores.
+pub fn check_fingerprint(stored: Option<u64>, fingerprint: u64) -> Result<(), String> {
+    match stored {
+        Some(stored) if stored == fingerprint => Ok(()),
+        Some(_) => Err("muvera fingerprint mismatch".to_string()),
+        None => Err("index has no muvera fingerprint".to_string()),
+    }
+}
+
+/// Keeps every vector in an index under one fingerprint: FDEs must match a
+/// bound index, and plain vectors cannot join one or be relabelled as FDEs.
+pub fn check_insert_fingerprint(
+    stored: Option<u64>,
+    fingerprint: Option<u64>,
+    empty: bool,
+) -> Result<(), String> {
+    match (stored, fingerprint) {
+        (Some(stored), Some(fingerprint)) if stored != fingerprint => {
+            Err("muvera fingerprint mismatch".to_string())
+        }
+        (Some(_), None) => Err("index holds muvera fdes; insert with a fingerprint".to_string()),
+        (None, Some(_)) if !empty => {
+            Err("index holds vectors without a muvera fingerprint".to_string())
+        }
+        _ => Ok(()),
+    }
 }
 
 const MAX_OUTPUT_DIMENSIONS: usize = 16_777_216;
 
+/// Cap on each hashed weight table an `Encoder` materialises.
+const MAX_ENCODER_WEIGHTS: usize = 16_777_216;
+
+/// Bumped whenever an unchanged config would encode to different FDEs.
+pub const ALGORITHM_VERSION: u64 = 2;
+const FINGERPRINT_STREAM: u64 = 0x3C6E_F372_FE94_F82B;
+
+/// Hash streams that keep the optional weight families independent.
+const GAUSSIAN_STREAM: u64 = 0x6A09_E667_F3BC_C908;
+const AMS_STREAM: u64 = 0xBB67_AE85_84CA_A73B;
+
 /// Builds a fixed-dimensional encoding for query or document multi-vectors.
 pub fn encode(vectors: Vec<Vec<f32>>, config: Config, mode: Mode) -> Result<Vec<f32>, String> {
     validate(&vectors, &config)?;
+    let layout = Layout::for_config(&config)?;
+    encode_with(&Hashed(&config), &vectors, &layout, mode)
+}
+
+/// Output geometry shared by the stateless and cached encoders.
+struct Layout {
+    partitions: usize,
+    repetition_size: usize,
+    output_size: usize,
+}
+
+impl Layout {
+    fn for_config(config: &Config) -> Result<Self, String> {
+        let partitions = 1usize
+            .checked_shl(config.num_simhash_projections as u32)
+            .ok_or_else(|| "invalid simhash projection count".to_string())?;
+        let repetition_size = partitions
+            .checked_mul(config.projection_dimension)
+            .ok_or_else(|| "fde dimension overflow".to_string())?;
+        let output_size = config
+            .num_repetitions
+            .checked_mul(repetition_size)
+            .ok_or_else(|| "fde dimension overflow".to_string())?;
+        let final_size = config.final_projection_dimension.unwrap_or(output_size);
+        if output_size > MAX_OUTPUT_DIMENSIONS || final_size > MAX_OUTPUT_DIMENSIONS {
+            return Err("fde dimension exceeds safety limit".to_string());
+        }
+        Ok(Self {
+            partitions,
+            repetition_size,
+            output_size,
+        })
+    }
+}
 
-    let partitions = 1usize
-        .checked_shl(config.num_simhash_projections as u32)
-        .ok_or_else(|| "invalid simhash projection count".to_string())?;
-    let repetition_size = partitions
-        .checked_mul(config.projection_dimension)
-        .ok_or_else(|| "fde dimension overflow".to_string())?;
-    let output_size = config
-        .num_repetitions
-        .checked_mul(repetition_size)
-        .ok_or_else(|| "fde dimension overflow".to_string())?;
-    let final_size = config.final_projection_dimension.unwrap_or(output_size);
-    if output_size > MAX_OUTPUT_DIMENSIONS || final_size > MAX_OUTPUT_DIMENSIONS {
-        return Err("fde dimension exceeds safety limit".to_string());
-    }
-
-    let mut out = vec![0.0f32; output_size];
-    let counts_size = config
-        .num_repetitions
-        .checked_mul(partitions)
-        .ok_or_else(|| "fde dimension overflow".to_string())?;
-    let mut counts = vec![0usize; counts_size];
+/// Hashed parameters consumed by `encode_with`.
+trait Weights {
+    fn config(&self) -> &Config;
+    fn simhash(&self, repetition: usize, projection: usize, dimension: usize) -> f32;
+    fn projection(&self, repetition: usize, row: usize, dimension: usize) -> f32;
+    fn sketch(&self, index: usize, final_dimension: usize) -> (usize, f32);
+}
+
+/// Recomputes every weight from the config seed on demand.
+struct Hashed<'a>(&'a Config);
+
+impl Weights for Hashed<'_> {
+    fn config(&self) -> &Config {
+        self.0
+    }
+
+    fn simhash(&self, repetition: usize, projection: usize, dimension: usize) -> f32 {
+        simhash_weight(self.0, repetition, projection, dimension)
+    }
+
+    fn projection(&self, repetition: usize, row: usize, dimension: usize) -> f32 {
+        projection_weight(self.0, repetition, row, dimension)
+    }
+
+    fn sketch(&self, index: usize, final_dimension: usize) -> (usize, f32) {
+        sketch_target(self.0.seed, index, final_dimension)
+    }
+}
+
+/// MUVERA encoder holding the hashed SimHash, projection and count-sketch
+/// parameters for one config.
+pub struct Encoder {
+    config: Config,
+    layout: Layout,
+    /// `[repetition][projection][dimension]` SimHash weights.
+    simhash: Vec<f32>,
+    /// `[repetition][row][dimension]` weights; empty for identity projection.
+    projection: Vec<f32>,
+    /// Count-sketch slot and sign per full-FDE coordinate.
+    sketch: Vec<(u32, f32)>,
+}
+
+impl Encoder {
+    /// Validates `config` and materialises every hashed weight it implies.
+    pub fn new(config: Config) -> Result<Self, String> {
+        validate_config(&config)?;
+        let layout = Layout::for_config(&config)?;
+        let simhash_size = encoder_table_size(
+            config.num_repetitions,
+            config.num_simhash_projections,
+            config.dimension,
+        )?;
+        let projection_size = if config.projection_dimension == config.dimension {
+            0
+        } else {
+            encoder_table_size(
+                config.num_repetitions,
+                config.projection_dimension,
+                config.dimension,
+            )?
+        };
+
+        let mut simhash = Vec::with_capacity(simhash_size);
+        for repetition in 0..config.num_repetitions {
+            for projection in 0..config.num_simhash_projections {
+                for dimension in 0..config.dimension {
+                    simhash.push(simhash_weight(&config, repetition, projection, dimension));
+                }
+            }
+        }
+
+        let mut projection = Vec::with_capacity(projection_size);
+        if config.projection_dimension != config.dimension {
+            for repetition in 0..config.num_repetitions {
+                for row in 0..config.projection_dimension {
+                    for dimension in 0..config.dimension {
+                        projection.push(projection_weight(&config, repetition, row, dimension));
+                    }
+                }
+            }
+        }
+
+        let sketch = match config.final_projection_dimension {
+            Some(final_dimension) => (0..layout.output_size)
+                .map(|index| sketch_target(config.seed, index, final_dimension))
+                .map(|(slot, sign)| (slot as u32, sign))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            config,
+            layout,
+            simhash,
+            projection,
+            sketch,
+        })
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.config.fingerprint()
+    }
+
+    /// Encodes one multi-vector exactly as `encode` would for the same config.
+    pub fn encode(&self, vectors: &[Vec<f32>], mode: Mode) -> Result<Vec<f32>, String> {
+        if vectors.is_empty() {
+            return Err("empty vectors".to_string());
+        }
+        validate_vectors(vectors, &self.config)?;
+        encode_with(self, vectors, &self.layout, mode)
+    }
+
+    /// Encodes several multi-vectors, failing on the first invalid one.
+    pub fn encode_batch(
+        &self,
+        batch: &[Vec<Vec<f32>>],
+        mode: Mode,
+    ) -> Result<Vec<Vec<f32>>, String> {
+        batch
+            .iter()
+            .map(|vectors| self.encode(vectors, mode))
+            .collect()
+    }
+
+    /// Encodes keyed documents on up to `threads` scoped workers, keeping
+    /// input order. Zero threads, or more than the available parallelism,
+    /// means the available parallelism.
+    pub fn encode_documents(
+        &self,
+        documents: &[(String, Vec<Vec<f32>>)],
+        threads: usize,
+    ) -> Result<Vec<(String, Vec<f32>)>, String> {
+        let available = std::thread::available_parallelism().map_or(1, usize::from);
+        let threads = match threads {
+            0 => available,
+            threads => threads.min(available),
+        };
+        let chunk_size = documents.len().div_ceil(threads).max(1);
+        let encoded = std::thread::scope(|scope| {
+            let workers: Vec<_> = documents
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    std::thread::Builder::new()
+                        .spawn_scoped(scope, move || {
+                            chunk
+                                .iter()
+                                .map(|(_, vectors)| self.encode(vectors, Mode::Document))
+                                .collect::<Result<Vec<_>, String>>()
+                        })
+                        .map_err(|_| "muvera worker spawn failed".to_string())
+                })
+                .collect::<Result<_, String>>()?;
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .map_err(|_| "muvera worker panicked".to_string())?
+                })
+                .collect::<Result<Vec<_>, String>>()
+        })?;
+
+        Ok(documents
+            .iter()
+            .map(|(id, _)| id.clone())
+            .zip(encoded.into_iter().flatten())
+            .collect())
+    }
+}
+
+impl Weights for Encoder {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn simhash(&self, repetition: usize, projection: usize, dimension: usize) -> f32 {
+        let row = repetition * self.config.num_simhash_projections + projection;
+        self.simhash[row * self.config.dimension + dimension]
+    }
+
+    fn projection(&self, repetition: usize, row: usize, dimension: usize) -> f32 {
+        let row = repetition * self.config.projection_dimension + row;
+        self.projection[row * self.config.dimension + dimension]
+    }
+
+    fn sketch(&self, index: usize, _final_dimension: usize) -> (usize, f32) {
+        let (slot, sign) = self.sketch[index];
+        (slot as usize, sign)
+    }
+}
+
+/// Shared encoding loop; both weight sources yield bit-identical output.
+fn encode_with<W: Weights>(
+    weights: &W,
+    vectors: &[Vec<f32>],
+    layout: &Layout,
+    mode: Mode,
+) -> Result<Vec<f32>, String> {
+    let config = weights.config();
+    let mut out = vec![0.0f32; layout.output_size];
+    let mut counts = vec![0usize; layout.partitions];
+    let mut partitions = vec![0usize; vectors.len()];
 
     for repetition in 0..config.num_repetitions {
-        for vector in &vectors {
-            let partition = partition_index(vector, &config, repetition);
-            let count_index = repetition * partitions + partition;
-            counts[count_index] += 1;
+        counts.fill(0);
+        for (vector, slot) in vectors.iter().zip(&mut partitions) {
+            let partition = partition_index(weights, vector, repetition);
+            *slot = partition;
+            counts[partition] += 1;
 
-            let base = repetition * repetition_size + partition * config.projection_dimension;
+            let base =
+                repetition * layout.repetition_size + partition * config.projection_dimension;
             add_projected(
+                weights,
                 &mut out,
                 base,
                 vector,
-                &config,
                 repetition,
                 mode,
-                counts[count_index],
+                counts[partition],
             )?;
         }
+
+        if config.fill_empty_partitions && matches!(mode, Mode::Document) {
+            for (partition, _) in counts.iter().enumerate().filter(|(_, count)| **count == 0) {
+                let nearest = nearest_partition_token(&partitions, partition);
+                let base =
+                    repetition * layout.repetition_size + partition * config.projection_dimension;
+                add_projected(
+                    weights,
+                    &mut out,
+                    base,
+                    &vectors[nearest],
+                    repetition,
+                    mode,
+                    1,
+                )?;
+            }
+        }
     }
 
     match config.final_projection_dimension {
-        Some(final_dimension) => count_sketch(&out, final_dimension, config.seed),
+        Some(final_dimension) => count_sketch(weights, &out, final_dimension),
         None => Ok(out),
     }
 }
 
+/// First token whose SimHash code has the smallest Hamming distance to `partition`.
+fn nearest_partition_token(partitions: &[usize], partition: usize) -> usize {
+    let mut best = (0, u32::MAX);
+    for (token, code) in partitions.iter().enumerate() {
+        let distance = (code ^ partition).count_ones();
+        if distance < best.1 {
+            best = (token, distance);
+        }
+    }
+    best.0
+}
+
+pub struct MuveraEncoderResource(pub Encoder);
+
+#[rustler::resource_impl]
+impl rustler::Resource for MuveraEncoderResource {}
+
 /// Validates shape and configuration before allocating the FDE output.
 fn validate(vectors: &[Vec<f32>], config: &Config) -> Result<(), String> {
     if vectors.is_empty() {
         return Err("empty vectors".to_string());
     }
+    validate_config(config)?;
+    validate_vectors(vectors, config)
+}
+
+fn validate_vectors(vectors: &[Vec<f32>], config: &Config) -> Result<(), String> {
+    if vectors
+        .iter()
+        .any(|vector| vector.len() != config.dimension)
+    {
+        return Err("dimension mismatch".to_string());
+    }
+    for vector in vectors {
+        crate::distances::validate_finite_vector(vector)?;
+    }
+    Ok(())
+}
+
+/// Checked `repetitions x rows x dimension` size of one cached weight table.
+fn encoder_table_size(repetitions: usize, rows: usize, dimension: usize) -> Result<usize, String> {
+    repetitions
+        .checked_mul(rows)
+        .and_then(|size| size.checked_mul(dimension))
+        .filter(|size| *size <= MAX_ENCODER_WEIGHTS)
+        .ok_or_else(|| "muvera encoder tables exceed safety limit".to_string())
+}
+
+fn validate_config(config: &Config) -> Result<(), String> {
     if config.dimension == 0 {
         return Err("dimension must be positive".to_string());
     }
@@ -93,51 +520,34 @@ fn validate(vectors: &[Vec<f32>], config: &Config) -> Result<(), String> {
     if config.final_projection_dimension == Some(0) {
         return Err("final_projection_dimension must be positive".to_string());
     }
-    if vectors
-        .iter()
-        .any(|vector| vector.len() != config.dimension)
-    {
-        return Err("dimension mismatch".to_string());
-    }
-    for vector in vectors {
-        crate::distances::validate_finite_vector(vector)?;
-    }
     Ok(())
 }
 
 /// Assigns one vector to a SimHash partition for a repetition.
-fn partition_index(vector: &[f32], config: &Config, repetition: usize) -> usize {
-    if config.num_simhash_projections == 0 {
-        return 0;
-    }
-
+fn partition_index<W: Weights>(weights: &W, vector: &[f32], repetition: usize) -> usize {
     let mut partition = 0usize;
-    for projection in 0..config.num_simhash_projections {
+    for projection in 0..weights.config().num_simhash_projections {
         let mut dot = 0.0f64;
         for (dimension, value) in vector.iter().enumerate() {
-            dot += f64::from(*value)
-                * f64::from(random_weight(
-                    config.seed,
-                    repetition,
-                    projection,
-                    dimension,
-                ));
+            dot +=
+                f64::from(*value) * f64::from(weights.simhash(repetition, projection, dimension));
         }
         partition = (partition << 1) + usize::from(dot >= 0.0);
     }
     partition
 }
 
-/// Adds either identity-projected or signed-projected coordinates into output.
-fn add_projected(
+/// Adds either identity-projected or randomly projected coordinates into output.
+fn add_projected<W: Weights>(
+    weights: &W,
     out: &mut [f32],
     base: usize,
     vector: &[f32],
-    config: &Config,
     repetition: usize,
     mode: Mode,
     count: usize,
 ) -> Result<(), String> {
+    let config = weights.config();
     if config.projection_dimension == config.dimension {
         for (offset, value) in vector.iter().enumerate() {
             accumulate(&mut out[base + offset], f64::from(*value), mode, count)?;
@@ -145,18 +555,13 @@ fn add_projected(
         return Ok(());
     }
 
-    for projection in 0..config.projection_dimension {
+    for row in 0..config.projection_dimension {
         let mut value = 0.0f64;
         for (dimension, coordinate) in vector.iter().enumerate() {
-            value += f64::from(*coordinate)
-                * f64::from(random_sign(
-                    config.seed.wrapping_add(17),
-                    repetition,
-                    projection,
-                    dimension,
-                ));
+            value +=
+                f64::from(*coordinate) * f64::from(weights.projection(repetition, row, dimension));
         }
-        accumulate(&mut out[base + projection], value, mode, count)?;
+        accumulate(&mut out[base + row], value, mode, count)?;
     }
     Ok(())
 }
@@ -177,28 +582,65 @@ fn accumulate(slot: &mut f32, value: f64, mode: Mode, count: usize) -> Result<()
 }
 
 /// Compresses the full FDE with a count-sketch style signed hash projection.
-fn count_sketch(input: &[f32], final_dimension: usize, seed: u64) -> Result<Vec<f32>, String> {
+fn count_sketch<W: Weights>(
+    weights: &W,
+    input: &[f32],
+    final_dimension: usize,
+) -> Result<Vec<f32>, String> {
     if final_dimension == 0 {
         return Err("final_projection_dimension must be positive".to_string());
     }
 
     let mut out = vec![0.0f32; final_dimension];
     for (index, value) in input.iter().enumerate() {
-        let slot = (hash4(seed, 0x9E37_79B9_7F4A_7C15, index as u64, 0) as usize) % final_dimension;
-        let sign = if hash4(seed, 0xD1B5_4A32_D192_ED03, index as u64, slot as u64) & 1 == 0 {
-            1.0
-        } else {
-            -1.0
-        };
-        let next = f64::from(out[slot]) + f64::from(sign * *value);
-        if !next.is_finite() || next < f64::from(f32::MIN) || next > f64::from(f32::MAX) {
-            return Err("encoding overflow".to_string());
-        }
-        out[slot] = next as f32;
+        let (slot, sign) = weights.sketch(index, final_dimension);
+        out[slot] = sketch_add(out[slot], sign, *value)?;
     }
     Ok(out)
 }
 
+/// Hashed count-sketch slot and sign for one full-FDE coordinate.
+fn sketch_target(seed: u64, index: usize, final_dimension: usize) -> (usize, f32) {
+    let slot = (hash4(seed, 0x9E37_79B9_7F4A_7C15, index as u64, 0) as usize) % final_dimension;
+    let sign = if hash4(seed, 0xD1B5_4A32_D192_ED03, index as u64, slot as u64) & 1 == 0 {
+        1.0
+    } else {
+        -1.0
+    };
+    (slot, sign)
+}
+
+fn sketch_add(current: f32, sign: f32, value: f32) -> Result<f32, String> {
+    let next = f64::from(current) + f64::from(sign * value);
+    if !next.is_finite() || next < f64::from(f32::MIN) || next > f64::from(f32::MAX) {
+        return Err("encoding overflow".to_string());
+    }
+    Ok(next as f32)
+}
+
+fn simhash_weight(config: &Config, repetition: usize, projection: usize, dimension: usize) -> f32 {
+    match config.simhash {
+        SimHash::Uniform => random_weight(config.seed, repetition, projection, dimension),
+        SimHash::Gaussian => gaussian_weight(config.seed, repetition, projection, dimension),
+    }
+}
+
+fn projection_weight(config: &Config, repetition: usize, row: usize, dimension: usize) -> f32 {
+    let seed = config.seed.wrapping_add(17);
+    match config.projection {
+        Projection::Rademacher => random_sign(seed, repetition, row, dimension),
+        Projection::Ams => {
+            let rows = config.projection_dimension as u64;
+            let bucket = hash4(seed, AMS_STREAM, repetition as u64, dimension as u64) % rows;
+            if bucket == row as u64 {
+                random_sign(seed ^ AMS_STREAM, repetition, 0, dimension)
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
 /// Generates a deterministic pseudo-random projection weight in `[-1.0, 1.0]`.
 fn random_weight(seed: u64, repetition: usize, projection: usize, dimension: usize) -> f32 {
     let hash = hash4(seed, repetition as u64, projection as u64, dimension as u64);
@@ -206,6 +648,13 @@ fn random_weight(seed: u64, repetition: usize, projection: usize, dimension: usi
     unit * 2.0 - 1.0
 }
 
+/// Generates a deterministic standard Gaussian weight with `gaussian`.
+fn gaussian_weight(seed: u64, repetition: usize, projection: usize, dimension: usize) -> f32 {
+    // `validate_config` keeps projections below 31, so five bits hold them.
+    let column = ((dimension as u64) << 5) | projection as u64;
+    gaussian(seed, GAUSSIAN_STREAM, repetition as u64, column) as f32
+}
+
 /// Generates a deterministic Rademacher sign for sparse random projection.
 fn random_sign(seed: u64, repetition: usize, projection: usize, dimension: usize) -> f32 {
     if hash4(seed, repetition as u64, projection as u64, dimension as u64) & 1 == 0 {
@@ -216,7 +665,7 @@ fn random_sign(seed: u64, repetition: usize, projection: usize, dimension: usize
 }
 
 /// Mixes four integer coordinates into one stable 64-bit hash.
-fn hash4(a: u64, b: u64, c: u64, d: u64) -> u64 {
+pub(crate) fn hash4(a: u64, b: u64, c: u64, d: u64) -> u64 {
     let mut x = a ^ b.rotate_left(17) ^ c.rotate_left(31) ^ d.rotate_left(47);
     x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
     x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
@@ -224,6 +673,24 @@ fn hash4(a: u64, b: u64, c: u64, d: u64) -> u64 {
     x ^ (x >> 31)
 }
 
+/// Deterministic, approximately standard normal sample from `hash4`.
+///
+/// Sums twelve 16-bit uniforms (Irwin-Hall), which has unit variance and
+/// needs only integer and exact IEEE arithmetic. Box-Muller would depend on
+/// `ln` and `cos`, which are not bit-reproducible across platform libms.
+pub(crate) fn gaussian(seed: u64, stream: u64, row: u64, column: u64) -> f64 {
+    let lanes: u64 = (0..3)
+        .map(|word| {
+            let hash = hash4(seed, stream ^ word, row, column);
+            (0..4)
+                .map(|lane| (hash >> (16 * lane)) & 0xFFFF)
+                .sum::<u64>()
+        })
+        .sum();
+    // Each lane maps to the open interval (0, 1) at `(lane + 0.5) / 2^16`.
+    (lanes as f64 + 6.0) / 65_536.0 - 6.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,6 +703,9 @@ mod tests {
             seed: 42,
             projection_dimension: 2,
             final_projection_dimension: None,
+            fill_empty_partitions: false,
+            simhash: SimHash::Uniform,
+            projection: Projection::Rademacher,
         }
     }
 
@@ -332,18 +802,24 @@ mod tests {
             encode(vectors, cfg, Mode::Query),
             Err("fde dimension exceeds safety limit".into())
         );
+
+        // Cached encoders bound their weight tables before allocating them.
+        for (dimension, projections, projection_dimension) in [
+            (usize::MAX, 1, 1),
+            (MAX_ENCODER_WEIGHTS + 1, 1, 1),
+            (16, 0, MAX_ENCODER_WEIGHTS / 8),
+        ] {
+            let cfg = Config::new(dimension, 1, projections, 0, projection_dimension, None);
+            assert_eq!(
+                Encoder::new(cfg).err(),
+                Some("muvera encoder tables exceed safety limit".into())
+            );
+        }
     }
 
     #[test]
     fn identity_projection_has_exact_sum_and_online_average_semantics() {
-        let cfg = Config {
-            dimension: 2,
-            num_repetitions: 1,
-            num_simhash_projections: 0,
-            seed: 0,
-            projection_dimension: 2,
-            final_projection_dimension: None,
-        };
+        let cfg = Config::new(2, 1, 0, 0, 2, None);
         let vectors = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![-2.0, 0.0]];
 
         assert_eq!(
@@ -401,18 +877,323 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cached_encoder_is_bit_identical_to_stateless_encoding() {
+        let vectors: Vec<Vec<f32>> = (0..7)
+            .map(|token| {
+                (0..6)
+                    .map(|dimension| (hash4(token, dimension, 5, 0) % 2_000) as f32 / 500.0 - 2.0)
+                    .collect()
+            })
+            .collect();
+        let mut configs = Vec::new();
+        for (projections, projection_dimension, final_projection_dimension) in [
+            (0, 6, None),
+            (2, 6, None),
+            (3, 4, None),
+            (3, 9, Some(17)),
+            (4, 6, Some(40)),
+        ] {
+            let base = Config::new(
+                6,
+                3,
+                projections,
+                1_234,
+                projection_dimension,
+                final_projection_dimension,
+            );
+            configs.push(base);
+            configs.push(Config {
+                fill_empty_partitions: true,
+                simhash: SimHash::Gaussian,
+                projection: Projection::Ams,
+                ..base
+            });
+        }
+
+        for cfg in configs {
+            let encoder = Encoder::new(cfg).unwrap();
+            for mode in [Mode::Query, Mode::Document] {
+                let expected = encode(vectors.clone(), cfg, mode).unwrap();
+                let cached = encoder.encode(&vectors, mode).unwrap();
+                assert_eq!(
+                    cached
+                        .iter()
+                        .map(|value| value.to_bits())
+                        .collect::<Vec<_>>(),
+                    expected
+                        .iter()
+                        .map(|value| value.to_bits())
+                        .collect::<Vec<_>>()
+                );
+
+                let batch = vec![vectors[..3].to_vec(), vectors.clone()];
+                let encoded = encoder.encode_batch(&batch, mode).unwrap();
+                assert_eq!(encoded[0], encode(batch[0].clone(), cfg, mode).unwrap());
+                assert_eq!(encoded[1], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn cached_encoder_rejects_invalid_configs_and_inputs() {
+        let mut cfg = config();
+        cfg.num_simhash_projections = 31;
+        assert!(Encoder::new(cfg).is_err());
+
+        let encoder = Encoder::new(config()).unwrap();
+        assert_eq!(
+            encoder.encode(&[], Mode::Query),
+            Err("empty vectors".into())
+        );
+        assert!(encoder.encode(&[vec![1.0]], Mode::Query).is_err());
+        assert!(encoder.encode(&[vec![f32::NAN, 0.0]], Mode::Query).is_err());
+        assert!(encoder
+            .encode_batch(&[vec![vec![1.0, 0.0]], vec![]], Mode::Document)
+            .is_err());
+    }
+
     #[test]
     fn count_sketch_detects_colliding_accumulation_overflow() {
+        let sketch = |input: &[f32], final_dimension: usize, seed: u64| {
+            let cfg = Config { seed, ..config() };
+            count_sketch(&Hashed(&cfg), input, final_dimension)
+        };
         let overflow_seed = (0..10_000u64)
-            .find(|seed| count_sketch(&[f32::MAX, f32::MAX], 1, *seed).is_err())
+            .find(|seed| sketch(&[f32::MAX, f32::MAX], 1, *seed).is_err())
             .expect("at least one seed must assign equal signs to both inputs");
         assert_eq!(
-            count_sketch(&[f32::MAX, f32::MAX], 1, overflow_seed),
+            sketch(&[f32::MAX, f32::MAX], 1, overflow_seed),
             Err("encoding overflow".into())
         );
         assert_eq!(
-            count_sketch(&[1.0, 2.0], 0, 0),
+            sketch(&[1.0, 2.0], 0, 0),
             Err("final_projection_dimension must be positive".into())
         );
     }
+
+    fn token(a: u64, b: u64, dimension: usize) -> Vec<f32> {
+        (0..dimension as u64)
+            .map(|coordinate| (hash4(a, b, coordinate, 9) % 2_000) as f32 / 1_000.0 - 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn threaded_document_batches_match_sequential_encoding_in_order() {
+        let encoder = Encoder::new(Config::new(8, 4, 3, 21, 6, Some(64))).unwrap();
+        let documents: Vec<(String, Vec<Vec<f32>>)> = (0..37)
+            .map(|document| {
+                let tokens = 1 + document as usize % 5;
+                (
+                    format!("doc-{document:02}"),
+                    (0..tokens as u64)
+                        .map(|index| token(document, index, 8))
+                        .collect(),
+                )
+            })
+            .collect();
+        let expected: Vec<(String, Vec<f32>)> = documents
+            .iter()
+            .map(|(id, vectors)| (id.clone(), encoder.encode(vectors, Mode::Document).unwrap()))
+            .collect();
+
+        for threads in [0, 1, 3, 64, 100_000] {
+            assert_eq!(
+                encoder.encode_documents(&documents, threads),
+                Ok(expected.clone())
+            );
+        }
+        assert_eq!(encoder.encode_documents(&[], 4), Ok(vec![]));
+
+        let mut invalid = documents;
+        invalid[30].1 = vec![vec![1.0]];
+        invalid[31].1 = vec![];
+        assert_eq!(
+            encoder.encode_documents(&invalid, 4),
+            Err("dimension mismatch".into())
+        );
+    }
+
+    #[test]
+    fn fingerprints_are_stable_and_cover_every_encoding_setting() {
+        let base = Config::new(128, 20, 5, 42, 16, Some(10_240));
+        assert_eq!(base.fingerprint(), 0x435C_1C5F_71B7_17BF);
+        assert_eq!(
+            Encoder::new(base).unwrap().fingerprint(),
+            base.fingerprint()
+        );
+
+        let variants = [
+            Config {
+                dimension: 64,
+                ..base
+            },
+            Config {
+                num_repetitions: 21,
+                ..base
+            },
+            Config {
+                num_simhash_projections: 4,
+                ..base
+            },
+            Config { seed: 43, ..base },
+            Config {
+                projection_dimension: 128,
+                ..base
+            },
+            Config {
+                final_projection_dimension: None,
+                ..base
+            },
+            Config {
+                final_projection_dimension: Some(10_239),
+                ..base
+            },
+            Config {
+                fill_empty_partitions: true,
+                ..base
+            },
+            Config {
+                simhash: SimHash::Gaussian,
+                ..base
+            },
+            Config {
+                projection: Projection::Ams,
+                ..base
+            },
+        ];
+        let mut seen = vec![base.fingerprint()];
+        for variant in variants {
+            assert!(!seen.contains(&variant.fingerprint()));
+            seen.push(variant.fingerprint());
+        }
+
+        assert_eq!(check_fingerprint(Some(seen[0]), seen[0]), Ok(()));
+        assert!(check_fingerprint(Some(seen[0]), seen[1]).is_err());
+        assert!(check_fingerprint(None, seen[0]).is_err());
+    }
+
+    #[test]
+    fn filling_empty_partitions_only_changes_document_encodings() {
+        let base = Config::new(4, 2, 3, 7, 4, None);
+        let filled = Config {
+            fill_empty_partitions: true,
+            ..base
+        };
+        let vectors = vec![token(1, 0, 4), token(1, 1, 4)];
+
+        assert_eq!(
+            encode(vectors.clone(), filled, Mode::Query),
+            encode(vectors.clone(), base, Mode::Query)
+        );
+        let plain = encode(vectors.clone(), base, Mode::Document).unwrap();
+        let document = encode(vectors.clone(), filled, Mode::Document).unwrap();
+        // Two tokens cover at most two of eight partitions per repetition;
+        // filling leaves no partition block empty and keeps occupied ones.
+        for (plain_block, block) in plain.chunks(4).zip(document.chunks(4)) {
+            assert!(block.iter().any(|value| *value != 0.0));
+            if plain_block.iter().any(|value| *value != 0.0) {
+                assert_eq!(plain_block, block);
+            } else {
+                assert!(vectors.iter().any(|vector| vector.as_slice() == block));
+            }
+        }
+    }
+
+    #[test]
+    fn filling_empty_partitions_improves_fde_recall_of_maxsim() {
+        let documents: Vec<Vec<Vec<f32>>> = (0..120)
+            .map(|document| (0..3).map(|index| token(document, index, 8)).collect())
+            .collect();
+        let recall = |config: Config| {
+            let encoder = Encoder::new(config).unwrap();
+            let fdes = encoder.encode_batch(&documents, Mode::Document).unwrap();
+            let mut found = 0;
+            for seed in 0..20 {
+                let query: Vec<Vec<f32>> =
+                    (0..4).map(|index| token(1_000 + seed, index, 8)).collect();
+                let encoded = encoder.encode(&query, Mode::Query).unwrap();
+                let rank = |scores: Vec<f32>| {
+                    let mut order: Vec<usize> = (0..scores.len()).collect();
+                    order.sort_by(|left, right| scores[*right].total_cmp(&scores[*left]));
+                    order
+                };
+                let exact = rank(
+                    documents
+                        .iter()
+                        .map(|document| {
+                            crate::multi_vector::score(
+                                &query,
+                                document,
+                                crate::distances::Metric::InnerProduct,
+                            )
+                            .unwrap()
+                        })
+                        .collect(),
+                );
+                let approximate = rank(
+                    fdes.iter()
+                        .map(|fde| crate::distances::dot(&encoded, fde))
+                        .collect(),
+                );
+                found += approximate[..10]
+                    .iter()
+                    .filter(|document| exact[..10].contains(document))
+                    .count();
+            }
+            found
+        };
+
+        let base = Config::new(8, 10, 4, 3, 8, None);
+        let plain = recall(base);
+        let filled = recall(Config {
+            fill_empty_partitions: true,
+            ..base
+        });
+        assert!(filled > plain, "filled {filled} plain {plain}");
+    }
+
+    #[test]
+    fn gaussian_simhash_and_ams_projection_weights_have_their_shapes() {
+        let cfg = Config {
+            simhash: SimHash::Gaussian,
+            projection: Projection::Ams,
+            ..Config::new(64, 4, 8, 11, 16, None)
+        };
+        let weights: Vec<f64> = (0..4)
+            .flat_map(|repetition| {
+                (0..8).flat_map(move |projection| {
+                    (0..64).map(move |dimension| {
+                        f64::from(simhash_weight(&cfg, repetition, projection, dimension))
+                    })
+                })
+            })
+            .collect();
+        let mean = weights.iter().sum::<f64>() / weights.len() as f64;
+        let variance = weights
+            .iter()
+            .map(|weight| (weight - mean).powi(2))
+            .sum::<f64>()
+            / weights.len() as f64;
+        assert!(mean.abs() < 0.1, "mean {mean}");
+        assert!((variance - 1.0).abs() < 0.15, "variance {variance}");
+        assert!(weights.iter().any(|weight| weight.abs() > 1.0));
+
+        for repetition in 0..4 {
+            for dimension in 0..64 {
+                let column: Vec<f32> = (0..16)
+                    .map(|row| projection_weight(&cfg, repetition, row, dimension))
+                    .collect();
+                assert_eq!(column.iter().filter(|weight| **weight != 0.0).count(), 1);
+                assert!(column
+                    .iter()
+                    .all(|weight| matches!(weight, -1.0 | 0.0 | 1.0)));
+            }
+        }
+
+        assert_eq!(SimHash::from_code(1), Ok(SimHash::Gaussian));
+        assert!(SimHash::from_code(2).is_err());
+        assert_eq!(Projection::from_code(1), Ok(Projection::Ams));
+        assert!(Projection::from_code(2).is_err());
+    }
 }
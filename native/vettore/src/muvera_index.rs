@@ -0,0 +1,251 @@
+//! Native end-to-end MUVERA retrieval index.
+//!
+//! Documents are stored twice: as a document-side FDE in an inner-product
+//! HNSW graph and as their original token matrix. A search encodes the query
+//! FDE, takes the best `candidates` documents by FDE inner product, then
+//! reranks them with exact inner-product MaxSim, the similarity MUVERA
+//! approximates. Hits report both scores so callers can inspect the gap.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::distances::Metric;
+use crate::hnsw::{HnswIndex, HnswParams};
+use crate::muvera::{Config, Encoder, Mode};
+
+pub struct MuveraIndex {
+    encoder: Encoder,
+    fdes: HnswIndex,
+    documents: HashMap<String, Vec<Vec<f32>>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MuveraHit {
+    pub id: String,
+    /// Exact inner-product MaxSim score.
+    pub score: f32,
+    /// FDE inner product that made the document a candidate.
+    pub fde_score: f32,
+}
+
+impl MuveraIndex {
+    /// Creates an empty index encoding with `config` over an HNSW FDE graph.
+    pub fn new(config: Config, params: HnswParams) -> Result<Self, String> {
+        Ok(Self {
+            encoder: Encoder::new(config)?,
+            fdes: HnswIndex::new(Metric::InnerProduct, params)?,
+            documents: HashMap::new(),
+        })
+    }
+
+    /// Fingerprint of the config every stored FDE was encoded with.
+    pub fn fingerprint(&self) -> u64 {
+        self.encoder.fingerprint()
+    }
+
+    /// Inserts or replaces one document's token vectors.
+    pub fn insert(&mut self, id: String, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+        let fde = self.encoder.encode(&vectors, Mode::Document)?;
+        self.fdes.insert(id.clone(), fde)?;
+        self.documents.insert(id, vectors);
+        Ok(())
+    }
+
+    /// Encodes a batch on native worker threads, then inserts every document.
+    pub fn insert_many(&mut self, documents: Vec<(String, Vec<Vec<f32>>)>) -> Result<(), String> {
+        let encoded = self.encoder.encode_documents(&documents, 0)?;
+        self.fdes.insert_many(encoded)?;
+        self.documents.extend(documents);
+        Ok(())
+    }
+
+    /// Deletes one document's FDE and token vectors.
+    pub fn delete(&mut self, id: &str) {
+        self.fdes.delete(id);
+        self.documents.remove(id);
+    }
+
+    /// Retrieves `candidates` documents by FDE and returns the best `limit`
+    /// by exact MaxSim.
+    pub fn search(
+        &self,
+        query_vectors: &[Vec<f32>],
+        candidates: usize,
+        limit: usize,
+    ) -> Result<Vec<MuveraHit>, String> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let fde = self.encoder.encode(query_vectors, Mode::Query)?;
+
+        let mut hits = self
+            .fdes
+            .search(&fde, usize::max(candidates, limit))?
+            .into_iter()
+            .map(|(id, fde_score)| {
+                let score = crate::multi_vector::score_validated(
+                    query_vectors,
+                    &self.documents[&id],
+                    Metric::InnerProduct,
+                )?;
+                Ok(MuveraHit {
+                    id,
+                    score,
+                    fde_score,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        hits.sort_by(|left, right| {
+            right
+                .score
+                .total_cmp(&left.score)
+                .then_with(|| left.id.cmp(&right.id))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+}
+
+pub struct MuveraIndexResource(pub RwLock<MuveraIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for MuveraIndexResource {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muvera::hash4;
+
+    fn params() -> HnswParams {
+        HnswParams {
+            m: 8,
+            m0: 16,
+            ef_construction: 64,
+            ef_search: 64,
+            max_level: 8,
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            fill_empty_partitions: true,
+            ..Config::new(8, 8, 3, 5, 8, None)
+        }
+    }
+
+    fn token(a: u64, b: u64) -> Vec<f32> {
+        (0..8)
+            .map(|coordinate| (hash4(a, b, coordinate, 4) % 2_000) as f32 / 1_000.0 - 1.0)
+            .collect()
+    }
+
+    fn documents(count: u64) -> Vec<(String, Vec<Vec<f32>>)> {
+        (0..count)
+            .map(|document| {
+                (
+                    format!("doc-{document:03}"),
+                    (0..2 + document % 4)
+                        .map(|index| token(document, index))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exhaustive_candidates_match_exact_maxsim_and_report_fde_scores() {
+        let documents = documents(80);
+        let mut index = MuveraIndex::new(config(), params()).unwrap();
+        index.insert_many(documents.clone()).unwrap();
+        let encoder = Encoder::new(config()).unwrap();
+        assert_eq!(index.fingerprint(), config().fingerprint());
+
+        for seed in 0..5 {
+            let query: Vec<Vec<f32>> = (0..3).map(|index| token(500 + seed, index)).collect();
+            let hits = index.search(&query, 80, 5).unwrap();
+            let expected =
+                crate::multi_vector::top_k(documents.clone(), &query, Metric::InnerProduct, 5)
+                    .unwrap();
+            assert_eq!(
+                hits.iter()
+                    .map(|hit| (hit.id.clone(), hit.score))
+                    .collect::<Vec<_>>(),
+                expected
+            );
+
+            let query_fde = encoder.encode(&query, Mode::Query).unwrap();
+            for hit in &hits {
+                let vectors = &documents.iter().find(|(id, _)| *id == hit.id).unwrap().1;
+                let document_fde = encoder.encode(vectors, Mode::Document).unwrap();
+                let fde_score = crate::distances::dot(&query_fde, &document_fde);
+                assert!((hit.fde_score - fde_score).abs() < 1.0e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn fde_candidates_recover_most_of_the_exact_top_k() {
+        let documents = documents(300);
+        let mut index = MuveraIndex::new(config(), params()).unwrap();
+        index.insert_many(documents.clone()).unwrap();
+
+        let mut found = 0;
+        for seed in 0..10 {
+            let query: Vec<Vec<f32>> = (0..3).map(|index| token(900 + seed, index)).collect();
+            let exact: Vec<String> =
+                crate::multi_vector::top_k(documents.clone(), &query, Metric::InnerProduct, 5)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect();
+            found += index
+                .search(&query, 60, 5)
+                .unwrap()
+                .iter()
+                .filter(|hit| exact.contains(&hit.id))
+                .count();
+        }
+        assert!(found >= 40, "recall {found}/50");
+    }
+
+    #[test]
+    fn replacements_and_deletes_update_both_stores() {
+        let mut index = MuveraIndex::new(config(), params()).unwrap();
+        index.insert("a".into(), vec![token(1, 0)]).unwrap();
+        index.insert("b".into(), vec![token(2, 0)]).unwrap();
+        index
+            .insert("a".into(), vec![token(3, 0), token(3, 1)])
+            .unwrap();
+        assert_eq!(index.documents["a"].len(), 2);
+
+        let hits = index.search(&[token(3, 1)], 10, 10).unwrap();
+        assert_eq!(hits.len(), 2);
+
+        index.delete("a");
+        index.delete("missing");
+        let hits = index.search(&[token(3, 1)], 10, 10).unwrap();
+        assert_eq!(
+            hits.into_iter().map(|hit| hit.id).collect::<Vec<_>>(),
+            vec!["b".to_string()]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configs_documents_and_queries() {
+        let mut invalid = config();
+        invalid.num_repetitions = 0;
+        assert!(MuveraIndex::new(invalid, params()).is_err());
+        assert!(MuveraIndex::new(config(), HnswParams { m: 0, ..params() }).is_err());
+
+        let mut index = MuveraIndex::new(config(), params()).unwrap();
+        assert!(index.insert("a".into(), vec![]).is_err());
+        assert!(index.insert("a".into(), vec![vec![1.0]]).is_err());
+        assert!(index
+            .insert_many(vec![("a".into(), vec![token(1, 0)]), ("b".into(), vec![])])
+            .is_err());
+        assert!(index.documents.is_empty());
+        assert!(index.search(&[], 4, 1).is_err());
+        assert!(index.search(&[vec![1.0]], 4, 1).is_err());
+        assert_eq!(index.search(&[token(1, 0)], 4, 0), Ok(vec![]));
+    }
+}
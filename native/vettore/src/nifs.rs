@@ -6,9 +6,51 @@
 
 use rustler::{NifResult, ResourceArc};
 
+use crate::aggregate::{Method as AggregateMethod, Params as AggregateParams};
+use crate::anomaly::{Params as AnomalyParams, Scores as AnomalyScores};
+use crate::binary::{BinaryIndex, BinaryResource};
+use crate::classifier::{Classifier, ClassifierResource, Vote};
+use crate::dedup::{Cluster, Representative};
 use crate::distances::Metric;
-use crate::flat::{FlatIndex, FlatResource};
+use crate::flat::{FlatIndex, FlatResource, NeighborGraph};
+use crate::fusion::{Combination, Normalization};
 use crate::hnsw::{HnswIndex, HnswParams, HnswResource};
+use crate::kmeans::Params as KmeansParams;
+use crate::multi_vector_index::{MultiVectorIndex, MultiVectorResource};
+use crate::muvera::{
+    Config as MuveraConfig, Encoder as MuveraEncoder, Mode as MuveraMode, MuveraEncoderResource,
+    Projection as MuveraProjection, SimHash as MuveraSimHash,
+};
+use crate::muvera_index::{MuveraIndex, MuveraIndexResource};
+use crate::pca::{Params as PcaParams, Pca, PcaResource};
+use crate::plaid::{
+    BuildParams as PlaidBuildParams, PlaidIndex, PlaidResource, SearchParams as PlaidSearchParams,
+};
+use crate::quantization::{Params as QuantizerParams, Quantizer, QuantizerResource, Rotation};
+use crate::rotation::{Kind as TransformKind, RandomTransform, RandomTransformResource};
+use crate::sparse::{SparseIndex, SparseResource};
+use crate::stats::{Accumulator, Params as StatsParams, StatsResource};
+use crate::text::{Bm25Params, TextIndex, TextResource};
+use crate::token_pooling::Reduction;
+
+/// Centroids, per-vector cluster labels, inertia, and iterations run.
+type KmeansResult<Label> = (Vec<Vec<f32>>, Vec<Label>, f64, usize);
+
+/// MUVERA `{dimension, num_repetitions, num_simhash_projections, seed,
+/// projection_dimension, final_projection_dimension}`.
+type MuveraConfigTerm = (usize, usize, usize, u64, usize, Option<usize>);
+
+/// Vector count, running mean, per-dimension variance and norm histogram.
+type StatsSummary = (u64, Vec<f32>, Vec<f32>, Vec<u64>);
+
+/// Aggregated vector plus the medoid's position or id, when one was chosen.
+type Aggregate<Medoid> = (Vec<f32>, Option<Medoid>);
+
+/// Per stored id, the kNN distance, LOF and isolation-forest scores.
+type AnomalyReport = Vec<(String, f32, f32, f32)>;
+
+/// MaxSim total and, per query vector, the best document position and contribution.
+type ExplainResult = (f32, Vec<(Option<usize>, f32)>);
 
 #[rustler::nif(schedule = "DirtyCpu")]
 /// Computes L2/Euclidean distance between two f32 vectors.
@@ -197,6 +239,211 @@ fn multi_vector_top_k(
         .and_then(|metric| crate::multi_vector::top_k(documents, &query_vectors, metric, limit)))
 }
 
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Computes one MaxSim score with per-query-vector weights.
+fn multi_vector_score_weighted(
+    query_vectors: Vec<Vec<f32>>,
+    document_vectors: Vec<Vec<f32>>,
+    weights: Vec<f32>,
+    metric_code: u8,
+) -> NifResult<Result<f32, String>> {
+    Ok(Metric::from_code(metric_code).and_then(|metric| {
+        crate::multi_vector::score_weighted(
+            &query_vectors,
+            &document_vectors,
+            Some(&weights),
+            metric,
+        )
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores and selects a multi-vector document batch with per-query-vector weights.
+fn multi_vector_top_k_weighted(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    query_vectors: Vec<Vec<f32>>,
+    weights: Vec<f32>,
+    metric_code: u8,
+    limit: usize,
+) -> NifResult<Result<Vec<(String, f32)>, String>> {
+    Ok(Metric::from_code(metric_code).and_then(|metric| {
+        crate::multi_vector::top_k_weighted(
+            documents,
+            &query_vectors,
+            Some(&weights),
+            metric,
+            limit,
+        )
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the MaxSim score plus each query vector's best document position and contribution.
+fn multi_vector_explain(
+    query_vectors: Vec<Vec<f32>>,
+    document_vectors: Vec<Vec<f32>>,
+    weights: Option<Vec<f32>>,
+    metric_code: u8,
+) -> NifResult<Result<ExplainResult, String>> {
+    Ok(Metric::from_code(metric_code).and_then(|metric| {
+        let explanation = crate::multi_vector::explain(
+            &query_vectors,
+            &document_vectors,
+            weights.as_deref(),
+            metric,
+        )?;
+        Ok((
+            explanation.score,
+            explanation
+                .matches
+                .into_iter()
+                .map(|token| (token.document_index, token.contribution))
+                .collect(),
+        ))
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Deduplicates, norm-prunes and Ward-pools one document's token vectors.
+fn multi_vector_reduce(
+    vectors: Vec<Vec<f32>>,
+    metric_code: u8,
+    dedup_threshold: Option<f32>,
+    max_tokens: Option<usize>,
+    pool_factor: usize,
+) -> NifResult<Result<Vec<Vec<f32>>, String>> {
+    let reduction = Reduction {
+        dedup_threshold,
+        max_tokens,
+        pool_factor,
+    };
+    Ok(Metric::from_code(metric_code).and_then(|metric| reduction.apply(vectors, metric)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Diversifies candidates with cached native maximal marginal relevance.
+fn mmr_rerank(
+    candidates: Vec<(String, f32)>,
+    vectors: Vec<(String, Vec<f32>)>,
+    metric_code: u8,
+    lambda: f32,
+    limit: usize,
+) -> NifResult<Result<Vec<(String, f32)>, String>> {
+    Ok(Metric::from_code(metric_code)
+        .and_then(|metric| crate::mmr::rerank(candidates, vectors, metric, lambda, limit)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Aggregates supplied vectors by mean, normalized mean, geometric median or medoid.
+fn aggregate_vectors(
+    vectors: Vec<Vec<f32>>,
+    weights: Option<Vec<f32>>,
+    method_code: u8,
+    metric_code: u8,
+    max_iterations: usize,
+    tolerance: f64,
+) -> NifResult<Result<Aggregate<usize>, String>> {
+    let vectors: Vec<&[f32]> = vectors.iter().map(Vec::as_slice).collect();
+    Ok(AggregateMethod::from_code(method_code).and_then(|method| {
+        let params = AggregateParams {
+            method,
+            metric: Metric::from_code(metric_code)?,
+            max_iterations,
+            tolerance,
+        };
+        crate::aggregate::aggregate(&vectors, weights.as_deref(), params)
+    }))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Clusters vectors with k-means++ seeding and Lloyd or mini-batch updates.
+fn kmeans_fit(
+    vectors: Vec<Vec<f32>>,
+    metric_code: u8,
+    k: usize,
+    max_iterations: usize,
+    tolerance: f64,
+    seed: u64,
+    batch_size: Option<usize>,
+) -> NifResult<Result<KmeansResult<usize>, String>> {
+    let vectors: Vec<&[f32]> = vectors.iter().map(Vec::as_slice).collect();
+    Ok(Metric::from_code(metric_code)
+        .and_then(|metric| {
+            crate::kmeans::fit(
+                &vectors,
+                metric,
+                KmeansParams {
+                    k,
+                    max_iterations,
+                    tolerance,
+                    seed,
+                    batch_size,
+                },
+            )
+        })
+        .map(|model| {
+            (
+                model.centroids,
+                model.assignments,
+                model.inertia,
+                model.iterations,
+            )
+        }))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Fuses best-first result lists with weighted Reciprocal Rank Fusion.
+fn fusion_rrf(
+    lists: Vec<Vec<(String, f32)>>,
+    weights: Option<Vec<f32>>,
+    k: f32,
+    limit: usize,
+) -> NifResult<Result<Vec<(String, f32)>, String>> {
+    Ok(crate::fusion::reciprocal_rank(
+        &lists,
+        weights.as_deref(),
+        k,
+        limit,
+    ))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Fuses normalized result scores with weighted CombSUM or CombMNZ.
+fn fusion_scores(
+    lists: Vec<Vec<(String, f32)>>,
+    weights: Option<Vec<f32>>,
+    metric_codes: Option<Vec<Option<u8>>>,
+    normalization_code: u8,
+    combination_code: u8,
+    limit: usize,
+) -> NifResult<Result<Vec<(String, f32)>, String>> {
+    let metrics = match metric_codes {
+        Some(codes) => match codes
+            .into_iter()
+            .map(|code| code.map(Metric::from_code).transpose())
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(metrics) => Some(metrics),
+            Err(error) => return Ok(Err(error)),
+        },
+        None => None,
+    };
+    Ok(
+        Normalization::from_code(normalization_code).and_then(|normalization| {
+            Combination::from_code(combination_code).and_then(|combination| {
+                crate::fusion::combine_scores(
+                    &lists,
+                    weights.as_deref(),
+                    metrics.as_deref(),
+                    normalization,
+                    combination,
+                    limit,
+                )
+            })
+        }),
+    )
+}
+
 #[rustler::nif(schedule = "DirtyCpu")]
 /// Creates a native exact flat index ordered by L2 distance.
 fn flat_new_l2() -> ResourceArc<FlatResource> {
@@ -283,6 +530,56 @@ fn flat_insert_many(
     guard.insert_many(vectors)
 }
 
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one MUVERA FDE in the flat index under a config fingerprint.
+fn flat_insert_fde(
+    index: ResourceArc<FlatResource>,
+    id: String,
+    vector: Vec<f32>,
+    fingerprint: u64,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.insert_fde(id, vector, fingerprint)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts a batch of MUVERA FDEs in the flat index under a config fingerprint.
+fn flat_insert_many_fde(
+    index: ResourceArc<FlatResource>,
+    vectors: Vec<(String, Vec<f32>)>,
+    fingerprint: u64,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.insert_many_fde(vectors, fingerprint)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts a batch into the flat index and feeds it to a stats accumulator.
+fn flat_insert_many_observed(
+    index: ResourceArc<FlatResource>,
+    vectors: Vec<(String, Vec<f32>)>,
+    stats: ResourceArc<StatsResource>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    let mut stats = stats
+        .0
+        .write()
+        .map_err(|_| "stats lock poisoned".to_string())?;
+    let observed: Vec<Vec<f32>> = vectors.iter().map(|(_, vector)| vector.clone()).collect();
+    stats.validate(&observed)?;
+    guard.insert_many(vectors)?;
+    stats.observe_many(&observed)
+}
+
 #[rustler::nif(schedule = "DirtyCpu")]
 /// Removes one vector from the native flat index.
 fn flat_delete(index: ResourceArc<FlatResource>, id: String) -> Result<(), String> {
@@ -308,6 +605,172 @@ fn flat_search(
     guard.search(&query, limit)
 }
 
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Runs a whole progressive-prefix funnel over the native flat index.
+fn flat_funnel_search(
+    index: ResourceArc<FlatResource>,
+    query: Vec<f32>,
+    stages: Vec<usize>,
+    candidates: usize,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.funnel_search(&query, &stages, candidates, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Builds the exact k-nearest-neighbour graph over every flat-index vector.
+fn flat_knn_graph(index: ResourceArc<FlatResource>, k: usize) -> Result<NeighborGraph, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.knn_graph(k)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Clusters the flat index's stored vectors under its own metric.
+fn flat_kmeans(
+    index: ResourceArc<FlatResource>,
+    k: usize,
+    max_iterations: usize,
+    tolerance: f64,
+    seed: u64,
+    batch_size: Option<usize>,
+) -> Result<KmeansResult<(String, usize)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    let (model, ids) = crate::kmeans::fit_flat(
+        &guard,
+        KmeansParams {
+            k,
+            max_iterations,
+            tolerance,
+            seed,
+            batch_size,
+        },
+    )?;
+    Ok((
+        model.centroids,
+        ids.into_iter().zip(model.assignments).collect(),
+        model.inertia,
+        model.iterations,
+    ))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Groups flat-index ids whose pairwise metric value passes `threshold`.
+fn flat_near_duplicates(
+    index: ResourceArc<FlatResource>,
+    threshold: f32,
+    representative_code: u8,
+) -> Result<Vec<Cluster>, String> {
+    let representative = Representative::from_code(representative_code)?;
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    crate::dedup::flat_clusters(&guard, threshold, representative)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Runs MMR over candidates whose vectors are read from the flat index.
+fn flat_mmr_rerank(
+    index: ResourceArc<FlatResource>,
+    candidates: Vec<(String, f32)>,
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    crate::mmr::rerank_flat(&guard, &candidates, lambda, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores query vectors by kNN distance, LOF and isolation against the flat index.
+fn flat_anomaly_scores(
+    index: ResourceArc<FlatResource>,
+    queries: Vec<Vec<f32>>,
+    k: usize,
+    trees: usize,
+    sample_size: usize,
+    seed: u64,
+) -> Result<Vec<(f32, f32, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    let params = AnomalyParams {
+        k,
+        trees,
+        sample_size,
+        seed,
+    };
+    Ok(crate::anomaly::flat_scores(&guard, &queries, params)?
+        .into_iter()
+        .map(anomaly_tuple)
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores every stored flat vector against the rest, ordered by id.
+fn flat_anomaly_report(
+    index: ResourceArc<FlatResource>,
+    k: usize,
+    trees: usize,
+    sample_size: usize,
+    seed: u64,
+) -> Result<AnomalyReport, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    let params = AnomalyParams {
+        k,
+        trees,
+        sample_size,
+        seed,
+    };
+    Ok(crate::anomaly::flat_report(&guard, params)?
+        .into_iter()
+        .map(|(id, scores)| {
+            let (knn, lof, isolation) = anomaly_tuple(scores);
+            (id, knn, lof, isolation)
+        })
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Aggregates stored flat vectors by id under the index metric.
+fn flat_aggregate(
+    index: ResourceArc<FlatResource>,
+    ids: Vec<String>,
+    weights: Option<Vec<f32>>,
+    method_code: u8,
+    max_iterations: usize,
+    tolerance: f64,
+) -> Result<Aggregate<String>, String> {
+    let method = AggregateMethod::from_code(method_code)?;
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    let params = AggregateParams {
+        method,
+        metric: guard.metric(),
+        max_iterations,
+        tolerance,
+    };
+    crate::aggregate::aggregate_flat(&guard, &ids, weights.as_deref(), params)
+}
+
 #[rustler::nif(schedule = "DirtyCpu")]
 /// Creates a native HNSW graph ordered by L2 distance.
 fn hnsw_new_l2(
@@ -400,6 +863,56 @@ fn hnsw_insert_many(
     guard.insert_many(vectors)
 }
 
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one MUVERA FDE in the HNSW graph under a config fingerprint.
+fn hnsw_insert_fde(
+    index: ResourceArc<HnswResource>,
+    id: String,
+    vector: Vec<f32>,
+    fingerprint: u64,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    guard.insert_fde(id, vector, fingerprint)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts a batch of MUVERA FDEs in the HNSW graph under a config fingerprint.
+fn hnsw_insert_many_fde(
+    index: ResourceArc<HnswResource>,
+    vectors: Vec<(String, Vec<f32>)>,
+    fingerprint: u64,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    guard.insert_many_fde(vectors, fingerprint)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts a batch into the HNSW graph and feeds it to a stats accumulator.
+fn hnsw_insert_many_observed(
+    index: ResourceArc<HnswResource>,
+    vectors: Vec<(String, Vec<f32>)>,
+    stats: ResourceArc<StatsResource>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    let mut stats = stats
+        .0
+        .write()
+        .map_err(|_| "stats lock poisoned".to_string())?;
+    let observed: Vec<Vec<f32>> = vectors.iter().map(|(_, vector)| vector.clone()).collect();
+    stats.validate(&observed)?;
+    guard.insert_many(vectors)?;
+    stats.observe_many(&observed)
+}
+
 #[rustler::nif(schedule = "DirtyCpu")]
 /// Removes one vector from the native HNSW graph.
 fn hnsw_delete(index: ResourceArc<HnswResource>, id: String) -> Result<(), String> {
@@ -426,53 +939,1180 @@ fn hnsw_search(
 }
 
 #[rustler::nif(schedule = "DirtyCpu")]
-/// Encodes query-side MUVERA/FDE vectors by summing projected partition vectors.
-fn muvera_encode_query(
-    vectors: Vec<Vec<f32>>,
-    dimension: usize,
-    num_repetitions: usize,
-    num_simhash_projections: usize,
-    seed: u64,
-    projection_dimension: usize,
-    final_projection_dimension: Option<usize>,
-) -> NifResult<Result<Vec<f32>, String>> {
-    Ok(crate::muvera::encode(
-        vectors,
-        crate::muvera::Config {
-            dimension,
-            num_repetitions,
-            num_simhash_projections,
-            seed,
-            projection_dimension,
-            final_projection_dimension,
-        },
-        crate::muvera::Mode::Query,
-    ))
+/// Builds an approximate k-nearest-neighbour graph through HNSW self-queries.
+fn hnsw_knn_graph(index: ResourceArc<HnswResource>, k: usize) -> Result<NeighborGraph, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    guard.knn_graph(k)
 }
 
 #[rustler::nif(schedule = "DirtyCpu")]
-/// Encodes document-side MUVERA/FDE vectors by averaging projected partition vectors.
-fn muvera_encode_document(
-    vectors: Vec<Vec<f32>>,
-    dimension: usize,
-    num_repetitions: usize,
-    num_simhash_projections: usize,
-    seed: u64,
-    projection_dimension: usize,
-    final_projection_dimension: Option<usize>,
-) -> NifResult<Result<Vec<f32>, String>> {
-    Ok(crate::muvera::encode(
-        vectors,
-        crate::muvera::Config {
-            dimension,
-            num_repetitions,
-            num_simhash_projections,
-            seed,
-            projection_dimension,
-            final_projection_dimension,
-        },
-        crate::muvera::Mode::Document,
-    ))
+/// Groups HNSW ids whose approximate range-search hits pass `threshold`.
+fn hnsw_near_duplicates(
+    index: ResourceArc<HnswResource>,
+    threshold: f32,
+    representative_code: u8,
+) -> Result<Vec<Cluster>, String> {
+    let representative = Representative::from_code(representative_code)?;
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    crate::dedup::hnsw_clusters(&guard, threshold, representative)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Runs MMR over candidates whose vectors are read from the HNSW graph.
+fn hnsw_mmr_rerank(
+    index: ResourceArc<HnswResource>,
+    candidates: Vec<(String, f32)>,
+    lambda: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    crate::mmr::rerank_hnsw(&guard, &candidates, lambda, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores query vectors by kNN distance, LOF and isolation against the hnsw index.
+fn hnsw_anomaly_scores(
+    index: ResourceArc<HnswResource>,
+    queries: Vec<Vec<f32>>,
+    k: usize,
+    trees: usize,
+    sample_size: usize,
+    seed: u64,
+) -> Result<Vec<(f32, f32, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    let params = AnomalyParams {
+        k,
+        trees,
+        sample_size,
+        seed,
+    };
+    Ok(crate::anomaly::hnsw_scores(&guard, &queries, params)?
+        .into_iter()
+        .map(anomaly_tuple)
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores every stored hnsw vector against the rest, ordered by id.
+fn hnsw_anomaly_report(
+    index: ResourceArc<HnswResource>,
+    k: usize,
+    trees: usize,
+    sample_size: usize,
+    seed: u64,
+) -> Result<AnomalyReport, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    let params = AnomalyParams {
+        k,
+        trees,
+        sample_size,
+        seed,
+    };
+    Ok(crate::anomaly::hnsw_report(&guard, params)?
+        .into_iter()
+        .map(|(id, scores)| {
+            let (knn, lof, isolation) = anomaly_tuple(scores);
+            (id, knn, lof, isolation)
+        })
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Aggregates stored hnsw vectors by id under the index metric.
+fn hnsw_aggregate(
+    index: ResourceArc<HnswResource>,
+    ids: Vec<String>,
+    weights: Option<Vec<f32>>,
+    method_code: u8,
+    max_iterations: usize,
+    tolerance: f64,
+) -> Result<Aggregate<String>, String> {
+    let method = AggregateMethod::from_code(method_code)?;
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    let params = AggregateParams {
+        method,
+        metric: guard.metric(),
+        max_iterations,
+        tolerance,
+    };
+    crate::aggregate::aggregate_hnsw(&guard, &ids, weights.as_deref(), params)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a native binary-quantized index that reranks by one metric.
+fn binary_new(metric_code: u8, keep_vectors: bool) -> Result<ResourceArc<BinaryResource>, String> {
+    let metric = Metric::from_code(metric_code)?;
+    Ok(ResourceArc::new(BinaryResource(std::sync::RwLock::new(
+        BinaryIndex::new(metric, keep_vectors),
+    ))))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one vector in the native binary index.
+fn binary_insert(
+    index: ResourceArc<BinaryResource>,
+    id: String,
+    vector: Vec<f32>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.insert(id, vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces a batch of vectors in the native binary index.
+fn binary_insert_many(
+    index: ResourceArc<BinaryResource>,
+    vectors: Vec<(String, Vec<f32>)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.insert_many(vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces precomputed packed codes in a code-only binary index.
+fn binary_insert_codes(
+    index: ResourceArc<BinaryResource>,
+    codes: Vec<(String, Vec<u64>)>,
+    dimensions: usize,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.insert_codes(codes, dimensions)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one entry from the native binary index.
+fn binary_delete(index: ResourceArc<BinaryResource>, id: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scans stored packed codes and returns external ids plus Hamming distances.
+fn binary_hamming_search(
+    index: ResourceArc<BinaryResource>,
+    query: Vec<u64>,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.hamming_search(&query, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Selects Hamming candidates and reranks them with held full vectors.
+fn binary_search(
+    index: ResourceArc<BinaryResource>,
+    query: Vec<f32>,
+    candidates: usize,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "binary lock poisoned".to_string())?;
+    guard.search(&query, candidates, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Trains a centred, optionally rotated, multi-bit binary quantizer.
+fn quantizer_train(
+    vectors: Vec<Vec<f32>>,
+    bits: usize,
+    rotation_code: u8,
+    iterations: usize,
+    seed: u64,
+) -> Result<ResourceArc<QuantizerResource>, String> {
+    let rotation = Rotation::from_code(rotation_code)?;
+    let vectors: Vec<&[f32]> = vectors.iter().map(Vec::as_slice).collect();
+    let quantizer = Quantizer::train(
+        &vectors,
+        QuantizerParams {
+            bits,
+            rotation,
+            iterations,
+            seed,
+        },
+    )?;
+    Ok(ResourceArc::new(QuantizerResource(quantizer)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the code bit length and mean training reconstruction error.
+fn quantizer_info(quantizer: ResourceArc<QuantizerResource>) -> (usize, f32) {
+    (quantizer.0.code_dimensions(), quantizer.0.expected_error())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes vectors as packed codes compatible with `binary_top_k`.
+fn quantizer_encode(
+    quantizer: ResourceArc<QuantizerResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<Vec<u64>>, String> {
+    vectors
+        .iter()
+        .map(|vector| quantizer.0.encode(vector))
+        .collect()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Bounds the L2 distance error each vector incurs when quantized.
+fn quantizer_error_bounds(
+    quantizer: ResourceArc<QuantizerResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<f32>, String> {
+    vectors
+        .iter()
+        .map(|vector| quantizer.0.error_bound(vector))
+        .collect()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Fits a PCA model with randomized SVD over a training sample.
+fn pca_fit(
+    vectors: Vec<Vec<f32>>,
+    components: usize,
+    whiten: bool,
+    power_iterations: usize,
+    seed: u64,
+) -> Result<ResourceArc<PcaResource>, String> {
+    let pca = Pca::fit(
+        &vectors,
+        PcaParams {
+            components,
+            whiten,
+            power_iterations,
+            seed,
+        },
+    )?;
+    Ok(ResourceArc::new(PcaResource(pca)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Projects one vector onto the fitted principal axes.
+fn pca_transform(pca: ResourceArc<PcaResource>, vector: Vec<f32>) -> Result<Vec<f32>, String> {
+    pca.0.transform(&vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Projects a batch of vectors onto the fitted principal axes.
+fn pca_transform_batch(
+    pca: ResourceArc<PcaResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<Vec<f32>>, String> {
+    pca.0.transform_batch(&vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Exports the mean, components, explained variances and whitening flag.
+fn pca_export(pca: ResourceArc<PcaResource>) -> crate::pca::Exported {
+    pca.0.export()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Rebuilds a PCA resource from exported model terms.
+fn pca_import(
+    mean: Vec<f64>,
+    components: Vec<Vec<f64>>,
+    explained_variance: Vec<f64>,
+    whiten: bool,
+) -> Result<ResourceArc<PcaResource>, String> {
+    let pca = Pca::import((mean, components, explained_variance, whiten))?;
+    Ok(ResourceArc::new(PcaResource(pca)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Builds a seeded orthogonal, Hadamard or sparse JL transform.
+fn random_transform_new(
+    kind_code: u8,
+    input: usize,
+    output: usize,
+    seed: u64,
+) -> Result<ResourceArc<RandomTransformResource>, String> {
+    let transform =
+        RandomTransform::new(TransformKind::from_code(kind_code)?, input, output, seed)?;
+    Ok(ResourceArc::new(RandomTransformResource(transform)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the input and output dimensions of a random transform.
+fn random_transform_info(transform: ResourceArc<RandomTransformResource>) -> (usize, usize) {
+    transform.0.dimensions()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Applies a random transform to one vector.
+fn random_transform_apply(
+    transform: ResourceArc<RandomTransformResource>,
+    vector: Vec<f32>,
+) -> Result<Vec<f32>, String> {
+    transform.0.apply(&vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Applies a random transform to a batch of vectors.
+fn random_transform_apply_batch(
+    transform: ResourceArc<RandomTransformResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<Vec<f32>>, String> {
+    transform.0.apply_batch(&vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates an empty labelled-vector classifier for one metric.
+fn classifier_new(metric_code: u8) -> Result<ResourceArc<ClassifierResource>, String> {
+    let classifier = Classifier::new(Metric::from_code(metric_code)?);
+    Ok(ResourceArc::new(ClassifierResource(
+        std::sync::RwLock::new(classifier),
+    )))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one labelled example.
+fn classifier_insert(
+    classifier: ResourceArc<ClassifierResource>,
+    id: String,
+    label: String,
+    vector: Vec<f32>,
+) -> Result<(), String> {
+    let mut guard = classifier
+        .0
+        .write()
+        .map_err(|_| "classifier lock poisoned".to_string())?;
+    guard.insert(id, label, vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces a batch of labelled examples.
+fn classifier_insert_many(
+    classifier: ResourceArc<ClassifierResource>,
+    examples: Vec<(String, String, Vec<f32>)>,
+) -> Result<(), String> {
+    let mut guard = classifier
+        .0
+        .write()
+        .map_err(|_| "classifier lock poisoned".to_string())?;
+    guard.insert_many(examples)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Deletes one labelled example.
+fn classifier_delete(
+    classifier: ResourceArc<ClassifierResource>,
+    id: String,
+) -> Result<(), String> {
+    let mut guard = classifier
+        .0
+        .write()
+        .map_err(|_| "classifier lock poisoned".to_string())?;
+    guard.delete(&id)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Pins a label centroid, or with `nil` returns it to the example mean.
+fn classifier_set_centroid(
+    classifier: ResourceArc<ClassifierResource>,
+    label: String,
+    vector: Option<Vec<f32>>,
+) -> Result<(), String> {
+    let mut guard = classifier
+        .0
+        .write()
+        .map_err(|_| "classifier lock poisoned".to_string())?;
+    guard.set_centroid(label, vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns per-label scores and confidences for one query.
+fn classifier_classify(
+    classifier: ResourceArc<ClassifierResource>,
+    query: Vec<f32>,
+    k: usize,
+    vote_code: u8,
+    temperature: f32,
+) -> Result<Vec<(String, f32, f32)>, String> {
+    let vote = Vote::from_code(vote_code)?;
+    let guard = classifier
+        .0
+        .read()
+        .map_err(|_| "classifier lock poisoned".to_string())?;
+    Ok(guard
+        .classify(query, k, vote, temperature)?
+        .into_iter()
+        .map(|prediction| (prediction.label, prediction.score, prediction.confidence))
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates an empty streaming vector statistics accumulator.
+fn stats_new(
+    reservoir_size: usize,
+    histogram_bins: usize,
+    max_norm: f32,
+    seed: u64,
+) -> Result<ResourceArc<StatsResource>, String> {
+    let accumulator = Accumulator::new(StatsParams {
+        reservoir_size,
+        histogram_bins,
+        max_norm,
+        seed,
+    })?;
+    Ok(ResourceArc::new(StatsResource(std::sync::RwLock::new(
+        accumulator,
+    ))))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Feeds a batch of vectors to a stats accumulator.
+fn stats_observe(stats: ResourceArc<StatsResource>, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+    let mut guard = stats
+        .0
+        .write()
+        .map_err(|_| "stats lock poisoned".to_string())?;
+    guard.observe_many(&vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the count, running mean, variance and norm histogram.
+fn stats_summary(stats: ResourceArc<StatsResource>) -> Result<StatsSummary, String> {
+    let guard = stats
+        .0
+        .read()
+        .map_err(|_| "stats lock poisoned".to_string())?;
+    let summary = guard.summary();
+    Ok((
+        summary.count,
+        summary.mean,
+        summary.variance,
+        summary.norm_histogram,
+    ))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the accumulator's reservoir sample.
+fn stats_sample(stats: ResourceArc<StatsResource>) -> Result<Vec<Vec<f32>>, String> {
+    let guard = stats
+        .0
+        .read()
+        .map_err(|_| "stats lock poisoned".to_string())?;
+    Ok(guard.sample().to_vec())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Scores drift from a baseline accumulator as mean shift, KS max, KS mean and MMD.
+fn stats_drift(
+    baseline: ResourceArc<StatsResource>,
+    current: ResourceArc<StatsResource>,
+) -> Result<(f32, f32, f32, f32), String> {
+    let drift = if std::ptr::eq(&*baseline, &*current) {
+        let guard = baseline
+            .0
+            .read()
+            .map_err(|_| "stats lock poisoned".to_string())?;
+        guard.drift(&guard)?
+    } else {
+        // Read locks are taken in address order so concurrent drift(a, b) and
+        // drift(b, a) cannot deadlock behind queued writers.
+        let baseline_first = std::ptr::from_ref(&*baseline) < std::ptr::from_ref(&*current);
+        let (first, second) = if baseline_first {
+            (&baseline, &current)
+        } else {
+            (&current, &baseline)
+        };
+        let first = first
+            .0
+            .read()
+            .map_err(|_| "stats lock poisoned".to_string())?;
+        let second = second
+            .0
+            .read()
+            .map_err(|_| "stats lock poisoned".to_string())?;
+        if baseline_first {
+            first.drift(&second)?
+        } else {
+            second.drift(&first)?
+        }
+    };
+    Ok((drift.mean_shift, drift.ks_max, drift.ks_mean, drift.mmd))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a native sparse inverted index scored by dot product.
+fn sparse_new() -> ResourceArc<SparseResource> {
+    ResourceArc::new(SparseResource(std::sync::RwLock::new(SparseIndex::new())))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one sparse vector in the native sparse index.
+fn sparse_insert(
+    index: ResourceArc<SparseResource>,
+    id: String,
+    vector: Vec<(u32, f32)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "sparse lock poisoned".to_string())?;
+    guard.insert(id, vector)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces a batch of sparse vectors in the native sparse index.
+fn sparse_insert_many(
+    index: ResourceArc<SparseResource>,
+    vectors: Vec<(String, Vec<(u32, f32)>)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "sparse lock poisoned".to_string())?;
+    guard.insert_many(vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one sparse vector from the native sparse index.
+fn sparse_delete(index: ResourceArc<SparseResource>, id: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "sparse lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Searches the native sparse index and returns external ids plus dot products.
+fn sparse_search(
+    index: ResourceArc<SparseResource>,
+    query: Vec<(u32, f32)>,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "sparse lock poisoned".to_string())?;
+    guard.search(query, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a native BM25 text index with tuning parameters and stopwords.
+fn text_new(k1: f32, b: f32, stopwords: Vec<String>) -> Result<ResourceArc<TextResource>, String> {
+    Ok(ResourceArc::new(TextResource(std::sync::RwLock::new(
+        TextIndex::new(Bm25Params { k1, b }, stopwords)?,
+    ))))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one document in the native text index.
+fn text_insert(index: ResourceArc<TextResource>, id: String, text: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "text lock poisoned".to_string())?;
+    guard.insert(id, &text)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces a batch of documents in the native text index.
+fn text_insert_many(
+    index: ResourceArc<TextResource>,
+    documents: Vec<(String, String)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "text lock poisoned".to_string())?;
+    guard.insert_many(documents)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one document from the native text index.
+fn text_delete(index: ResourceArc<TextResource>, id: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "text lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Searches the native text index and returns external ids plus BM25 scores.
+fn text_search(
+    index: ResourceArc<TextResource>,
+    query: String,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "text lock poisoned".to_string())?;
+    guard.search(&query, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a native multi-vector index over a token-level HNSW graph.
+fn multi_vector_index_new(
+    metric_code: u8,
+    m: usize,
+    m0: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    max_level: usize,
+) -> Result<ResourceArc<MultiVectorResource>, String> {
+    let metric = Metric::from_code(metric_code)?;
+    let params = HnswParams {
+        m,
+        m0,
+        ef_construction,
+        ef_search,
+        max_level,
+    };
+
+    Ok(ResourceArc::new(MultiVectorResource(
+        std::sync::RwLock::new(MultiVectorIndex::new(metric, params)?),
+    )))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one document's token vectors in the multi-vector index.
+fn multi_vector_index_insert(
+    index: ResourceArc<MultiVectorResource>,
+    id: String,
+    vectors: Vec<Vec<f32>>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "multi-vector lock poisoned".to_string())?;
+    guard.insert(id, vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces a batch of documents in the multi-vector index.
+fn multi_vector_index_insert_many(
+    index: ResourceArc<MultiVectorResource>,
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "multi-vector lock poisoned".to_string())?;
+    guard.insert_many(documents)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one document and its token vectors from the multi-vector index.
+fn multi_vector_index_delete(
+    index: ResourceArc<MultiVectorResource>,
+    id: String,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "multi-vector lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Gathers token-ANN candidates and returns document ids plus exact MaxSim scores.
+fn multi_vector_index_search(
+    index: ResourceArc<MultiVectorResource>,
+    query_vectors: Vec<Vec<f32>>,
+    token_candidates: usize,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "multi-vector lock poisoned".to_string())?;
+    guard.search(&query_vectors, token_candidates, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Sets or clears the token reduction applied on multi-vector index inserts.
+fn multi_vector_index_set_reduction(
+    index: ResourceArc<MultiVectorResource>,
+    reduction: Option<(Option<f32>, Option<usize>, usize)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "multi-vector lock poisoned".to_string())?;
+    guard.set_reduction(
+        reduction.map(|(dedup_threshold, max_tokens, pool_factor)| Reduction {
+            dedup_threshold,
+            max_tokens,
+            pool_factor,
+        }),
+    )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Trains PLAID centroids and residual buckets and indexes the documents.
+fn plaid_build(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    metric_code: u8,
+    centroids: usize,
+    residual_bits: usize,
+    max_iterations: usize,
+    seed: u64,
+) -> Result<ResourceArc<PlaidResource>, String> {
+    let metric = Metric::from_code(metric_code)?;
+    let params = PlaidBuildParams {
+        centroids,
+        residual_bits,
+        max_iterations,
+        seed,
+    };
+
+    Ok(ResourceArc::new(PlaidResource(std::sync::RwLock::new(
+        PlaidIndex::build(documents, metric, params)?,
+    ))))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one document using the trained PLAID centroids.
+fn plaid_insert(
+    index: ResourceArc<PlaidResource>,
+    id: String,
+    vectors: Vec<Vec<f32>>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "plaid lock poisoned".to_string())?;
+    guard.insert(id, vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one document from the PLAID index.
+fn plaid_delete(index: ResourceArc<PlaidResource>, id: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "plaid lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Runs centroid interaction and pruning, then returns exact MaxSim scores.
+fn plaid_search(
+    index: ResourceArc<PlaidResource>,
+    query_vectors: Vec<Vec<f32>>,
+    ncells: usize,
+    centroid_threshold: f32,
+    ndocs: usize,
+    rescore_fraction: f32,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "plaid lock poisoned".to_string())?;
+    guard.search(
+        &query_vectors,
+        PlaidSearchParams {
+            ncells,
+            centroid_threshold,
+            ndocs,
+            rescore_fraction,
+            limit,
+        },
+    )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes query-side MUVERA/FDE vectors by summing projected partition vectors.
+fn muvera_encode_query(
+    vectors: Vec<Vec<f32>>,
+    dimension: usize,
+    num_repetitions: usize,
+    num_simhash_projections: usize,
+    seed: u64,
+    projection_dimension: usize,
+    final_projection_dimension: Option<usize>,
+) -> NifResult<Result<Vec<f32>, String>> {
+    Ok(crate::muvera::encode(
+        vectors,
+        crate::muvera::Config::new(
+            dimension,
+            num_repetitions,
+            num_simhash_projections,
+            seed,
+            projection_dimension,
+            final_projection_dimension,
+        ),
+        crate::muvera::Mode::Query,
+    ))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes document-side MUVERA/FDE vectors by averaging projected partition vectors.
+fn muvera_encode_document(
+    vectors: Vec<Vec<f32>>,
+    dimension: usize,
+    num_repetitions: usize,
+    num_simhash_projections: usize,
+    seed: u64,
+    projection_dimension: usize,
+    final_projection_dimension: Option<usize>,
+) -> NifResult<Result<Vec<f32>, String>> {
+    Ok(crate::muvera::encode(
+        vectors,
+        crate::muvera::Config::new(
+            dimension,
+            num_repetitions,
+            num_simhash_projections,
+            seed,
+            projection_dimension,
+            final_projection_dimension,
+        ),
+        crate::muvera::Mode::Document,
+    ))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes one query statelessly under a full MUVERA config and `options`.
+fn muvera_encode_query_with_options(
+    vectors: Vec<Vec<f32>>,
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+) -> Result<Vec<f32>, String> {
+    crate::muvera::encode(
+        vectors,
+        muvera_config(config, options)?,
+        crate::muvera::Mode::Query,
+    )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes one document statelessly under a full MUVERA config and `options`.
+fn muvera_encode_document_with_options(
+    vectors: Vec<Vec<f32>>,
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+) -> Result<Vec<f32>, String> {
+    crate::muvera::encode(
+        vectors,
+        muvera_config(config, options)?,
+        crate::muvera::Mode::Document,
+    )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a MUVERA encoder that caches its hashed projection matrices.
+/// `options` is `{fill_empty_partitions, simhash_code, projection_code}`.
+fn muvera_encoder_new(
+    dimension: usize,
+    num_repetitions: usize,
+    num_simhash_projections: usize,
+    seed: u64,
+    projection_dimension: usize,
+    final_projection_dimension: Option<usize>,
+    options: (bool, u8, u8),
+) -> Result<ResourceArc<MuveraEncoderResource>, String> {
+    let config = (
+        dimension,
+        num_repetitions,
+        num_simhash_projections,
+        seed,
+        projection_dimension,
+        final_projection_dimension,
+    );
+    Ok(ResourceArc::new(MuveraEncoderResource(muvera_encoder(
+        config, options,
+    )?)))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes one query with a cached MUVERA encoder.
+fn muvera_encoder_encode_query(
+    encoder: ResourceArc<MuveraEncoderResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<f32>, String> {
+    encoder.0.encode(&vectors, MuveraMode::Query)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes one document with a cached MUVERA encoder.
+fn muvera_encoder_encode_document(
+    encoder: ResourceArc<MuveraEncoderResource>,
+    vectors: Vec<Vec<f32>>,
+) -> Result<Vec<f32>, String> {
+    encoder.0.encode(&vectors, MuveraMode::Document)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes a batch of queries with a cached MUVERA encoder.
+fn muvera_encoder_encode_queries(
+    encoder: ResourceArc<MuveraEncoderResource>,
+    batch: Vec<Vec<Vec<f32>>>,
+) -> Result<Vec<Vec<f32>>, String> {
+    encoder.0.encode_batch(&batch, MuveraMode::Query)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes a batch of documents with a cached MUVERA encoder.
+fn muvera_encoder_encode_documents(
+    encoder: ResourceArc<MuveraEncoderResource>,
+    batch: Vec<Vec<Vec<f32>>>,
+) -> Result<Vec<Vec<f32>>, String> {
+    encoder.0.encode_batch(&batch, MuveraMode::Document)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes many keyed documents into MUVERA FDEs on native worker threads.
+fn muvera_encode_documents(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+    threads: usize,
+) -> Result<Vec<(String, Vec<f32>)>, String> {
+    muvera_encoder(config, options)?.encode_documents(&documents, threads)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes many keyed documents and inserts their FDEs into a flat index.
+fn muvera_encode_documents_into_flat(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+    threads: usize,
+    index: ResourceArc<FlatResource>,
+) -> Result<(), String> {
+    let encoder = muvera_encoder(config, options)?;
+    let encoded = encoder.encode_documents(&documents, threads)?;
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.insert_many_fde(encoded, encoder.fingerprint())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes many keyed documents and inserts their FDEs into an HNSW graph.
+fn muvera_encode_documents_into_hnsw(
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+    threads: usize,
+    index: ResourceArc<HnswResource>,
+) -> Result<(), String> {
+    let encoder = muvera_encoder(config, options)?;
+    let encoded = encoder.encode_documents(&documents, threads)?;
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    guard.insert_many_fde(encoded, encoder.fingerprint())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Creates a MUVERA index with an HNSW FDE graph and exact MaxSim rerank.
+fn muvera_index_new(
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+    m: usize,
+    m0: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    max_level: usize,
+) -> Result<ResourceArc<MuveraIndexResource>, String> {
+    let params = HnswParams {
+        m,
+        m0,
+        ef_construction,
+        ef_search,
+        max_level,
+    };
+    let config = muvera_config(config, options)?;
+
+    Ok(ResourceArc::new(MuveraIndexResource(
+        std::sync::RwLock::new(MuveraIndex::new(config, params)?),
+    )))
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Inserts or replaces one document in the MUVERA index.
+fn muvera_index_insert(
+    index: ResourceArc<MuveraIndexResource>,
+    id: String,
+    vectors: Vec<Vec<f32>>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "muvera lock poisoned".to_string())?;
+    guard.insert(id, vectors)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Encodes and inserts a batch of documents into the MUVERA index.
+fn muvera_index_insert_many(
+    index: ResourceArc<MuveraIndexResource>,
+    documents: Vec<(String, Vec<Vec<f32>>)>,
+) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "muvera lock poisoned".to_string())?;
+    guard.insert_many(documents)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Removes one document from the MUVERA index.
+fn muvera_index_delete(index: ResourceArc<MuveraIndexResource>, id: String) -> Result<(), String> {
+    let mut guard = index
+        .0
+        .write()
+        .map_err(|_| "muvera lock poisoned".to_string())?;
+    guard.delete(&id);
+    Ok(())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Retrieves FDE candidates and returns ids with exact MaxSim and FDE scores.
+fn muvera_index_search(
+    index: ResourceArc<MuveraIndexResource>,
+    query_vectors: Vec<Vec<f32>>,
+    candidates: usize,
+    limit: usize,
+) -> Result<Vec<(String, f32, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "muvera lock poisoned".to_string())?;
+    Ok(guard
+        .search(&query_vectors, candidates, limit)?
+        .into_iter()
+        .map(|hit| (hit.id, hit.score, hit.fde_score))
+        .collect())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Derives the versioned MUVERA config fingerprint.
+fn muvera_fingerprint(config: MuveraConfigTerm, options: (bool, u8, u8)) -> Result<u64, String> {
+    Ok(muvera_config(config, options)?.fingerprint())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the config fingerprint of a cached MUVERA encoder.
+fn muvera_encoder_fingerprint(encoder: ResourceArc<MuveraEncoderResource>) -> u64 {
+    encoder.0.fingerprint()
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Returns the config fingerprint of every FDE in the MUVERA index.
+fn muvera_index_fingerprint(index: ResourceArc<MuveraIndexResource>) -> Result<u64, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "muvera lock poisoned".to_string())?;
+    Ok(guard.fingerprint())
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Searches MUVERA FDEs in a flat index after checking the query fingerprint.
+fn flat_search_fde(
+    index: ResourceArc<FlatResource>,
+    query: Vec<f32>,
+    fingerprint: u64,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "flat lock poisoned".to_string())?;
+    guard.search_fde(&query, fingerprint, limit)
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+/// Searches MUVERA FDEs in an HNSW graph after checking the query fingerprint.
+fn hnsw_search_fde(
+    index: ResourceArc<HnswResource>,
+    query: Vec<f32>,
+    fingerprint: u64,
+    limit: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let guard = index
+        .0
+        .read()
+        .map_err(|_| "hnsw lock poisoned".to_string())?;
+    guard.search_fde(&query, fingerprint, limit)
+}
+
+fn muvera_encoder(
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+) -> Result<MuveraEncoder, String> {
+    MuveraEncoder::new(muvera_config(config, options)?)
+}
+
+fn muvera_config(
+    config: MuveraConfigTerm,
+    options: (bool, u8, u8),
+) -> Result<MuveraConfig, String> {
+    let (
+        dimension,
+        num_repetitions,
+        num_simhash_projections,
+        seed,
+        projection_dimension,
+        final_projection_dimension,
+    ) = config;
+    let (fill_empty_partitions, simhash_code, projection_code) = options;
+    Ok(MuveraConfig {
+        fill_empty_partitions,
+        simhash: MuveraSimHash::from_code(simhash_code)?,
+        projection: MuveraProjection::from_code(projection_code)?,
+        ..MuveraConfig::new(
+            dimension,
+            num_repetitions,
+            num_simhash_projections,
+            seed,
+            projection_dimension,
+            final_projection_dimension,
+        )
+    })
+}
+
+fn anomaly_tuple(scores: AnomalyScores) -> (f32, f32, f32) {
+    (scores.knn, scores.lof, scores.isolation)
 }
 
 rustler::init!("Elixir.Vettore.Nifs");
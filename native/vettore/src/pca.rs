@@ -0,0 +1,493 @@
+//! Native principal component analysis for dimensionality reduction.
+//!
+//! Fitting centres a sample and finds its top principal axes with a seeded
+//! randomized SVD: a Gaussian sketch of the column space is sharpened by
+//! power iterations, and the small projected problem is solved exactly with
+//! the Jacobi eigensolver used by the quantizers. No BLAS is required, and a
+//! fit only ever materialises `samples x (components + oversampling)` and
+//! `(components + oversampling) x dimensions` matrices.
+//!
+//! A fitted model exports its mean, components and explained variances as
+//! plain lists so collections can persist it and rebuild the same transform.
+
+use crate::quantization::jacobi_eigen;
+use crate::rotation::gaussian;
+
+/// Extra sketch columns beyond `components`, improving subspace accuracy.
+const OVERSAMPLING: usize = 10;
+
+/// Hash stream for the sketch matrix, distinct from rotation sampling.
+const SKETCH_STREAM: u64 = 0x7063_615F_736B_6574;
+
+/// Singular values at or below this fraction of the largest count as zero.
+const RANK_TOLERANCE: f64 = 1.0e-10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub components: usize,
+    pub whiten: bool,
+    pub power_iterations: usize,
+    pub seed: u64,
+}
+
+/// Mean, row-major `components x dimensions` axes, explained variances and
+/// the whitening flag, as exported for persistence.
+pub type Exported = (Vec<f64>, Vec<Vec<f64>>, Vec<f64>, bool);
+
+#[derive(Debug)]
+pub struct Pca {
+    mean: Vec<f64>,
+    /// Unit-length principal axes, strongest first.
+    components: Vec<Vec<f64>>,
+    /// Sample variance along each axis.
+    explained_variance: Vec<f64>,
+    whiten: bool,
+}
+
+impl Pca {
+    /// Fits the mean and top principal axes of `vectors`.
+    pub fn fit(vectors: &[Vec<f32>], params: Params) -> Result<Self, String> {
+        let Some(first) = vectors.first() else {
+            return Err("training vectors must not be empty".to_string());
+        };
+        let dimensions = first.len();
+        if dimensions == 0 {
+            return Err("vectors must not be empty".to_string());
+        }
+        for vector in vectors {
+            if vector.len() != dimensions {
+                return Err("dimension mismatch".to_string());
+            }
+            crate::distances::validate_finite_vector(vector)?;
+        }
+        if vectors.len() < 2 {
+            return Err("pca needs at least two training vectors".to_string());
+        }
+        if params.components == 0 || params.components > usize::min(dimensions, vectors.len() - 1) {
+            return Err(
+                "components must be between 1 and min(dimensions, samples - 1)".to_string(),
+            );
+        }
+
+        let count = vectors.len();
+        let mut mean = vec![0.0f64; dimensions];
+        for vector in vectors {
+            for (sum, value) in mean.iter_mut().zip(vector) {
+                *sum += f64::from(*value);
+            }
+        }
+        for sum in &mut mean {
+            *sum /= count as f64;
+        }
+        let centred: Vec<Vec<f64>> = vectors
+            .iter()
+            .map(|vector| {
+                vector
+                    .iter()
+                    .zip(&mean)
+                    .map(|(value, mean)| f64::from(*value) - mean)
+                    .collect()
+            })
+            .collect();
+
+        let width = usize::min(
+            params.components + OVERSAMPLING,
+            usize::min(dimensions, count),
+        );
+        let sketch: Vec<Vec<f64>> = (0..dimensions)
+            .map(|row| {
+                (0..width)
+                    .map(|column| gaussian(params.seed ^ SKETCH_STREAM, row, column))
+                    .collect()
+            })
+            .collect();
+
+        // Range finder: Q spans X Ω, refined by (X Xᵀ)^q with re-orthonormalization.
+        let mut basis = orthonormal_columns(multiply(&centred, &sketch), width);
+        for _ in 0..params.power_iterations {
+            let back =
+                orthonormal_columns(multiply_transposed(&centred, &basis, dimensions), width);
+            basis = orthonormal_columns(multiply(&centred, &back), width);
+        }
+
+        // B = Qᵀ X is small; its Gram matrix B Bᵀ carries the squared singular values.
+        let projected_rows = multiply_transposed(&basis, &centred, width);
+        let mut gram = vec![0.0f64; width * width];
+        for row in 0..width {
+            for column in row..width {
+                let value: f64 = projected_rows[row]
+                    .iter()
+                    .zip(&projected_rows[column])
+                    .map(|(left, right)| left * right)
+                    .sum();
+                gram[row * width + column] = value;
+                gram[column * width + row] = value;
+            }
+        }
+        let (eigenvalues, eigenvectors) = jacobi_eigen(gram, width);
+        let mut order: Vec<usize> = (0..width).collect();
+        order.sort_by(|left, right| {
+            eigenvalues[*right]
+                .total_cmp(&eigenvalues[*left])
+                .then_with(|| left.cmp(right))
+        });
+
+        let largest = eigenvalues[order[0]].max(0.0).sqrt();
+        let mut components = Vec::with_capacity(params.components);
+        let mut explained_variance = Vec::with_capacity(params.components);
+        for index in order.into_iter().take(params.components) {
+            let singular = eigenvalues[index].max(0.0).sqrt();
+            if singular <= largest * RANK_TOLERANCE || singular == 0.0 {
+                return Err("training sample rank is below components".to_string());
+            }
+            // Right singular vector: Bᵀ u / σ.
+            let mut axis = vec![0.0f64; dimensions];
+            for (row, values) in projected_rows.iter().enumerate() {
+                let weight = eigenvectors[row * width + index] / singular;
+                for (slot, value) in axis.iter_mut().zip(values) {
+                    *slot += weight * value;
+                }
+            }
+            orient(&mut axis);
+            components.push(axis);
+            explained_variance.push(singular * singular / (count - 1) as f64);
+        }
+
+        Ok(Self {
+            mean,
+            components,
+            explained_variance,
+            whiten: params.whiten,
+        })
+    }
+
+    /// Rebuilds a model from `export` output, validating its shape.
+    pub fn import(exported: Exported) -> Result<Self, String> {
+        let (mean, components, explained_variance, whiten) = exported;
+        if mean.is_empty() || components.is_empty() {
+            return Err("pca model must not be empty".to_string());
+        }
+        if components.len() != explained_variance.len()
+            || components.iter().any(|axis| axis.len() != mean.len())
+        {
+            return Err("pca model shape mismatch".to_string());
+        }
+        let finite = |values: &[f64]| values.iter().all(|value| value.is_finite());
+        if !finite(&mean)
+            || !finite(&explained_variance)
+            || components.iter().any(|axis| !finite(axis))
+        {
+            return Err("pca model contains a non-finite value".to_string());
+        }
+        if whiten && explained_variance.iter().any(|variance| *variance <= 0.0) {
+            return Err("whitened pca needs positive variances".to_string());
+        }
+        Ok(Self {
+            mean,
+            components,
+            explained_variance,
+            whiten,
+        })
+    }
+
+    pub fn export(&self) -> Exported {
+        (
+            self.mean.clone(),
+            self.components.clone(),
+            self.explained_variance.clone(),
+            self.whiten,
+        )
+    }
+
+    /// Projects one vector onto the principal axes, whitening if configured.
+    pub fn transform(&self, vector: &[f32]) -> Result<Vec<f32>, String> {
+        if vector.len() != self.mean.len() {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+        let centred: Vec<f64> = vector
+            .iter()
+            .zip(&self.mean)
+            .map(|(value, mean)| f64::from(*value) - mean)
+            .collect();
+        self.components
+            .iter()
+            .zip(&self.explained_variance)
+            .map(|(axis, variance)| {
+                let mut value: f64 = axis.iter().zip(&centred).map(|(a, x)| a * x).sum();
+                if self.whiten {
+                    value /= variance.sqrt();
+                }
+                if value.is_finite() && value.abs() <= f64::from(f32::MAX) {
+                    Ok(value as f32)
+                } else {
+                    Err("pca transform overflow".to_string())
+                }
+            })
+            .collect()
+    }
+
+    pub fn transform_batch(&self, vectors: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, String> {
+        vectors
+            .iter()
+            .map(|vector| self.transform(vector))
+            .collect()
+    }
+}
+
+pub struct PcaResource(pub Pca);
+
+#[rustler::resource_impl]
+impl rustler::Resource for PcaResource {}
+
+/// `rows x inner` times `inner x width`, both row-major nested.
+fn multiply(left: &[Vec<f64>], right: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let width = right.first().map_or(0, Vec::len);
+    left.iter()
+        .map(|row| {
+            let mut out = vec![0.0f64; width];
+            for (value, right_row) in row.iter().zip(right) {
+                for (slot, weight) in out.iter_mut().zip(right_row) {
+                    *slot += value * weight;
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// `leftᵀ right` for `rows x columns` and `rows x width`, as `columns x width`.
+fn multiply_transposed(left: &[Vec<f64>], right: &[Vec<f64>], columns: usize) -> Vec<Vec<f64>> {
+    let width = right.first().map_or(0, Vec::len);
+    let mut out = vec![vec![0.0f64; width]; columns];
+    for (left_row, right_row) in left.iter().zip(right) {
+        for (out_row, value) in out.iter_mut().zip(left_row) {
+            for (slot, weight) in out_row.iter_mut().zip(right_row) {
+                *slot += value * weight;
+            }
+        }
+    }
+    out
+}
+
+/// Modified Gram-Schmidt over the columns of a row-major matrix, applied
+/// twice for stability. Dependent columns become zero and later drop out as
+/// zero singular values.
+fn orthonormal_columns(mut matrix: Vec<Vec<f64>>, width: usize) -> Vec<Vec<f64>> {
+    for _ in 0..2 {
+        for column in 0..width {
+            for previous in 0..column {
+                let projection: f64 = matrix.iter().map(|row| row[column] * row[previous]).sum();
+                for row in &mut matrix {
+                    row[column] -= projection * row[previous];
+                }
+            }
+            let norm = matrix
+                .iter()
+                .map(|row| row[column].powi(2))
+                .sum::<f64>()
+                .sqrt();
+            for row in &mut matrix {
+                row[column] = if norm > 1.0e-300 {
+                    row[column] / norm
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+    matrix
+}
+
+/// Fixes each axis's sign so its largest-magnitude coordinate is positive.
+fn orient(axis: &mut [f64]) {
+    let pivot = axis.iter().copied().fold(0.0f64, |best, value| {
+        if value.abs() > best.abs() {
+            value
+        } else {
+            best
+        }
+    });
+    if pivot < 0.0 {
+        for value in axis {
+            *value = -*value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muvera::hash4;
+
+    fn noise(a: usize, b: usize, stream: u64) -> f32 {
+        (hash4(a as u64, b as u64, stream, 0) % 2_000) as f32 / 1_000.0 - 1.0
+    }
+
+    /// Three latent factors with decreasing scale embedded in 24 dimensions,
+    /// plus an offset and a little isotropic noise.
+    fn planted(count: usize) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|row| {
+                let factors = [
+                    6.0 * noise(row, 0, 1),
+                    3.0 * noise(row, 1, 1),
+                    1.5 * noise(row, 2, 1),
+                ];
+                (0..24)
+                    .map(|dimension| {
+                        5.0 + factors
+                            .iter()
+                            .enumerate()
+                            .map(|(factor, scale)| scale * noise(factor, dimension, 2))
+                            .sum::<f32>()
+                            + 0.01 * noise(row, dimension, 3)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn params(components: usize) -> Params {
+        Params {
+            components,
+            whiten: false,
+            power_iterations: 4,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn recovers_a_planted_low_rank_subspace() {
+        let vectors = planted(400);
+        let pca = Pca::fit(&vectors, params(3)).unwrap();
+
+        let variances = &pca.explained_variance;
+        assert!(variances[0] > variances[1] && variances[1] > variances[2]);
+        for (left, axis) in pca.components.iter().enumerate() {
+            for (right, other) in pca.components.iter().enumerate() {
+                let dot: f64 = axis.iter().zip(other).map(|(a, b)| a * b).sum();
+                let expected = if left == right { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1.0e-9);
+            }
+        }
+
+        // Reconstructing from three coordinates loses only the planted noise.
+        for vector in vectors.iter().take(50) {
+            let reduced = pca.transform(vector).unwrap();
+            let mut rebuilt = pca.mean.clone();
+            for (coordinate, axis) in reduced.iter().zip(&pca.components) {
+                for (slot, value) in rebuilt.iter_mut().zip(axis) {
+                    *slot += f64::from(*coordinate) * value;
+                }
+            }
+            let error: f64 = rebuilt
+                .iter()
+                .zip(vector)
+                .map(|(left, right)| (left - f64::from(*right)).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            assert!(error < 0.1, "reconstruction error {error}");
+        }
+    }
+
+    #[test]
+    fn randomized_fit_matches_exact_covariance_eigenvalues() {
+        let vectors: Vec<Vec<f32>> = (0..120)
+            .map(|row| {
+                (0..10)
+                    .map(|dimension| noise(row, dimension, 4) * (1 + dimension) as f32)
+                    .collect()
+            })
+            .collect();
+        let pca = Pca::fit(&vectors, params(4)).unwrap();
+
+        let dimensions = 10;
+        let mut covariance = vec![0.0f64; dimensions * dimensions];
+        for vector in &vectors {
+            for row in 0..dimensions {
+                for column in 0..dimensions {
+                    covariance[row * dimensions + column] += (f64::from(vector[row])
+                        - pca.mean[row])
+                        * (f64::from(vector[column]) - pca.mean[column])
+                        / (vectors.len() - 1) as f64;
+                }
+            }
+        }
+        let (mut exact, _) = jacobi_eigen(covariance, dimensions);
+        exact.sort_by(|left, right| right.total_cmp(left));
+        for (fitted, expected) in pca.explained_variance.iter().zip(&exact) {
+            assert!(
+                (fitted - expected).abs() <= expected * 1.0e-6,
+                "{fitted} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn whitening_gives_unit_variance_training_coordinates() {
+        let vectors = planted(300);
+        let pca = Pca::fit(
+            &vectors,
+            Params {
+                whiten: true,
+                ..params(3)
+            },
+        )
+        .unwrap();
+        let reduced = pca.transform_batch(&vectors).unwrap();
+        for component in 0..3 {
+            let values: Vec<f64> = reduced
+                .iter()
+                .map(|row| f64::from(row[component]))
+                .collect();
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let variance = values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / (values.len() - 1) as f64;
+            assert!(mean.abs() < 1.0e-4);
+            assert!((variance - 1.0).abs() < 1.0e-4, "variance {variance}");
+        }
+    }
+
+    #[test]
+    fn exported_models_round_trip_to_identical_transforms() {
+        let vectors = planted(100);
+        let pca = Pca::fit(&vectors, params(2)).unwrap();
+        let restored = Pca::import(pca.export()).unwrap();
+        assert_eq!(
+            pca.transform_batch(&vectors).unwrap(),
+            restored.transform_batch(&vectors).unwrap()
+        );
+        assert_eq!(
+            Pca::fit(&vectors, params(2)).unwrap().export(),
+            pca.export()
+        );
+
+        let (mean, components, variances, _) = pca.export();
+        assert!(Pca::import((mean.clone(), components.clone(), vec![1.0], false)).is_err());
+        assert!(Pca::import((vec![1.0], components.clone(), variances.clone(), false)).is_err());
+        assert!(Pca::import((mean.clone(), vec![], vec![], false)).is_err());
+        assert!(Pca::import((mean, components, vec![0.0, 1.0], true)).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_samples_and_queries() {
+        assert!(Pca::fit(&[], params(1)).is_err());
+        assert!(Pca::fit(&[vec![1.0, 2.0]], params(1)).is_err());
+        assert!(Pca::fit(&[vec![1.0, 2.0], vec![1.0]], params(1)).is_err());
+        assert!(Pca::fit(&[vec![1.0, f32::NAN], vec![1.0, 2.0]], params(1)).is_err());
+        assert!(Pca::fit(&[vec![1.0, 2.0], vec![2.0, 1.0]], params(2)).is_err());
+        assert!(Pca::fit(&[vec![1.0, 2.0], vec![2.0, 1.0]], params(0)).is_err());
+        assert_eq!(
+            Pca::fit(&[vec![1.0, 2.0], vec![1.0, 2.0], vec![1.0, 2.0]], params(1)).err(),
+            Some("training sample rank is below components".into())
+        );
+
+        let pca = Pca::fit(&planted(20), params(2)).unwrap();
+        assert!(pca.transform(&[1.0]).is_err());
+        assert!(pca.transform(&[f32::INFINITY; 24]).is_err());
+    }
+}
@@ -0,0 +1,662 @@
+//! Native PLAID-style multi-vector index.
+//!
+//! Token vectors are clustered with k-means; every token keeps its centroid id
+//! plus a residual compressed into `residual_bits` quantile buckets per
+//! dimension. Search narrows documents in three passes before exact MaxSim:
+//!
+//! 1. each query token probes its `ncells` best centroids' inverted lists;
+//! 2. centroid interaction scores candidates with centroid similarities only,
+//!    ignoring centroids no query token rates above `centroid_threshold`, and
+//!    keeps the best `ndocs` (at least `limit`);
+//! 3. decompressed tokens (centroid plus residual) rescore those documents and
+//!    keep a `rescore_fraction` of them, never fewer than `limit`.
+//!
+//! Residual compression only speeds up scoring; it saves no memory. Every
+//! document keeps its full-precision token vectors next to the codes, so the
+//! index is larger than the raw tokens and survivors get exact MaxSim from
+//! `multi_vector::score_validated`, keeping returned scores exact.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::distances::Metric;
+use crate::kmeans::Params as KmeansParams;
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+/// External id and internal document number of a ranked document.
+type Ranked = (String, u32);
+
+#[derive(Clone, Copy, Debug)]
+pub struct BuildParams {
+    pub centroids: usize,
+    pub residual_bits: usize,
+    pub max_iterations: usize,
+    pub seed: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchParams {
+    pub ncells: usize,
+    pub centroid_threshold: f32,
+    pub ndocs: usize,
+    /// Share of the `ndocs` interaction survivors kept after decompression.
+    pub rescore_fraction: f32,
+    pub limit: usize,
+}
+
+struct Document {
+    id: String,
+    /// Full-precision tokens for the final exact rescore.
+    vectors: Vec<Vec<f32>>,
+    codes: Vec<u32>,
+    /// Packed residual bucket ids, `vectors.len() * dimensions` entries.
+    residuals: Vec<u8>,
+}
+
+pub struct PlaidIndex {
+    metric: Metric,
+    dimensions: usize,
+    residual_bits: usize,
+    centroids: Vec<Vec<f32>>,
+    /// Ascending residual bucket boundaries shared by every dimension.
+    cutoffs: Vec<f32>,
+    /// Reconstruction value of each residual bucket.
+    bucket_weights: Vec<f32>,
+    /// Centroid id to the documents owning at least one token in that cell.
+    cells: Vec<Vec<u32>>,
+    ids: HashMap<String, u32>,
+    documents: HashMap<u32, Document>,
+    next_document: u32,
+}
+
+impl PlaidIndex {
+    /// Trains centroids and residual buckets on `documents` and indexes them.
+    pub fn build(
+        documents: Vec<(String, Vec<Vec<f32>>)>,
+        metric: Metric,
+        params: BuildParams,
+    ) -> Result<Self, String> {
+        if !matches!(metric, Metric::Cosine | Metric::L2) {
+            return Err("unsupported plaid metric".to_string());
+        }
+        if !matches!(params.residual_bits, 1 | 2 | 4 | 8) {
+            return Err("residual_bits must be 1, 2, 4 or 8".to_string());
+        }
+
+        let documents = documents
+            .into_iter()
+            .map(|(id, vectors)| prepare_document(metric, vectors).map(|vectors| (id, vectors)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tokens: Vec<&[f32]> = documents
+            .iter()
+            .flat_map(|(_, vectors)| vectors.iter().map(Vec::as_slice))
+            .collect();
+        let Some(first) = tokens.first() else {
+            return Err("training documents must not be empty".to_string());
+        };
+        let dimensions = first.len();
+        if tokens.iter().any(|token| token.len() != dimensions) {
+            return Err("dimension mismatch".to_string());
+        }
+
+        let model = crate::kmeans::fit(
+            &tokens,
+            metric,
+            KmeansParams {
+                k: usize::min(params.centroids, tokens.len()),
+                max_iterations: params.max_iterations,
+                tolerance: 1.0e-6,
+                seed: params.seed,
+                batch_size: None,
+            },
+        )?;
+
+        let mut residuals: Vec<f32> = tokens
+            .iter()
+            .zip(&model.assignments)
+            .flat_map(|(token, centroid)| {
+                token
+                    .iter()
+                    .zip(&model.centroids[*centroid])
+                    .map(|(value, centre)| value - centre)
+            })
+            .collect();
+        residuals.sort_unstable_by(f32::total_cmp);
+        let buckets = 1usize << params.residual_bits;
+        let cutoffs: Vec<f32> = (1..buckets)
+            .map(|bucket| residuals[bucket * residuals.len() / buckets])
+            .collect();
+        let mut sums = vec![(0.0f64, 0usize); buckets];
+        for residual in &residuals {
+            let bucket = cutoffs.partition_point(|cutoff| cutoff <= residual);
+            sums[bucket].0 += f64::from(*residual);
+            sums[bucket].1 += 1;
+        }
+        let bucket_weights = sums
+            .iter()
+            .enumerate()
+            .map(|(bucket, (sum, count))| {
+                if *count > 0 {
+                    (sum / *count as f64) as f32
+                } else {
+                    // Empty buckets sit between equal cutoffs.
+                    cutoffs[bucket.saturating_sub(1)]
+                }
+            })
+            .collect();
+
+        let mut index = Self {
+            metric,
+            dimensions,
+            residual_bits: params.residual_bits,
+            cells: vec![Vec::new(); model.centroids.len()],
+            centroids: model.centroids,
+            cutoffs,
+            bucket_weights,
+            ids: HashMap::new(),
+            documents: HashMap::new(),
+            next_document: 0,
+        };
+        for (id, vectors) in documents {
+            index.insert_prepared(id, vectors)?;
+        }
+        Ok(index)
+    }
+
+    /// Inserts or replaces one document using the trained centroids.
+    pub fn insert(&mut self, id: String, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+        let vectors = prepare_document(self.metric, vectors)?;
+        if vectors[0].len() != self.dimensions {
+            return Err("dimension mismatch".to_string());
+        }
+        self.insert_prepared(id, vectors)
+    }
+
+    /// Deletes one document and drops it from every inverted list.
+    pub fn delete(&mut self, id: &str) {
+        let Some(document) = self.ids.remove(id) else {
+            return;
+        };
+        let Some(stored) = self.documents.remove(&document) else {
+            return;
+        };
+        let codes: HashSet<u32> = stored.codes.into_iter().collect();
+        for code in codes {
+            self.cells[code as usize].retain(|owner| *owner != document);
+        }
+    }
+
+    /// Runs the pruned PLAID pipeline and returns ids with exact MaxSim scores.
+    pub fn search(
+        &self,
+        query_vectors: &[Vec<f32>],
+        params: SearchParams,
+    ) -> Result<Vec<(String, f32)>, String> {
+        if params.centroid_threshold.is_nan() {
+            return Err("centroid_threshold must not be NaN".to_string());
+        }
+        if !(params.rescore_fraction > 0.0 && params.rescore_fraction <= 1.0) {
+            return Err("rescore_fraction must be in (0, 1]".to_string());
+        }
+        if params.ndocs < params.limit {
+            return Err("ndocs must be at least limit".to_string());
+        }
+        crate::multi_vector::validate_standalone_vectors(query_vectors)?;
+        if query_vectors.is_empty() || params.limit == 0 || params.ncells == 0 {
+            return Ok(Vec::new());
+        }
+        if query_vectors[0].len() != self.dimensions {
+            return Err("dimension mismatch".to_string());
+        }
+        let query = query_vectors
+            .iter()
+            .map(|vector| prepare(self.metric, vector.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Query-token by centroid similarity table, reused by every stage.
+        let centroid_scores = query
+            .iter()
+            .map(|token| {
+                self.centroids
+                    .iter()
+                    .map(|centroid| similarity(self.metric, token, centroid))
+                    .collect::<Result<Vec<f32>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut candidates = HashSet::new();
+        for scores in &centroid_scores {
+            let mut order: Vec<usize> = (0..scores.len()).collect();
+            order.sort_by(|left, right| {
+                scores[*right]
+                    .total_cmp(&scores[*left])
+                    .then_with(|| left.cmp(right))
+            });
+            for cell in order.into_iter().take(params.ncells) {
+                candidates.extend(self.cells[cell].iter().copied());
+            }
+        }
+
+        let kept: Vec<bool> = (0..self.centroids.len())
+            .map(|centroid| {
+                centroid_scores
+                    .iter()
+                    .any(|scores| scores[centroid] >= params.centroid_threshold)
+            })
+            .collect();
+        let interaction = self.top_documents(candidates, params.ndocs, |document| {
+            let codes: HashSet<u32> = document
+                .codes
+                .iter()
+                .copied()
+                .filter(|code| kept[*code as usize])
+                .collect();
+            Ok(centroid_scores
+                .iter()
+                .map(|scores| {
+                    codes
+                        .iter()
+                        .map(|code| scores[*code as usize])
+                        .fold(0.0f32, f32::max)
+                })
+                .sum())
+        })?;
+
+        let decompressed_limit = usize::max(
+            params.limit,
+            (params.ndocs as f64 * f64::from(params.rescore_fraction)).ceil() as usize,
+        );
+        let decompressed = self.top_documents(interaction, decompressed_limit, |document| {
+            let approximate = self.decompress(document);
+            crate::multi_vector::score_validated(&query, &approximate, self.metric)
+        })?;
+
+        Ok(self
+            .ranked_documents(decompressed, params.limit, |document| {
+                crate::multi_vector::score_validated(&query, &document.vectors, self.metric)
+            })?
+            .into_iter()
+            .map(|((id, _), score)| (id, score))
+            .collect())
+    }
+
+    fn top_documents(
+        &self,
+        documents: impl IntoIterator<Item = u32>,
+        limit: usize,
+        score: impl Fn(&Document) -> Result<f32, String>,
+    ) -> Result<Vec<u32>, String> {
+        Ok(self
+            .ranked_documents(documents, limit, score)?
+            .into_iter()
+            .map(|((_, document), _)| document)
+            .collect())
+    }
+
+    /// Keeps the best `limit` documents with their scores, best first.
+    fn ranked_documents(
+        &self,
+        documents: impl IntoIterator<Item = u32>,
+        limit: usize,
+        score: impl Fn(&Document) -> Result<f32, String>,
+    ) -> Result<Vec<(Ranked, f32)>, String> {
+        let mut heap = BinaryHeap::new();
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        for document in documents {
+            let stored = &self.documents[&document];
+            let hit = Hit::scored((stored.id.clone(), document), score(stored)?);
+            push_top_k(&mut heap, hit, limit);
+        }
+        Ok(sorted_hits(heap))
+    }
+
+    fn insert_prepared(&mut self, id: String, vectors: Vec<Vec<f32>>) -> Result<(), String> {
+        self.delete(&id);
+        let document = self.next_document;
+        self.next_document = document
+            .checked_add(1)
+            .ok_or_else(|| "plaid document numbers exhausted".to_string())?;
+
+        let mut codes = Vec::with_capacity(vectors.len());
+        let mut buckets = Vec::with_capacity(vectors.len() * self.dimensions);
+        for vector in &vectors {
+            let code = self.nearest_centroid(vector)?;
+            for (value, centre) in vector.iter().zip(&self.centroids[code]) {
+                let residual = value - centre;
+                buckets.push(self.cutoffs.partition_point(|cutoff| *cutoff <= residual) as u8);
+            }
+            codes.push(code as u32);
+        }
+
+        let distinct: HashSet<u32> = codes.iter().copied().collect();
+        for code in distinct {
+            self.cells[code as usize].push(document);
+        }
+        self.ids.insert(id.clone(), document);
+        self.documents.insert(
+            document,
+            Document {
+                id,
+                vectors,
+                codes,
+                residuals: pack(&buckets, self.residual_bits),
+            },
+        );
+        Ok(())
+    }
+
+    fn nearest_centroid(&self, vector: &[f32]) -> Result<usize, String> {
+        let mut best = (0, f32::NEG_INFINITY);
+        for (index, centroid) in self.centroids.iter().enumerate() {
+            let score = similarity(self.metric, vector, centroid)?;
+            if score > best.1 {
+                best = (index, score);
+            }
+        }
+        Ok(best.0)
+    }
+
+    /// Rebuilds approximate tokens from centroid ids and residual buckets.
+    fn decompress(&self, document: &Document) -> Vec<Vec<f32>> {
+        let buckets = unpack(
+            &document.residuals,
+            self.residual_bits,
+            document.codes.len() * self.dimensions,
+        );
+        document
+            .codes
+            .iter()
+            .zip(buckets.chunks_exact(self.dimensions))
+            .map(|(code, residuals)| {
+                self.centroids[*code as usize]
+                    .iter()
+                    .zip(residuals)
+                    .map(|(centre, bucket)| centre + self.bucket_weights[*bucket as usize])
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+pub struct PlaidResource(pub RwLock<PlaidIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for PlaidResource {}
+
+fn prepare_document(metric: Metric, vectors: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, String> {
+    if vectors.is_empty() {
+        return Err("document must have at least one vector".to_string());
+    }
+    crate::multi_vector::validate_standalone_vectors(&vectors)?;
+    vectors
+        .into_iter()
+        .map(|vector| prepare(metric, vector))
+        .collect()
+}
+
+/// Cosine indexes keep unit-length tokens, like the k-means spherical geometry.
+fn prepare(metric: Metric, vector: Vec<f32>) -> Result<Vec<f32>, String> {
+    if metric == Metric::Cosine {
+        crate::distances::normalize_l2(vector)
+    } else {
+        Ok(vector)
+    }
+}
+
+fn similarity(metric: Metric, left: &[f32], right: &[f32]) -> Result<f32, String> {
+    let raw = if metric == Metric::Cosine {
+        crate::distances::cosine(left, right)?
+    } else {
+        crate::distances::compute(metric, left, right)?
+    };
+    Ok(crate::distances::similarity_value(metric, raw))
+}
+
+fn pack(buckets: &[u8], bits: usize) -> Vec<u8> {
+    let per_byte = 8 / bits;
+    buckets
+        .chunks(per_byte)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (slot, bucket)| byte | (bucket << (slot * bits)))
+        })
+        .collect()
+}
+
+fn unpack(packed: &[u8], bits: usize, len: usize) -> Vec<u8> {
+    let per_byte = 8 / bits;
+    let mask = ((1u16 << bits) - 1) as u8;
+    (0..len)
+        .map(|index| (packed[index / per_byte] >> ((index % per_byte) * bits)) & mask)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muvera::hash4;
+
+    type Documents = Vec<(String, Vec<Vec<f32>>)>;
+
+    fn value(a: usize, b: usize, c: usize, d: usize) -> f32 {
+        (hash4(a as u64, b as u64, c as u64, d as u64) % 2_000) as f32 / 1_000.0 - 1.0
+    }
+
+    /// Documents whose tokens orbit a few shared topics.
+    fn corpus(count: usize) -> Documents {
+        (0..count)
+            .map(|index| {
+                let tokens = 3 + index % 5;
+                (
+                    format!("doc-{index:03}"),
+                    (0..tokens)
+                        .map(|token| {
+                            let topic = (index * 3 + token) % 12;
+                            (0..16)
+                                .map(|dimension| {
+                                    3.0 * value(topic, dimension, 0, 1)
+                                        + 0.4 * value(index, token, dimension, 2)
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn build(metric: Metric, residual_bits: usize) -> (PlaidIndex, Documents) {
+        let documents = corpus(150);
+        let index = PlaidIndex::build(
+            documents.clone(),
+            metric,
+            BuildParams {
+                centroids: 24,
+                residual_bits,
+                max_iterations: 20,
+                seed: 9,
+            },
+        )
+        .unwrap();
+        (index, documents)
+    }
+
+    fn query(seed: usize) -> Vec<Vec<f32>> {
+        (0..4)
+            .map(|token| {
+                let topic = (seed * 5 + token * 7) % 12;
+                (0..16)
+                    .map(|dimension| {
+                        3.0 * value(topic, dimension, 0, 1) + 0.4 * value(seed, token, dimension, 3)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unpruned_search_matches_exact_maxsim() {
+        let (index, documents) = build(Metric::L2, 2);
+        let everything = SearchParams {
+            ncells: 24,
+            centroid_threshold: f32::NEG_INFINITY,
+            ndocs: 1_000,
+            rescore_fraction: 0.25,
+            limit: 10,
+        };
+        for seed in 0..5 {
+            let query = query(seed);
+            assert_eq!(
+                index.search(&query, everything).unwrap(),
+                crate::multi_vector::top_k(documents.clone(), &query, Metric::L2, 10).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn pruned_search_keeps_high_recall() {
+        let (index, documents) = build(Metric::Cosine, 4);
+        let knobs = SearchParams {
+            ncells: 4,
+            centroid_threshold: 0.3,
+            ndocs: 60,
+            rescore_fraction: 0.25,
+            limit: 10,
+        };
+        let mut found = 0;
+        for seed in 0..10 {
+            let query = query(seed);
+            let exact: Vec<String> =
+                crate::multi_vector::top_k(documents.clone(), &query, Metric::Cosine, 10)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect();
+            let hits = index.search(&query, knobs).unwrap();
+            assert_eq!(hits.len(), 10);
+            found += hits.iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+        assert!(found >= 80, "recall {found}/100");
+    }
+
+    #[test]
+    fn residual_buckets_round_trip_and_shrink_reconstruction_error() {
+        assert_eq!(
+            unpack(&pack(&[3, 0, 2, 1, 1], 2), 2, 5),
+            vec![3, 0, 2, 1, 1]
+        );
+        assert_eq!(pack(&[1, 0, 1, 1, 0, 0, 0, 1, 1], 1), vec![0b1000_1101, 1]);
+
+        let error = |bits| {
+            let (index, _) = build(Metric::L2, bits);
+            let mut total = 0.0f64;
+            for document in index.documents.values() {
+                for (original, approximate) in
+                    document.vectors.iter().zip(index.decompress(document))
+                {
+                    total += f64::from(
+                        crate::distances::compute(Metric::L2Squared, original, &approximate)
+                            .unwrap(),
+                    );
+                }
+            }
+            total
+        };
+        let (one, four) = (error(1), error(4));
+        assert!(four < one * 0.5, "1-bit {one} 4-bit {four}");
+    }
+
+    #[test]
+    fn inserts_and_deletes_update_inverted_lists() {
+        let (mut index, documents) = build(Metric::L2, 2);
+        let everything = SearchParams {
+            ncells: 24,
+            centroid_threshold: f32::NEG_INFINITY,
+            ndocs: 1_000,
+            rescore_fraction: 0.25,
+            limit: 1,
+        };
+        let target = documents[7].1.clone();
+        index.insert("copy".into(), target.clone()).unwrap();
+        index.delete("doc-007");
+        index.delete("missing");
+        assert_eq!(index.search(&target, everything).unwrap()[0].0, "copy");
+        assert!(index
+            .cells
+            .iter()
+            .flatten()
+            .all(|doc| index.documents.contains_key(doc)));
+
+        index.insert("copy".into(), vec![vec![50.0; 16]]).unwrap();
+        assert_ne!(index.search(&target, everything).unwrap()[0].0, "copy");
+        assert_eq!(index.documents.len(), 150);
+    }
+
+    #[test]
+    fn rejects_invalid_builds_and_queries() {
+        let documents = corpus(4);
+        let params = BuildParams {
+            centroids: 4,
+            residual_bits: 2,
+            max_iterations: 5,
+            seed: 1,
+        };
+        assert!(PlaidIndex::build(documents.clone(), Metric::InnerProduct, params).is_err());
+        assert!(PlaidIndex::build(
+            documents.clone(),
+            Metric::L2,
+            BuildParams {
+                residual_bits: 3,
+                ..params
+            }
+        )
+        .is_err());
+        assert!(PlaidIndex::build(vec![], Metric::L2, params).is_err());
+        assert!(PlaidIndex::build(vec![("a".into(), vec![])], Metric::L2, params).is_err());
+
+        let mut index = PlaidIndex::build(documents, Metric::L2, params).unwrap();
+        assert!(index.insert("bad".into(), vec![vec![1.0]]).is_err());
+        let search = SearchParams {
+            ncells: 2,
+            centroid_threshold: 0.0,
+            ndocs: 4,
+            rescore_fraction: 0.5,
+            limit: 2,
+        };
+        assert!(index.search(&[vec![1.0]], search).is_err());
+        assert!(index
+            .search(
+                &[vec![0.0; 16]],
+                SearchParams {
+                    centroid_threshold: f32::NAN,
+                    ..search
+                }
+            )
+            .is_err());
+        for invalid in [0.0, 1.5, f32::NAN] {
+            assert_eq!(
+                index.search(
+                    &[vec![0.0; 16]],
+                    SearchParams {
+                        rescore_fraction: invalid,
+                        ..search
+                    }
+                ),
+                Err("rescore_fraction must be in (0, 1]".into())
+            );
+        }
+        assert_eq!(
+            index.search(&[vec![0.0; 16]], SearchParams { ndocs: 1, ..search }),
+            Err("ndocs must be at least limit".into())
+        );
+        assert_eq!(index.search(&[], search), Ok(vec![]));
+        assert_eq!(
+            index.search(&[vec![0.0; 16]], SearchParams { limit: 0, ..search }),
+            Ok(vec![])
+        );
+    }
+}
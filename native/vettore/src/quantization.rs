@@ -0,0 +1,533 @@
+//! Native learned binary quantization beyond plain sign bits.
+//!
+//! Training centres vectors by the dataset mean, optionally applies a random
+//! (RaBitQ-style) or learned (ITQ) orthogonal rotation, and learns per-dimension
+//! quantile thresholds. Each dimension is stored as a thermometer code of
+//! `bits` bits, so `packed_hamming` over `code_dimensions()` bits equals the L1
+//! distance between quantization levels and works with `binary_top_k` as-is.
+//!
+//! Every level also has a reconstruction value. The distance from a rotated
+//! vector to its reconstruction bounds the L2 distance error through the
+//! triangle inequality: `| |q - x| - |q - x̂| | <= |x - x̂|`.
+
+use crate::rotation::orthogonal_rows;
+
+/// Jacobi sweeps used when orthogonalizing the ITQ rotation update.
+const JACOBI_SWEEPS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rotation {
+    None,
+    Random,
+    Itq,
+}
+
+impl Rotation {
+    /// Decodes the compact rotation selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Random),
+            2 => Ok(Self::Itq),
+            _ => Err("unknown rotation".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub bits: usize,
+    pub rotation: Rotation,
+    pub iterations: usize,
+    pub seed: u64,
+}
+
+#[derive(Debug)]
+pub struct Quantizer {
+    dimensions: usize,
+    bits: usize,
+    mean: Vec<f64>,
+    /// Row-major `dimensions x dimensions` matrix applied as `y = x R`.
+    rotation: Option<Vec<f64>>,
+    /// Ascending thresholds per dimension, `bits` each.
+    thresholds: Vec<Vec<f64>>,
+    /// Reconstruction values per dimension, `bits + 1` each.
+    levels: Vec<Vec<f64>>,
+    expected_error: f32,
+}
+
+impl Quantizer {
+    /// Learns centring, rotation, thresholds and reconstruction levels.
+    pub fn train(vectors: &[&[f32]], params: Params) -> Result<Self, String> {
+        if !matches!(params.bits, 1 | 2 | 4) {
+            return Err("bits must be 1, 2 or 4".to_string());
+        }
+        let Some(first) = vectors.first() else {
+            return Err("training vectors must not be empty".to_string());
+        };
+        let dimensions = first.len();
+        if dimensions == 0 {
+            return Err("vector must not be empty".to_string());
+        }
+        for vector in vectors {
+            if vector.len() != dimensions {
+                return Err("dimension mismatch".to_string());
+            }
+            crate::distances::validate_finite_vector(vector)?;
+        }
+
+        let count = vectors.len() as f64;
+        let mut mean = vec![0.0f64; dimensions];
+        for vector in vectors {
+            for (total, value) in mean.iter_mut().zip(vector.iter()) {
+                *total += f64::from(*value);
+            }
+        }
+        mean.iter_mut().for_each(|total| *total /= count);
+
+        let centred: Vec<Vec<f64>> = vectors
+            .iter()
+            .map(|vector| {
+                vector
+                    .iter()
+                    .zip(&mean)
+                    .map(|(value, mean)| f64::from(*value) - mean)
+                    .collect()
+            })
+            .collect();
+
+        let rotation = match params.rotation {
+            Rotation::None => None,
+            Rotation::Random => Some(orthogonal_rows(dimensions, dimensions, params.seed)?),
+            Rotation::Itq => Some(itq_rotation(&centred, params.iterations, params.seed)?),
+        };
+        let transformed: Vec<Vec<f64>> = centred
+            .iter()
+            .map(|vector| rotate(vector, rotation.as_deref()))
+            .collect();
+
+        let mut thresholds = Vec::with_capacity(dimensions);
+        let mut levels = Vec::with_capacity(dimensions);
+        for dimension in 0..dimensions {
+            let mut column: Vec<f64> = transformed.iter().map(|row| row[dimension]).collect();
+            column.sort_unstable_by(f64::total_cmp);
+            let cuts: Vec<f64> = (1..=params.bits)
+                .map(|cut| column[(cut * column.len() / (params.bits + 1)).min(column.len() - 1)])
+                .collect();
+
+            let mut sums = vec![(0.0f64, 0usize); params.bits + 1];
+            for value in &column {
+                let level = level_of(*value, &cuts);
+                sums[level].0 += value;
+                sums[level].1 += 1;
+            }
+            let reconstruction = sums
+                .iter()
+                .enumerate()
+                .map(|(level, (sum, members))| {
+                    if *members > 0 {
+                        sum / *members as f64
+                    } else {
+                        // An empty bucket sits between equal cuts; use its lower cut.
+                        cuts[level.saturating_sub(1)]
+                    }
+                })
+                .collect();
+            thresholds.push(cuts);
+            levels.push(reconstruction);
+        }
+
+        let mut quantizer = Self {
+            dimensions,
+            bits: params.bits,
+            mean,
+            rotation,
+            thresholds,
+            levels,
+            expected_error: 0.0,
+        };
+        let total_error: f64 = transformed
+            .iter()
+            .map(|vector| quantizer.residual(vector))
+            .sum();
+        quantizer.expected_error = (total_error / count) as f32;
+        Ok(quantizer)
+    }
+
+    /// Bit length of every code, for `packed_hamming` and `binary_top_k`.
+    pub fn code_dimensions(&self) -> usize {
+        self.dimensions * self.bits
+    }
+
+    /// Mean reconstruction error over the training set.
+    pub fn expected_error(&self) -> f32 {
+        self.expected_error
+    }
+
+    /// Encodes one vector as packed thermometer codes.
+    pub fn encode(&self, vector: &[f32]) -> Result<Vec<u64>, String> {
+        let transformed = self.transform(vector)?;
+        let mut words = vec![0u64; self.code_dimensions().div_ceil(64)];
+        for (dimension, value) in transformed.iter().enumerate() {
+            let level = level_of(*value, &self.thresholds[dimension]);
+            for step in 0..level {
+                let bit = dimension * self.bits + step;
+                words[bit / 64] |= 1u64 << (bit % 64);
+            }
+        }
+        Ok(words)
+    }
+
+    /// Upper bound on the L2 distance error introduced by quantizing `vector`.
+    pub fn error_bound(&self, vector: &[f32]) -> Result<f32, String> {
+        let transformed = self.transform(vector)?;
+        Ok(self.residual(&transformed) as f32)
+    }
+
+    fn transform(&self, vector: &[f32]) -> Result<Vec<f64>, String> {
+        if vector.len() != self.dimensions {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+        let centred: Vec<f64> = vector
+            .iter()
+            .zip(&self.mean)
+            .map(|(value, mean)| f64::from(*value) - mean)
+            .collect();
+        Ok(rotate(&centred, self.rotation.as_deref()))
+    }
+
+    fn residual(&self, transformed: &[f64]) -> f64 {
+        transformed
+            .iter()
+            .enumerate()
+            .map(|(dimension, value)| {
+                let level = level_of(*value, &self.thresholds[dimension]);
+                let error = value - self.levels[dimension][level];
+                error * error
+            })
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+pub struct QuantizerResource(pub Quantizer);
+
+#[rustler::resource_impl]
+impl rustler::Resource for QuantizerResource {}
+
+/// Number of ascending thresholds at or below `value`.
+fn level_of(value: f64, thresholds: &[f64]) -> usize {
+    thresholds.partition_point(|threshold| *threshold <= value)
+}
+
+fn rotate(vector: &[f64], rotation: Option<&[f64]>) -> Vec<f64> {
+    let Some(rotation) = rotation else {
+        return vector.to_vec();
+    };
+    let dimensions = vector.len();
+    let mut out = vec![0.0; dimensions];
+    for (row, value) in vector.iter().enumerate() {
+        for (column, slot) in out.iter_mut().enumerate() {
+            *slot += value * rotation[row * dimensions + column];
+        }
+    }
+    out
+}
+
+/// Learns an ITQ rotation by alternating sign codes and orthogonal Procrustes.
+fn itq_rotation(centred: &[Vec<f64>], iterations: usize, seed: u64) -> Result<Vec<f64>, String> {
+    let dimensions = centred[0].len();
+    let mut rotation = orthogonal_rows(dimensions, dimensions, seed)?;
+
+    for _ in 0..iterations {
+        // M = Vᵀ B with B = sign(V R); the best R for fixed B is polar(M).
+        let mut cross = vec![0.0f64; dimensions * dimensions];
+        for vector in centred {
+            let signs: Vec<f64> = rotate(vector, Some(&rotation))
+                .into_iter()
+                .map(|value| if value >= 0.0 { 1.0 } else { -1.0 })
+                .collect();
+            for (row, value) in vector.iter().enumerate() {
+                for (column, sign) in signs.iter().enumerate() {
+                    cross[row * dimensions + column] += value * sign;
+                }
+            }
+        }
+        rotation = polar(&cross, dimensions);
+    }
+    Ok(rotation)
+}
+
+/// Orthogonal polar factor `M (MᵀM)^{-1/2}` via a symmetric Jacobi eigensolve.
+fn polar(matrix: &[f64], dimensions: usize) -> Vec<f64> {
+    let mut gram = vec![0.0f64; dimensions * dimensions];
+    for row in 0..dimensions {
+        for column in 0..dimensions {
+            gram[row * dimensions + column] = (0..dimensions)
+                .map(|inner| matrix[inner * dimensions + row] * matrix[inner * dimensions + column])
+                .sum();
+        }
+    }
+    let (eigenvalues, eigenvectors) = jacobi_eigen(gram, dimensions);
+    let largest = eigenvalues.iter().copied().fold(0.0, f64::max);
+    let floor = largest * 1.0e-12;
+
+    // (MᵀM)^{-1/2} = E diag(λ^{-1/2}) Eᵀ with tiny eigenvalues clamped.
+    let mut inverse_root = vec![0.0f64; dimensions * dimensions];
+    for (index, eigenvalue) in eigenvalues.iter().enumerate() {
+        let scale = 1.0 / eigenvalue.max(floor).max(f64::MIN_POSITIVE).sqrt();
+        for row in 0..dimensions {
+            for column in 0..dimensions {
+                inverse_root[row * dimensions + column] += scale
+                    * eigenvectors[row * dimensions + index]
+                    * eigenvectors[column * dimensions + index];
+            }
+        }
+    }
+
+    let mut out = vec![0.0f64; dimensions * dimensions];
+    for row in 0..dimensions {
+        for column in 0..dimensions {
+            out[row * dimensions + column] = (0..dimensions)
+                .map(|inner| {
+                    matrix[row * dimensions + inner] * inverse_root[inner * dimensions + column]
+                })
+                .sum();
+        }
+    }
+    out
+}
+
+/// Cyclic Jacobi eigendecomposition; eigenvectors are returned as columns.
+pub(crate) fn jacobi_eigen(mut matrix: Vec<f64>, dimensions: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut vectors = vec![0.0f64; dimensions * dimensions];
+    for index in 0..dimensions {
+        vectors[index * dimensions + index] = 1.0;
+    }
+
+    for _ in 0..JACOBI_SWEEPS {
+        let off_diagonal: f64 = (0..dimensions)
+            .flat_map(|row| (0..dimensions).map(move |column| (row, column)))
+            .filter(|(row, column)| row != column)
+            .map(|(row, column)| matrix[row * dimensions + column].powi(2))
+            .sum();
+        let diagonal: f64 = (0..dimensions)
+            .map(|index| matrix[index * dimensions + index].powi(2))
+            .sum();
+        if off_diagonal <= diagonal * 1.0e-24 {
+            break;
+        }
+
+        for p in 0..dimensions {
+            for q in p + 1..dimensions {
+                let apq = matrix[p * dimensions + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let app = matrix[p * dimensions + p];
+                let aqq = matrix[q * dimensions + q];
+                let theta = (aqq - app) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..dimensions {
+                    let akp = matrix[k * dimensions + p];
+                    let akq = matrix[k * dimensions + q];
+                    matrix[k * dimensions + p] = c * akp - s * akq;
+                    matrix[k * dimensions + q] = s * akp + c * akq;
+                }
+                for k in 0..dimensions {
+                    let apk = matrix[p * dimensions + k];
+                    let aqk = matrix[q * dimensions + k];
+                    matrix[p * dimensions + k] = c * apk - s * aqk;
+                    matrix[q * dimensions + k] = s * apk + c * aqk;
+                }
+                for k in 0..dimensions {
+                    let vkp = vectors[k * dimensions + p];
+                    let vkq = vectors[k * dimensions + q];
+                    vectors[k * dimensions + p] = c * vkp - s * vkq;
+                    vectors[k * dimensions + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let eigenvalues = (0..dimensions)
+        .map(|index| matrix[index * dimensions + index])
+        .collect();
+    (eigenvalues, vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distances::Metric;
+    use crate::rotation::gaussian;
+
+    /// Un-centred clusters: every coordinate is positive, so plain sign bits
+    /// collapse every vector onto the same code.
+    fn clustered(count: usize, dimensions: usize) -> Vec<(String, Vec<f32>)> {
+        (0..count)
+            .map(|index| {
+                let cluster = index % 8;
+                (
+                    format!("v-{index:04}"),
+                    (0..dimensions)
+                        .map(|dimension| {
+                            let centre = 4.0 + 2.0 * gaussian(7, cluster, dimension).tanh();
+                            let noise = 0.35 * gaussian(11, index, dimension);
+                            (centre + noise) as f32
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn recall(
+        data: &[(String, Vec<f32>)],
+        queries: &[Vec<f32>],
+        encode: impl Fn(&[f32]) -> Vec<u64>,
+        code_dimensions: usize,
+    ) -> f64 {
+        let codes: Vec<_> = data
+            .iter()
+            .map(|(id, vector)| (id.clone(), encode(vector)))
+            .collect();
+        let mut found = 0usize;
+        for query in queries {
+            let exact: Vec<String> =
+                crate::search::vector_top_k(data.to_vec(), query, Metric::L2, query.len(), 10)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect();
+            let candidates =
+                crate::search::binary_top_k(codes.clone(), &encode(query), code_dimensions, 50)
+                    .unwrap();
+            found += candidates
+                .iter()
+                .filter(|(id, _)| exact.contains(id))
+                .count();
+        }
+        found as f64 / (queries.len() * 10) as f64
+    }
+
+    fn train(data: &[(String, Vec<f32>)], bits: usize, rotation: Rotation) -> Quantizer {
+        let rows: Vec<&[f32]> = data.iter().map(|(_, vector)| vector.as_slice()).collect();
+        Quantizer::train(
+            &rows,
+            Params {
+                bits,
+                rotation,
+                iterations: 10,
+                seed: 42,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn centred_multi_bit_codes_beat_plain_sign_bits_on_clustered_data() {
+        let data = clustered(800, 24);
+        let queries: Vec<Vec<f32>> = data
+            .iter()
+            .step_by(40)
+            .map(|(_, vector)| vector.iter().map(|value| value + 0.05).collect())
+            .collect();
+
+        let sign = recall(&data, &queries, crate::distances::compress_sign_bits, 24);
+        let one_bit = train(&data, 1, Rotation::None);
+        let centred = recall(&data, &queries, |v| one_bit.encode(v).unwrap(), 24);
+        let four_bit = train(&data, 4, Rotation::Random);
+        let multi = recall(&data, &queries, |v| four_bit.encode(v).unwrap(), 96);
+        let itq = train(&data, 1, Rotation::Itq);
+        let learned = recall(&data, &queries, |v| itq.encode(v).unwrap(), 24);
+
+        assert!(sign < 0.2, "sign recall {sign}");
+        assert!(centred > sign + 0.3, "centred {centred} sign {sign}");
+        assert!(learned > sign + 0.3, "itq {learned} sign {sign}");
+        assert!(multi > centred, "4-bit {multi} 1-bit {centred}");
+    }
+
+    #[test]
+    fn thermometer_codes_make_hamming_equal_level_distance() {
+        let data: Vec<(String, Vec<f32>)> = (0..100)
+            .map(|index| (format!("{index}"), vec![index as f32, (99 - index) as f32]))
+            .collect();
+        let quantizer = train(&data, 4, Rotation::None);
+        assert_eq!(quantizer.code_dimensions(), 8);
+
+        let low = quantizer.encode(&[0.0, 99.0]).unwrap();
+        let high = quantizer.encode(&[99.0, 0.0]).unwrap();
+        let middle = quantizer.encode(&[50.0, 49.0]).unwrap();
+        assert_eq!(low, vec![0b1111_0000]);
+        assert_eq!(high, vec![0b0000_1111]);
+        assert_eq!(crate::distances::packed_hamming(&low, &high, 8), Ok(8.0));
+        assert_eq!(crate::distances::packed_hamming(&low, &middle, 8), Ok(4.0));
+    }
+
+    #[test]
+    fn rotations_are_orthogonal_and_error_bounds_hold() {
+        let data = clustered(200, 12);
+        for rotation in [Rotation::Random, Rotation::Itq] {
+            let quantizer = train(&data, 2, rotation);
+            let matrix = quantizer.rotation.as_ref().unwrap();
+            for row in 0..12 {
+                for other in 0..12 {
+                    let dot: f64 = (0..12)
+                        .map(|column| matrix[row * 12 + column] * matrix[other * 12 + column])
+                        .sum();
+                    let expected = if row == other { 1.0 } else { 0.0 };
+                    assert!((dot - expected).abs() < 1.0e-9, "{rotation:?} {dot}");
+                }
+            }
+
+            // Rotations preserve norms, so L2 distances survive the transform.
+            let (left, right) = (&data[3].1, &data[50].1);
+            let transformed_left = quantizer.transform(left).unwrap();
+            let transformed_right = quantizer.transform(right).unwrap();
+            let rotated: f64 = transformed_left
+                .iter()
+                .zip(&transformed_right)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            let original = crate::distances::compute(Metric::L2, left, right).unwrap();
+            assert!((rotated - f64::from(original)).abs() < 1.0e-4);
+
+            let bounds: Vec<f32> = data
+                .iter()
+                .map(|(_, vector)| quantizer.error_bound(vector).unwrap())
+                .collect();
+            let mean = bounds.iter().sum::<f32>() / bounds.len() as f32;
+            assert!((mean - quantizer.expected_error()).abs() < 1.0e-3);
+            assert!(quantizer.expected_error() > 0.0);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_training_inputs_and_codes() {
+        let rows: [&[f32]; 2] = [&[1.0, 2.0], &[3.0, 4.0]];
+        let params = Params {
+            bits: 2,
+            rotation: Rotation::None,
+            iterations: 0,
+            seed: 0,
+        };
+        assert!(Quantizer::train(&[], params).is_err());
+        assert!(Quantizer::train(&rows, Params { bits: 3, ..params }).is_err());
+        assert!(Quantizer::train(&[&[1.0], &[1.0, 2.0]], params).is_err());
+        assert!(Quantizer::train(&[&[f32::NAN]], params).is_err());
+        assert!(Quantizer::train(&[&[]], params).is_err());
+
+        let quantizer = Quantizer::train(&rows, params).unwrap();
+        assert!(quantizer.encode(&[1.0]).is_err());
+        assert!(quantizer.error_bound(&[1.0, f32::INFINITY]).is_err());
+        assert_eq!(Rotation::from_code(2), Ok(Rotation::Itq));
+        assert!(Rotation::from_code(3).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Seeded random rotations and Johnson-Lindenstrauss projections.
+//!
+//! Three constructions map `input` dimensions to `output <= input`:
+//!
+//! - `Orthogonal`: the first `output` rows of a Gram-Schmidt QR of a Gaussian
+//!   matrix, rescaled by `sqrt(input / output)` to preserve expected norms; a
+//!   full rotation when `output == input`.
+//! - `Hadamard`: three rounds of random sign flips and a normalized fast
+//!   Walsh-Hadamard transform over the input zero-padded to a power of two,
+//!   keeping the first `output` coordinates rescaled to preserve expected
+//!   norms. It is an exact rotation when `input` is a power of two and
+//!   `output == input`, at `O(d log d)` cost per vector.
+//! - `SparseJl`: an Achlioptas projection whose entries are `±sqrt(3 / output)`
+//!   with probability 1/6 each and zero otherwise.
+//!
+//! Construction is refused when the dense weights, Hadamard signs or sparse
+//! draws would exceed `MAX_TRANSFORM_ENTRIES`.
+//!
+//! Every random draw comes from `muvera::hash4`, so the same seed yields the
+//! same transform on every platform. Sign flips and sparse entries use only
+//! integer hashing; Gaussian samples sum hashed uniforms, so no step goes
+//! through the platform libm.
+
+use crate::muvera::hash4;
+
+/// Hash stream for Gaussian sampling, distinct from k-means and MUVERA streams.
+const ROTATION_STREAM: u64 = 0x726F_7461_7469_6F6E;
+
+/// Hash stream for Hadamard sign flips.
+const HADAMARD_STREAM: u64 = 0x6877_6874_5F73_676E;
+
+/// Hash stream for sparse JL entries.
+const SPARSE_STREAM: u64 = 0x7370_6172_7365_6A6C;
+
+/// Sign-flip and transform rounds of the Hadamard construction.
+const HADAMARD_ROUNDS: usize = 3;
+
+/// Cap on the weights, signs or sparse draws one transform may generate.
+const MAX_TRANSFORM_ENTRIES: usize = 16_777_216;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Orthogonal,
+    Hadamard,
+    SparseJl,
+}
+
+impl Kind {
+    /// Decodes the compact transform selector used by the NIF boundary.
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(Self::Orthogonal),
+            1 => Ok(Self::Hadamard),
+            2 => Ok(Self::SparseJl),
+            _ => Err("unknown random transform".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Matrix {
+    /// Row-major `output x input` orthogonal rows of norm `sqrt(input / output)`.
+    Dense(Vec<f64>),
+    /// `HADAMARD_ROUNDS x padded` signs applied before each transform.
+    Hadamard { padded: usize, signs: Vec<f64> },
+    /// Nonzero `(output row, weight)` entries per input dimension.
+    Sparse(Vec<Vec<(usize, f64)>>),
+}
+
+#[derive(Debug)]
+pub struct RandomTransform {
+    input: usize,
+    output: usize,
+    matrix: Matrix,
+}
+
+impl RandomTransform {
+    /// Builds a seeded transform from `input` to `output` dimensions.
+    pub fn new(kind: Kind, input: usize, output: usize, seed: u64) -> Result<Self, String> {
+        if input == 0 {
+            return Err("input dimensions must be positive".to_string());
+        }
+        if output == 0 || output > input {
+            return Err("output dimensions must be between 1 and input dimensions".to_string());
+        }
+        transform_entries(kind, input, output)?;
+        let matrix = match kind {
+            Kind::Orthogonal => {
+                let mut rows = orthogonal_rows(output, input, seed)?;
+                if output < input {
+                    let scale = (input as f64 / output as f64).sqrt();
+                    rows.iter_mut().for_each(|value| *value *= scale);
+                }
+                Matrix::Dense(rows)
+            }
+            Kind::Hadamard => {
+                let padded = input.next_power_of_two();
+                let signs = (0..HADAMARD_ROUNDS * padded)
+                    .map(|index| {
+                        let hash = hash4(
+                            seed,
+                            HADAMARD_STREAM,
+                            (index / padded) as u64,
+                            (index % padded) as u64,
+                        );
+                        if hash & 1 == 0 {
+                            1.0
+                        } else {
+                            -1.0
+                        }
+                    })
+                    .collect();
+                Matrix::Hadamard { padded, signs }
+            }
+            Kind::SparseJl => {
+                let scale = (3.0 / output as f64).sqrt();
+                Matrix::Sparse(
+                    (0..input)
+                        .map(|column| {
+                            (0..output)
+                                .filter_map(|row| {
+                                    match hash4(seed, SPARSE_STREAM, row as u64, column as u64) % 6
+                                    {
+                                        0 => Some((row, scale)),
+                                        1 => Some((row, -scale)),
+                                        _ => None,
+                                    }
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )
+            }
+        };
+        Ok(Self {
+            input,
+            output,
+            matrix,
+        })
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.input, self.output)
+    }
+
+    /// Applies the transform to one vector.
+    pub fn apply(&self, vector: &[f32]) -> Result<Vec<f32>, String> {
+        if vector.len() != self.input {
+            return Err("dimension mismatch".to_string());
+        }
+        crate::distances::validate_finite_vector(vector)?;
+        let vector: Vec<f64> = vector.iter().map(|value| f64::from(*value)).collect();
+        let out = self.apply_f64(&vector);
+        if out.iter().any(|value| value.abs() > f64::from(f32::MAX)) {
+            return Err("random transform overflow".to_string());
+        }
+        Ok(out.into_iter().map(|value| value as f32).collect())
+    }
+
+    pub fn apply_batch(&self, vectors: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, String> {
+        vectors.iter().map(|vector| self.apply(vector)).collect()
+    }
+
+    fn apply_f64(&self, vector: &[f64]) -> Vec<f64> {
+        match &self.matrix {
+            Matrix::Dense(rows) => rows
+                .chunks_exact(self.input)
+                .map(|row| row.iter().zip(vector).map(|(a, x)| a * x).sum())
+                .collect(),
+            Matrix::Hadamard { padded, signs } => {
+                let mut values = vector.to_vec();
+                values.resize(*padded, 0.0);
+                for round in signs.chunks_exact(*padded) {
+                    for (value, sign) in values.iter_mut().zip(round) {
+                        *value *= sign;
+                    }
+                    fwht(&mut values);
+                }
+                let scale = (*padded as f64 / self.output as f64).sqrt();
+                values.truncate(self.output);
+                values.iter_mut().for_each(|value| *value *= scale);
+                values
+            }
+            Matrix::Sparse(columns) => {
+                let mut out = vec![0.0f64; self.output];
+                for (value, column) in vector.iter().zip(columns) {
+                    for (row, weight) in column {
+                        out[*row] += value * weight;
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Counts the entries `RandomTransform::new` would generate, refusing sizes
+/// that overflow or exceed `MAX_TRANSFORM_ENTRIES`.
+fn transform_entries(kind: Kind, input: usize, output: usize) -> Result<usize, String> {
+    let entries = match kind {
+        Kind::Orthogonal | Kind::SparseJl => output.checked_mul(input),
+        Kind::Hadamard => input
+            .checked_next_power_of_two()
+            .and_then(|padded| padded.checked_mul(HADAMARD_ROUNDS)),
+    };
+    entries
+        .filter(|entries| *entries <= MAX_TRANSFORM_ENTRIES)
+        .ok_or_else(|| "random transform exceeds safety limit".to_string())
+}
+
+pub struct RandomTransformResource(pub RandomTransform);
+
+#[rustler::resource_impl]
+impl rustler::Resource for RandomTransformResource {}
+
+/// In-place orthonormal fast Walsh-Hadamard transform; `values.len()` must be
+/// a power of two. The transform is its own inverse.
+pub(crate) fn fwht(values: &mut [f64]) {
+    let mut half = 1;
+    while half < values.len() {
+        for block in values.chunks_exact_mut(2 * half) {
+            let (left, right) = block.split_at_mut(half);
+            for (a, b) in left.iter_mut().zip(right.iter_mut()) {
+                let sum = *a + *b;
+                *b = *a - *b;
+                *a = sum;
+            }
+        }
+        half *= 2;
+    }
+    let scale = 1.0 / (values.len() as f64).sqrt();
+    values.iter_mut().for_each(|value| *value *= scale);
+}
+
+/// Standard normal sample on the rotation stream; see `muvera::gaussian`.
+pub(crate) fn gaussian(seed: u64, row: usize, column: usize) -> f64 {
+    crate::muvera::gaussian(seed, ROTATION_STREAM, row as u64, column as u64)
+}
+
+/// Samples `rows` orthonormal Haar-like rows of length `dimensions` with
+/// Gram-Schmidt on Gaussian rows, row-major.
+pub(crate) fn orthogonal_rows(
+    rows: usize,
+    dimensions: usize,
+    seed: u64,
+) -> Result<Vec<f64>, String> {
+    let mut matrix: Vec<f64> = (0..rows * dimensions)
+        .map(|index| gaussian(seed, index / dimensions, index % dimensions))
+        .collect();
+    for row in 0..rows {
+        for previous in 0..row {
+            let projection: f64 = (0..dimensions)
+                .map(|column| {
+                    matrix[row * dimensions + column] * matrix[previous * dimensions + column]
+                })
+                .sum();
+            for column in 0..dimensions {
+                matrix[row * dimensions + column] -=
+                    projection * matrix[previous * dimensions + column];
+            }
+        }
+        let norm = (0..dimensions)
+            .map(|column| matrix[row * dimensions + column].powi(2))
+            .sum::<f64>()
+            .sqrt();
+        if norm < 1.0e-12 {
+            return Err("degenerate random rotation".to_string());
+        }
+        for column in 0..dimensions {
+            matrix[row * dimensions + column] /= norm;
+        }
+    }
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(index: u64, dimensions: usize) -> Vec<f32> {
+        (0..dimensions)
+            .map(|dimension| (hash4(index, dimension as u64, 9, 0) % 2_000) as f32 / 1_000.0 - 1.0)
+            .collect()
+    }
+
+    fn norm(vector: &[f32]) -> f64 {
+        vector
+            .iter()
+            .map(|value| f64::from(*value).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn dot(left: &[f32], right: &[f32]) -> f64 {
+        left.iter()
+            .zip(right)
+            .map(|(a, b)| f64::from(*a) * f64::from(*b))
+            .sum()
+    }
+
+    #[test]
+    fn full_orthogonal_and_hadamard_rotations_preserve_geometry() {
+        for (kind, dimensions) in [(Kind::Orthogonal, 24), (Kind::Hadamard, 32)] {
+            let transform = RandomTransform::new(kind, dimensions, dimensions, 3).unwrap();
+            let left = vector(1, dimensions);
+            let right = vector(2, dimensions);
+            let rotated = transform
+                .apply_batch(&[left.clone(), right.clone()])
+                .unwrap();
+            assert!((norm(&rotated[0]) - norm(&left)).abs() < 1.0e-5);
+            assert!((dot(&rotated[0], &rotated[1]) - dot(&left, &right)).abs() < 1.0e-4);
+            assert_ne!(rotated[0], left);
+        }
+    }
+
+    #[test]
+    fn orthogonal_rows_are_orthonormal() {
+        let rows = orthogonal_rows(6, 20, 11).unwrap();
+        for left in 0..6 {
+            for right in 0..6 {
+                let dot: f64 = (0..20)
+                    .map(|column| rows[left * 20 + column] * rows[right * 20 + column])
+                    .sum();
+                let expected = if left == right { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1.0e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn fwht_is_its_own_inverse() {
+        let original: Vec<f64> = (0..16).map(|index| index as f64 - 7.5).collect();
+        let mut values = original.clone();
+        fwht(&mut values);
+        assert_ne!(values, original);
+        fwht(&mut values);
+        for (value, expected) in values.iter().zip(&original) {
+            assert!((value - expected).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn reductions_approximately_preserve_pairwise_distances() {
+        let vectors: Vec<Vec<f32>> = (0..20).map(|index| vector(index, 1_000)).collect();
+        for kind in [Kind::Orthogonal, Kind::Hadamard, Kind::SparseJl] {
+            let transform = RandomTransform::new(kind, 1_000, 256, 17).unwrap();
+            let reduced = transform.apply_batch(&vectors).unwrap();
+            let mut worst = 0.0f64;
+            for left in 0..vectors.len() {
+                for right in left + 1..vectors.len() {
+                    let original = crate::distances::l2(&vectors[left], &vectors[right]) as f64;
+                    let projected = crate::distances::l2(&reduced[left], &reduced[right]) as f64;
+                    worst = worst.max((projected / original - 1.0).abs());
+                }
+            }
+            assert!(worst < 0.25, "{kind:?} distortion {worst}");
+        }
+    }
+
+    #[test]
+    fn transforms_are_deterministic_per_seed() {
+        let input = vector(5, 48);
+        for kind in [Kind::Orthogonal, Kind::Hadamard, Kind::SparseJl] {
+            let first = RandomTransform::new(kind, 48, 16, 1).unwrap();
+            let again = RandomTransform::new(kind, 48, 16, 1).unwrap();
+            let other = RandomTransform::new(kind, 48, 16, 2).unwrap();
+            assert_eq!(first.apply(&input), again.apply(&input));
+            assert_ne!(first.apply(&input), other.apply(&input));
+            assert_eq!(first.dimensions(), (48, 16));
+        }
+
+        // Every construction is pinned to exact values.
+        let unit: Vec<f32> = (0..8)
+            .map(|index| if index == 0 { 1.0 } else { 0.0 })
+            .collect();
+        let hadamard = RandomTransform::new(Kind::Hadamard, 8, 8, 42).unwrap();
+        let sparse = RandomTransform::new(Kind::SparseJl, 8, 4, 42).unwrap();
+        let orthogonal = RandomTransform::new(Kind::Orthogonal, 8, 8, 42).unwrap();
+        assert_eq!(hadamard.apply(&unit).unwrap(), HADAMARD_GOLDEN);
+        assert_eq!(sparse.apply(&unit).unwrap(), SPARSE_GOLDEN);
+        assert_eq!(orthogonal.apply(&unit).unwrap(), ORTHOGONAL_GOLDEN);
+    }
+
+    const HADAMARD_GOLDEN: [f32; 8] = [
+        -0.17677669,
+        -0.17677669,
+        -0.17677669,
+        0.53033006,
+        -0.17677669,
+        0.53033006,
+        0.53033006,
+        -0.17677669,
+    ];
+    const SPARSE_GOLDEN: [f32; 4] = [0.0, -0.8660254, -0.8660254, 0.0];
+    const ORTHOGONAL_GOLDEN: [f32; 8] = [
+        0.010146043,
+        0.13153896,
+        -0.7582777,
+        0.5724074,
+        0.026256576,
+        0.1190074,
+        0.2398685,
+        -0.08700633,
+    ];
+
+    #[test]
+    fn rejects_invalid_shapes_and_vectors() {
+        assert_eq!(
+            Kind::from_code(3).err(),
+            Some("unknown random transform".into())
+        );
+        assert!(RandomTransform::new(Kind::Orthogonal, 0, 0, 1).is_err());
+        assert!(RandomTransform::new(Kind::Hadamard, 8, 0, 1).is_err());
+        assert!(RandomTransform::new(Kind::SparseJl, 8, 9, 1).is_err());
+        for (kind, input, output) in [
+            (Kind::Orthogonal, MAX_TRANSFORM_ENTRIES, 2),
+            (Kind::Hadamard, usize::MAX, 1),
+            (Kind::Hadamard, MAX_TRANSFORM_ENTRIES / 2, 1),
+            (Kind::SparseJl, usize::MAX, 2),
+        ] {
+            assert_eq!(
+                RandomTransform::new(kind, input, output, 1).err(),
+                Some("random transform exceeds safety limit".into())
+            );
+        }
+
+        let transform = RandomTransform::new(Kind::Hadamard, 8, 4, 1).unwrap();
+        assert!(transform.apply(&[1.0; 7]).is_err());
+        assert!(transform.apply(&[f32::NAN; 8]).is_err());
+        assert!(transform.apply_batch(&[vec![1.0; 8], vec![1.0]]).is_err());
+    }
+}
@@ -5,22 +5,38 @@ use std::collections::BinaryHeap;
 
 use crate::distances::Metric;
 
+/// Bounded top-k heap entry shared by the native search paths.
+///
+/// Hits order by ascending `rank`, then id, so the heap maximum is the worst
+/// retained result. `raw` is the value handed back to callers; higher-is-better
+/// scores rank by their negation.
 #[derive(Debug)]
-struct Hit {
-    id: String,
-    raw: f32,
-    rank: f32,
+pub(crate) struct Hit<I = String> {
+    pub(crate) id: I,
+    pub(crate) raw: f32,
+    pub(crate) rank: f32,
 }
 
-impl Eq for Hit {}
+impl<I> Hit<I> {
+    /// Wraps a higher-is-better score, such as a similarity or BM25 score.
+    pub(crate) fn scored(id: I, score: f32) -> Self {
+        Self {
+            id,
+            raw: score,
+            rank: -score,
+        }
+    }
+}
 
-impl PartialEq for Hit {
+impl<I: Ord> Eq for Hit<I> {}
+
+impl<I: Ord> PartialEq for Hit<I> {
     fn eq(&self, other: &Self) -> bool {
         self.rank.total_cmp(&other.rank) == Ordering::Equal && self.id == other.id
     }
 }
 
-impl Ord for Hit {
+impl<I: Ord> Ord for Hit<I> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.rank
             .total_cmp(&other.rank)
@@ -28,7 +44,7 @@ impl Ord for Hit {
     }
 }
 
-impl PartialOrd for Hit {
+impl<I: Ord> PartialOrd for Hit<I> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
@@ -42,10 +58,7 @@ pub fn vector_top_k(
     dimensions: usize,
     limit: usize,
 ) -> Result<Vec<(String, f32)>, String> {
-    if dimensions == 0 || dimensions > query.len() {
-        return Err("invalid prefix dimensions".to_string());
-    }
-    crate::distances::validate_finite_vector(&query[..dimensions])?;
+    validate_prefix_query(query, dimensions)?;
 
     let mut heap = BinaryHeap::with_capacity(usize::min(limit, vectors.len()));
     for (id, vector) in vectors {
@@ -53,11 +66,40 @@ pub fn vector_top_k(
             return Err("dimension mismatch".to_string());
         }
         crate::distances::validate_finite_vector(&vector[..dimensions])?;
-        let raw = if metric == Metric::Cosine {
-            crate::distances::cosine(&query[..dimensions], &vector[..dimensions])?
-        } else {
-            crate::distances::compute(metric, &query[..dimensions], &vector[..dimensions])?
-        };
+        let raw = prefix_raw(metric, query, &vector, dimensions)?;
+        push_top_k(
+            &mut heap,
+            Hit {
+                id,
+                raw,
+                rank: crate::distances::rank_value(metric, raw),
+            },
+            limit,
+        );
+    }
+
+    Ok(sorted_hits(heap))
+}
+
+/// Scores borrowed rows on a query prefix and keeps the best `limit` ids.
+///
+/// Rows are expected to come from an index that already validated them, so
+/// only their length is checked here.
+pub(crate) fn prefix_top_k<'a>(
+    rows: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+    query: &[f32],
+    metric: Metric,
+    dimensions: usize,
+    limit: usize,
+) -> Result<Vec<(&'a str, f32)>, String> {
+    validate_prefix_query(query, dimensions)?;
+
+    let mut heap = BinaryHeap::new();
+    for (id, vector) in rows {
+        if dimensions > vector.len() {
+            return Err("dimension mismatch".to_string());
+        }
+        let raw = prefix_raw(metric, query, vector, dimensions)?;
         push_top_k(
             &mut heap,
             Hit {
@@ -69,7 +111,7 @@ pub fn vector_top_k(
         );
     }
 
-    sorted_hits(heap)
+    Ok(sorted_hits(heap))
 }
 
 /// Scores packed sign vectors by Hamming distance in one native call.
@@ -88,10 +130,33 @@ pub fn binary_top_k(
         let raw = crate::distances::packed_hamming(query, &vector, dimensions)?;
         push_top_k(&mut heap, Hit { id, raw, rank: raw }, limit);
     }
-    sorted_hits(heap)
+    Ok(sorted_hits(heap))
+}
+
+/// Rejects prefix lengths outside the query and non-finite prefix values.
+pub(crate) fn validate_prefix_query(query: &[f32], dimensions: usize) -> Result<(), String> {
+    if dimensions == 0 || dimensions > query.len() {
+        return Err("invalid prefix dimensions".to_string());
+    }
+    crate::distances::validate_finite_vector(&query[..dimensions])
+}
+
+/// Computes the raw metric on the first `dimensions` values, using true cosine.
+fn prefix_raw(
+    metric: Metric,
+    query: &[f32],
+    vector: &[f32],
+    dimensions: usize,
+) -> Result<f32, String> {
+    if metric == Metric::Cosine {
+        crate::distances::cosine(&query[..dimensions], &vector[..dimensions])
+    } else {
+        crate::distances::compute(metric, &query[..dimensions], &vector[..dimensions])
+    }
 }
 
-fn push_top_k(heap: &mut BinaryHeap<Hit>, hit: Hit, limit: usize) {
+/// Offers `hit` to a heap that keeps the best `limit` results.
+pub(crate) fn push_top_k<I: Ord>(heap: &mut BinaryHeap<Hit<I>>, hit: Hit<I>, limit: usize) {
     if limit == 0 {
         return;
     }
@@ -103,10 +168,11 @@ fn push_top_k(heap: &mut BinaryHeap<Hit>, hit: Hit, limit: usize) {
     }
 }
 
-fn sorted_hits(heap: BinaryHeap<Hit>) -> Result<Vec<(String, f32)>, String> {
+/// Drains a top-k heap best-first as ids with their raw values.
+pub(crate) fn sorted_hits<I: Ord>(heap: BinaryHeap<Hit<I>>) -> Vec<(I, f32)> {
     let mut hits = heap.into_vec();
     hits.sort();
-    Ok(hits.into_iter().map(|hit| (hit.id, hit.raw)).collect())
+    hits.into_iter().map(|hit| (hit.id, hit.raw)).collect()
 }
 
 #[cfg(test)]
@@ -282,7 +348,7 @@ mod tests {
 
     #[test]
     fn heap_hit_equality_and_partial_order_include_the_external_id() {
-        let first = Hit {
+        let first: Hit = Hit {
             id: "a".into(),
             raw: 1.0,
             rank: 1.0,
@@ -300,5 +366,7 @@ mod tests {
         assert_eq!(first, equal);
         assert_ne!(first, other_id);
         assert_eq!(first.partial_cmp(&other_id), Some(Ordering::Less));
+        assert!(Hit::scored("b", 2.0) < Hit::scored("a", 1.0));
+        assert_eq!(Hit::scored("a", 2.0).raw, 2.0);
     }
 }
@@ -0,0 +1,440 @@
+//! Native sparse vector index resource.
+//!
+//! Learned sparse encoders (SPLADE) and BM25-style weighting produce
+//! `{term_id, weight}` pairs instead of dense vectors. This resource mirrors
+//! those pairs in per-term posting lists and scores documents by dot product,
+//! skipping documents with MaxScore dynamic pruning once the top-k is full.
+//! Weights must be non-negative so posting-list maxima are valid upper bounds.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::RwLock;
+
+use crate::search::{sorted_hits, Hit};
+
+/// Relative slack applied before pruning so f64 bound sums that round to the
+/// current f32 threshold still reach the exact score and id tie-break.
+const PRUNE_SLACK: f64 = 1.0e-6;
+
+struct Document {
+    id: String,
+    terms: Vec<(u32, f32)>,
+}
+
+#[derive(Default)]
+struct PostingList {
+    /// Internal document numbers paired with weights, ascending by document.
+    entries: Vec<(u32, f32)>,
+    max_weight: f32,
+}
+
+pub struct SparseIndex {
+    ids: HashMap<String, u32>,
+    documents: HashMap<u32, Document>,
+    postings: HashMap<u32, PostingList>,
+    next_document: u32,
+}
+
+struct Cursor<'a> {
+    entries: &'a [(u32, f32)],
+    position: usize,
+    weight: f64,
+    bound: f64,
+}
+
+impl Cursor<'_> {
+    fn current(&self) -> Option<u32> {
+        self.entries
+            .get(self.position)
+            .map(|(document, _)| *document)
+    }
+
+    /// Advances to the first posting at or after `document` and returns its
+    /// weight when the document itself is present.
+    fn seek(&mut self, document: u32) -> Option<f32> {
+        self.position += self.entries[self.position..].partition_point(|(doc, _)| *doc < document);
+        match self.entries.get(self.position) {
+            Some((doc, weight)) if *doc == document => Some(*weight),
+            _ => None,
+        }
+    }
+}
+
+impl Default for SparseIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseIndex {
+    /// Creates an empty sparse index.
+    pub fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            documents: HashMap::new(),
+            postings: HashMap::new(),
+            next_document: 0,
+        }
+    }
+
+    /// Inserts or replaces one sparse vector by external id.
+    pub fn insert(&mut self, id: String, vector: Vec<(u32, f32)>) -> Result<(), String> {
+        let terms = validate_sparse_vector(vector)?;
+        self.insert_validated(id, terms)
+    }
+
+    /// Inserts or replaces a batch of sparse vectors.
+    pub fn insert_many(&mut self, vectors: Vec<(String, Vec<(u32, f32)>)>) -> Result<(), String> {
+        let validated = vectors
+            .into_iter()
+            .map(|(id, vector)| validate_sparse_vector(vector).map(|terms| (id, terms)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (id, terms) in validated {
+            self.insert_validated(id, terms)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes one sparse vector by external id.
+    pub fn delete(&mut self, id: &str) {
+        let Some(document) = self.ids.remove(id) else {
+            return;
+        };
+        let Some(stored) = self.documents.remove(&document) else {
+            return;
+        };
+
+        for (term, weight) in stored.terms {
+            let Some(list) = self.postings.get_mut(&term) else {
+                continue;
+            };
+            if let Ok(position) = list
+                .entries
+                .binary_search_by_key(&document, |(doc, _)| *doc)
+            {
+                list.entries.remove(position);
+            }
+            if list.entries.is_empty() {
+                self.postings.remove(&term);
+            } else if weight >= list.max_weight {
+                list.max_weight = list
+                    .entries
+                    .iter()
+                    .map(|(_, weight)| *weight)
+                    .fold(0.0, f32::max);
+            }
+        }
+    }
+
+    /// Searches by dot product and returns ids with raw scores, best first.
+    ///
+    /// Only documents sharing at least one term with the query are returned.
+    pub fn search(
+        &self,
+        query: Vec<(u32, f32)>,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        let query = validate_sparse_vector(query)?;
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut cursors: Vec<Cursor<'_>> = query
+            .iter()
+            .filter_map(|(term, weight)| {
+                self.postings.get(term).map(|list| Cursor {
+                    entries: &list.entries,
+                    position: 0,
+                    weight: f64::from(*weight),
+                    bound: f64::from(*weight) * f64::from(list.max_weight),
+                })
+            })
+            .collect();
+        // Ascending bounds let the cheapest lists become non-essential first.
+        cursors.sort_by(|left, right| left.bound.total_cmp(&right.bound));
+        let prefix_bounds: Vec<f64> = cursors
+            .iter()
+            .scan(0.0, |total, cursor| {
+                *total += cursor.bound;
+                Some(*total)
+            })
+            .collect();
+
+        let mut hits: BinaryHeap<Hit> = BinaryHeap::with_capacity(limit);
+        let mut threshold = f64::NEG_INFINITY;
+        let mut first_essential = 0;
+
+        'documents: while let Some(document) = cursors[first_essential..]
+            .iter()
+            .filter_map(Cursor::current)
+            .min()
+        {
+            let mut bound = 0.0;
+            for cursor in &mut cursors[first_essential..] {
+                if cursor.current() == Some(document) {
+                    bound += cursor.weight * f64::from(cursor.entries[cursor.position].1);
+                    cursor.position += 1;
+                }
+            }
+            for term in (0..first_essential).rev() {
+                if bound + prefix_bounds[term] < threshold {
+                    continue 'documents;
+                }
+                if let Some(weight) = cursors[term].seek(document) {
+                    bound += cursors[term].weight * f64::from(weight);
+                }
+            }
+            if bound < threshold {
+                continue;
+            }
+
+            let stored = &self.documents[&document];
+            let hit = Hit::scored(stored.id.clone(), dot(&query, &stored.terms)?);
+            if hits.len() < limit {
+                hits.push(hit);
+            } else if hits.peek().is_some_and(|worst| hit < *worst) {
+                hits.pop();
+                hits.push(hit);
+            } else {
+                continue;
+            }
+
+            if hits.len() == limit {
+                if let Some(worst) = hits.peek() {
+                    let raw = f64::from(worst.raw);
+                    threshold = raw - raw.abs() * PRUNE_SLACK;
+                }
+                while first_essential < cursors.len() && prefix_bounds[first_essential] < threshold
+                {
+                    first_essential += 1;
+                }
+            }
+        }
+
+        Ok(sorted_hits(hits))
+    }
+
+    fn insert_validated(&mut self, id: String, terms: Vec<(u32, f32)>) -> Result<(), String> {
+        self.delete(&id);
+        let document = self.next_document;
+        self.next_document = document
+            .checked_add(1)
+            .ok_or_else(|| "sparse document numbers exhausted".to_string())?;
+
+        // Document numbers only grow, so appending keeps postings sorted.
+        for (term, weight) in &terms {
+            let list = self.postings.entry(*term).or_default();
+            list.entries.push((document, *weight));
+            list.max_weight = list.max_weight.max(*weight);
+        }
+        self.ids.insert(id.clone(), document);
+        self.documents.insert(document, Document { id, terms });
+        Ok(())
+    }
+}
+
+pub struct SparseResource(pub RwLock<SparseIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for SparseResource {}
+
+/// Sorts terms, drops zero weights and rejects malformed weights.
+fn validate_sparse_vector(mut vector: Vec<(u32, f32)>) -> Result<Vec<(u32, f32)>, String> {
+    for (_, weight) in &vector {
+        if !weight.is_finite() {
+            return Err("sparse vector contains a non-finite weight".to_string());
+        }
+        if *weight < 0.0 {
+            return Err("sparse weights must be non-negative".to_string());
+        }
+    }
+    vector.sort_unstable_by_key(|(term, _)| *term);
+    if vector.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err("duplicate term id".to_string());
+    }
+    vector.retain(|(_, weight)| *weight > 0.0);
+    Ok(vector)
+}
+
+/// Merge-joins two term-sorted vectors in term order.
+fn dot(left: &[(u32, f32)], right: &[(u32, f32)]) -> Result<f32, String> {
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut total = 0.0f64;
+    while left_index < left.len() && right_index < right.len() {
+        match left[left_index].0.cmp(&right[right_index].0) {
+            Ordering::Less => left_index += 1,
+            Ordering::Greater => right_index += 1,
+            Ordering::Equal => {
+                total += f64::from(left[left_index].1) * f64::from(right[right_index].1);
+                left_index += 1;
+                right_index += 1;
+            }
+        }
+    }
+    let raw = total as f32;
+    if raw.is_finite() {
+        Ok(raw)
+    } else {
+        Err("score overflow".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exhaustive(
+        documents: &[(String, Vec<(u32, f32)>)],
+        query: &[(u32, f32)],
+        limit: usize,
+    ) -> Vec<(String, f32)> {
+        let query = validate_sparse_vector(query.to_vec()).unwrap();
+        let mut scored: Vec<(String, f32)> = documents
+            .iter()
+            .filter_map(|(id, vector)| {
+                let terms = validate_sparse_vector(vector.clone()).unwrap();
+                let shares_term = terms
+                    .iter()
+                    .any(|(term, _)| query.iter().any(|(other, _)| other == term));
+                shares_term.then(|| (id.clone(), dot(&query, &terms).unwrap()))
+            })
+            .collect();
+        scored.sort_by(|left, right| {
+            right
+                .1
+                .total_cmp(&left.1)
+                .then_with(|| left.0.cmp(&right.0))
+        });
+        scored.truncate(limit);
+        scored
+    }
+
+    fn corpus() -> Vec<(String, Vec<(u32, f32)>)> {
+        (0..400u32)
+            .map(|index| {
+                let terms = (0..1 + index % 7)
+                    .map(|offset| {
+                        let term = (index * 13 + offset * 31) % 97;
+                        let weight = ((index * 7 + offset * 11) % 9) as f32 * 0.25;
+                        (term, weight)
+                    })
+                    .collect::<HashMap<_, _>>()
+                    .into_iter()
+                    .collect();
+                (format!("doc-{index:03}"), terms)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inserts_replaces_deletes_and_scores_by_dot_product() {
+        let mut index = SparseIndex::new();
+        index.insert("b".into(), vec![(1, 2.0), (5, 1.0)]).unwrap();
+        index.insert("a".into(), vec![(5, 3.0)]).unwrap();
+        index.insert("c".into(), vec![(9, 4.0)]).unwrap();
+
+        assert_eq!(
+            index.search(vec![(5, 1.0), (1, 1.0)], 10).unwrap(),
+            vec![("a".into(), 3.0), ("b".into(), 3.0)]
+        );
+
+        index.insert("a".into(), vec![(9, 1.0)]).unwrap();
+        assert_eq!(
+            index.search(vec![(5, 1.0), (1, 1.0)], 10).unwrap(),
+            vec![("b".into(), 3.0)]
+        );
+
+        index.delete("c");
+        index.delete("missing");
+        assert_eq!(
+            index.search(vec![(9, 2.0)], 10).unwrap(),
+            vec![("a".into(), 2.0)]
+        );
+        assert_eq!(index.search(vec![(9, 2.0)], 0).unwrap(), vec![]);
+        assert_eq!(index.search(vec![], 3).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn max_score_pruning_matches_exhaustive_scoring() {
+        let documents = corpus();
+        let mut index = SparseIndex::new();
+        index.insert_many(documents.clone()).unwrap();
+
+        for seed in 0..40u32 {
+            let query: Vec<(u32, f32)> = (0..1 + seed % 6)
+                .map(|offset| ((seed * 17 + offset * 23) % 97, 0.5 + offset as f32))
+                .collect::<HashMap<_, _>>()
+                .into_iter()
+                .collect();
+            for limit in [1, 5, 25, 500] {
+                assert_eq!(
+                    index.search(query.clone(), limit).unwrap(),
+                    exhaustive(&documents, &query, limit),
+                    "seed {seed} limit {limit}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn deletes_refresh_posting_maxima_used_for_pruning() {
+        let mut documents = corpus();
+        let mut index = SparseIndex::new();
+        index.insert_many(documents.clone()).unwrap();
+
+        for removed in (0..400).step_by(3) {
+            index.delete(&format!("doc-{removed:03}"));
+        }
+        documents.retain(|(id, _)| {
+            let number: usize = id[4..].parse().unwrap();
+            !number.is_multiple_of(3)
+        });
+        index
+            .insert("heavy".into(), vec![(3, 100.0), (40, 50.0)])
+            .unwrap();
+        index.delete("heavy");
+
+        for term in 0..97u32 {
+            let query = vec![(term, 1.0), ((term + 40) % 97, 2.0)];
+            assert_eq!(
+                index.search(query.clone(), 3).unwrap(),
+                exhaustive(&documents, &query, 3),
+                "term {term}"
+            );
+        }
+        assert!(index.postings.values().all(|list| {
+            list.max_weight
+                == list
+                    .entries
+                    .iter()
+                    .map(|(_, weight)| *weight)
+                    .fold(0.0, f32::max)
+        }));
+    }
+
+    #[test]
+    fn rejects_malformed_sparse_vectors() {
+        let mut index = SparseIndex::new();
+        assert!(index.insert("a".into(), vec![(1, f32::NAN)]).is_err());
+        assert!(index.insert("a".into(), vec![(1, -1.0)]).is_err());
+        assert!(index.insert("a".into(), vec![(1, 1.0), (1, 2.0)]).is_err());
+        assert!(index
+            .insert_many(vec![
+                ("ok".into(), vec![(1, 1.0)]),
+                ("bad".into(), vec![(2, f32::INFINITY)]),
+            ])
+            .is_err());
+        assert_eq!(index.search(vec![(1, 1.0)], 5).unwrap(), vec![]);
+        assert!(index.search(vec![(1, -1.0)], 5).is_err());
+
+        index.insert("zeros".into(), vec![(1, 0.0)]).unwrap();
+        assert_eq!(index.search(vec![(1, 1.0)], 5).unwrap(), vec![]);
+
+        index.insert("huge".into(), vec![(1, f32::MAX)]).unwrap();
+        assert_eq!(
+            index.search(vec![(1, f32::MAX)], 1),
+            Err("score overflow".into())
+        );
+    }
+}
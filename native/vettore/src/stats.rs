@@ -0,0 +1,465 @@
+//! Streaming vector statistics and distribution drift scores.
+//!
+//! An accumulator ingests vectors one at a time and keeps Welford running
+//! means and per-dimension variances in f64, a fixed-range histogram of L2
+//! norms (the last bin also counts norms beyond `max_norm`), and a seeded
+//! Algorithm R reservoir sample. Memory stays bounded by the dimension,
+//! histogram and reservoir sizes however many vectors stream through.
+//!
+//! Drift between two accumulators combines three views:
+//!
+//! - `mean_shift`: cosine distance between the two running means.
+//! - `ks_max` / `ks_mean`: two-sample Kolmogorov-Smirnov statistics per
+//!   dimension over the reservoirs, maximum and average.
+//! - `mmd`: biased squared maximum mean discrepancy between the reservoirs
+//!   with a Gaussian kernel whose bandwidth is the median squared distance
+//!   over a bounded, seeded sample of pooled pairs.
+
+use std::sync::RwLock;
+
+use crate::muvera::hash4;
+
+/// Hash stream for reservoir replacement draws.
+const RESERVOIR_STREAM: u64 = 0x7265_7365_7276_6F69;
+
+/// Hash stream for the pooled pairs behind the MMD bandwidth.
+const BANDWIDTH_STREAM: u64 = 0x6277_5F70_6169_7273;
+
+/// Largest reservoir an accumulator may keep.
+pub const MAX_RESERVOIR_SIZE: usize = 16_384;
+
+/// Largest norm histogram an accumulator may keep.
+pub const MAX_HISTOGRAM_BINS: usize = 65_536;
+
+/// Pooled pairs whose median sets the MMD bandwidth; smaller pools use all.
+const BANDWIDTH_PAIRS: usize = 4_096;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub reservoir_size: usize,
+    pub histogram_bins: usize,
+    pub max_norm: f32,
+    pub seed: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub count: u64,
+    pub mean: Vec<f32>,
+    /// Unbiased per-dimension sample variance; zero below two vectors.
+    pub variance: Vec<f32>,
+    pub norm_histogram: Vec<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Drift {
+    pub mean_shift: f32,
+    pub ks_max: f32,
+    pub ks_mean: f32,
+    pub mmd: f32,
+}
+
+#[derive(Debug)]
+pub struct Accumulator {
+    params: Params,
+    dimension: Option<usize>,
+    count: u64,
+    mean: Vec<f64>,
+    /// Welford sums of squared deviations per dimension.
+    squares: Vec<f64>,
+    norm_histogram: Vec<u64>,
+    reservoir: Vec<Vec<f32>>,
+}
+
+impl Accumulator {
+    /// Creates an empty accumulator.
+    pub fn new(params: Params) -> Result<Self, String> {
+        if params.reservoir_size == 0 || params.reservoir_size > MAX_RESERVOIR_SIZE {
+            return Err(format!(
+                "reservoir size must be between 1 and {MAX_RESERVOIR_SIZE}"
+            ));
+        }
+        if params.histogram_bins == 0 || params.histogram_bins > MAX_HISTOGRAM_BINS {
+            return Err(format!(
+                "histogram bins must be between 1 and {MAX_HISTOGRAM_BINS}"
+            ));
+        }
+        if !params.max_norm.is_finite() || params.max_norm <= 0.0 {
+            return Err("max norm must be finite and positive".to_string());
+        }
+        Ok(Self {
+            params,
+            dimension: None,
+            count: 0,
+            mean: Vec::new(),
+            squares: Vec::new(),
+            norm_histogram: vec![0; params.histogram_bins],
+            reservoir: Vec::new(),
+        })
+    }
+
+    /// Checks a batch against the accumulator's dimension without observing
+    /// it, so callers can validate before mutating a paired index.
+    pub fn validate(&self, vectors: &[Vec<f32>]) -> Result<(), String> {
+        let expected = self.dimension.or_else(|| vectors.first().map(Vec::len));
+        for vector in vectors {
+            if vector.is_empty() {
+                return Err("vector must not be empty".to_string());
+            }
+            if Some(vector.len()) != expected {
+                return Err("dimension mismatch".to_string());
+            }
+            crate::distances::validate_finite_vector(vector)?;
+        }
+        Ok(())
+    }
+
+    /// Observes a validated batch in order.
+    pub fn observe_many(&mut self, vectors: &[Vec<f32>]) -> Result<(), String> {
+        self.validate(vectors)?;
+        for vector in vectors {
+            self.observe_validated(vector);
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> Summary {
+        let denominator = self.count.saturating_sub(1).max(1) as f64;
+        Summary {
+            count: self.count,
+            mean: self.mean.iter().map(|value| *value as f32).collect(),
+            variance: self
+                .squares
+                .iter()
+                .map(|value| {
+                    if self.count < 2 {
+                        0.0
+                    } else {
+                        (value / denominator) as f32
+                    }
+                })
+                .collect(),
+            norm_histogram: self.norm_histogram.clone(),
+        }
+    }
+
+    pub fn sample(&self) -> &[Vec<f32>] {
+        &self.reservoir
+    }
+
+    /// Scores how far `current`'s distribution has drifted from `self`.
+    pub fn drift(&self, current: &Accumulator) -> Result<Drift, String> {
+        if self.count == 0 || current.count == 0 {
+            return Err("drift needs observed vectors on both sides".to_string());
+        }
+        if self.dimension != current.dimension {
+            return Err("dimension mismatch".to_string());
+        }
+        let dimension = self.mean.len();
+
+        let dot: f64 = self
+            .mean
+            .iter()
+            .zip(&current.mean)
+            .map(|(a, b)| a * b)
+            .sum();
+        let norms = norm64(&self.mean) * norm64(&current.mean);
+        let mean_shift = if norms > 0.0 {
+            1.0 - dot / norms
+        } else if norm64(&self.mean) == norm64(&current.mean) {
+            0.0
+        } else {
+            1.0
+        };
+
+        let mut ks_max = 0.0f64;
+        let mut ks_total = 0.0f64;
+        for column in 0..dimension {
+            let mut left: Vec<f32> = self.reservoir.iter().map(|row| row[column]).collect();
+            let mut right: Vec<f32> = current.reservoir.iter().map(|row| row[column]).collect();
+            let statistic = kolmogorov_smirnov(&mut left, &mut right);
+            ks_max = ks_max.max(statistic);
+            ks_total += statistic;
+        }
+
+        Ok(Drift {
+            mean_shift: mean_shift.max(0.0) as f32,
+            ks_max: ks_max as f32,
+            ks_mean: (ks_total / dimension as f64) as f32,
+            mmd: mmd(&self.reservoir, &current.reservoir, self.params.seed) as f32,
+        })
+    }
+
+    fn observe_validated(&mut self, vector: &[f32]) {
+        if self.dimension.is_none() {
+            self.dimension = Some(vector.len());
+            self.mean = vec![0.0; vector.len()];
+            self.squares = vec![0.0; vector.len()];
+        }
+        self.count += 1;
+        let count = self.count as f64;
+        let mut norm = 0.0f64;
+        for ((mean, squares), value) in self.mean.iter_mut().zip(&mut self.squares).zip(vector) {
+            let value = f64::from(*value);
+            let delta = value - *mean;
+            *mean += delta / count;
+            *squares += delta * (value - *mean);
+            norm += value * value;
+        }
+
+        let bins = self.params.histogram_bins;
+        let bin = (norm.sqrt() / f64::from(self.params.max_norm) * bins as f64) as usize;
+        self.norm_histogram[bin.min(bins - 1)] += 1;
+
+        if self.reservoir.len() < self.params.reservoir_size {
+            self.reservoir.push(vector.to_vec());
+        } else {
+            let slot = hash4(self.params.seed, RESERVOIR_STREAM, self.count, 0) % self.count;
+            if let Some(entry) = self.reservoir.get_mut(slot as usize) {
+                *entry = vector.to_vec();
+            }
+        }
+    }
+}
+
+pub struct StatsResource(pub RwLock<Accumulator>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for StatsResource {}
+
+fn norm64(vector: &[f64]) -> f64 {
+    vector.iter().map(|value| value * value).sum::<f64>().sqrt()
+}
+
+/// Largest gap between the two empirical CDFs, stepping past ties together.
+fn kolmogorov_smirnov(left: &mut [f32], right: &mut [f32]) -> f64 {
+    left.sort_unstable_by(f32::total_cmp);
+    right.sort_unstable_by(f32::total_cmp);
+    let (mut i, mut j, mut statistic) = (0, 0, 0.0f64);
+    while i < left.len() && j < right.len() {
+        let value = left[i].min(right[j]);
+        while i < left.len() && left[i] <= value {
+            i += 1;
+        }
+        while j < right.len() && right[j] <= value {
+            j += 1;
+        }
+        let gap = (i as f64 / left.len() as f64 - j as f64 / right.len() as f64).abs();
+        statistic = statistic.max(gap);
+    }
+    statistic
+}
+
+/// Biased squared MMD with a median-heuristic Gaussian kernel.
+///
+/// The bandwidth median is taken over at most `BANDWIDTH_PAIRS` pooled pairs,
+/// drawn with `seed` once the pool has more, so memory stays constant.
+fn mmd(left: &[Vec<f32>], right: &[Vec<f32>], seed: u64) -> f64 {
+    let squared = |a: &[f32], b: &[f32]| f64::from(crate::distances::l2_squared(a, b));
+    let pooled: Vec<&[f32]> = left.iter().chain(right).map(Vec::as_slice).collect();
+    let count = pooled.len();
+    let mut distances: Vec<f64> = if count * (count - 1) / 2 <= BANDWIDTH_PAIRS {
+        (0..count)
+            .flat_map(|row| (row + 1..count).map(move |column| (row, column)))
+            .map(|(row, column)| squared(pooled[row], pooled[column]))
+            .collect()
+    } else {
+        (0..BANDWIDTH_PAIRS as u64)
+            .map(|pair| {
+                let row = (hash4(seed, BANDWIDTH_STREAM, pair, 0) % count as u64) as usize;
+                let offset = hash4(seed, BANDWIDTH_STREAM, pair, 1) % (count as u64 - 1);
+                let column = (row + 1 + offset as usize) % count;
+                squared(pooled[row], pooled[column])
+            })
+            .collect()
+    };
+    let bandwidth = if distances.is_empty() {
+        1.0
+    } else {
+        let middle = distances.len() / 2;
+        let (_, median, _) = distances.select_nth_unstable_by(middle, f64::total_cmp);
+        if *median > 0.0 {
+            *median
+        } else {
+            1.0
+        }
+    };
+    let mean_kernel = |a: &[Vec<f32>], b: &[Vec<f32>]| {
+        let total: f64 = a
+            .iter()
+            .flat_map(|x| b.iter().map(move |y| (-squared(x, y) / bandwidth).exp()))
+            .sum();
+        total / (a.len() * b.len()) as f64
+    };
+    (mean_kernel(left, left) + mean_kernel(right, right) - 2.0 * mean_kernel(left, right)).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Params {
+        Params {
+            reservoir_size: 64,
+            histogram_bins: 4,
+            max_norm: 4.0,
+            seed: 3,
+        }
+    }
+
+    fn uniform(a: u64, b: u64, stream: u64) -> f32 {
+        (hash4(a, b, stream, 0) % 2_000) as f32 / 1_000.0 - 1.0
+    }
+
+    fn vectors(count: u64, offset: f32, stream: u64) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|row| {
+                (0..6)
+                    .map(|dimension| offset + uniform(row, dimension, stream))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn running_moments_and_histogram_match_two_pass_values() {
+        let data = vectors(500, 0.5, 1);
+        let mut accumulator = Accumulator::new(params()).unwrap();
+        for chunk in data.chunks(37) {
+            accumulator.observe_many(chunk).unwrap();
+        }
+        let summary = accumulator.summary();
+        assert_eq!(summary.count, 500);
+        for dimension in 0..6 {
+            let column: Vec<f64> = data.iter().map(|row| f64::from(row[dimension])).collect();
+            let mean = column.iter().sum::<f64>() / 500.0;
+            let variance = column
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / 499.0;
+            assert!((f64::from(summary.mean[dimension]) - mean).abs() < 1.0e-6);
+            assert!((f64::from(summary.variance[dimension]) - variance).abs() < 1.0e-6);
+        }
+
+        let mut expected = vec![0u64; 4];
+        for vector in &data {
+            let norm = crate::distances::l2(vector, &[0.0; 6]);
+            expected[((norm / 4.0 * 4.0) as usize).min(3)] += 1;
+        }
+        assert_eq!(summary.norm_histogram, expected);
+        assert_eq!(summary.norm_histogram.iter().sum::<u64>(), 500);
+    }
+
+    #[test]
+    fn reservoir_is_bounded_deterministic_and_roughly_uniform() {
+        let data: Vec<Vec<f32>> = (0..10_000).map(|value| vec![value as f32]).collect();
+        let run = |seed| {
+            let mut accumulator = Accumulator::new(Params {
+                reservoir_size: 500,
+                seed,
+                ..params()
+            })
+            .unwrap();
+            accumulator.observe_many(&data).unwrap();
+            accumulator.sample().to_vec()
+        };
+        let sample = run(9);
+        assert_eq!(sample.len(), 500);
+        assert_eq!(sample, run(9));
+        assert_ne!(sample, run(10));
+        let mean = sample.iter().map(|row| f64::from(row[0])).sum::<f64>() / 500.0;
+        assert!((mean - 5_000.0).abs() < 500.0, "sample mean {mean}");
+        assert!(sample.iter().filter(|row| row[0] >= 5_000.0).count() > 200);
+    }
+
+    #[test]
+    fn drift_separates_shifted_distributions() {
+        let mut baseline = Accumulator::new(params()).unwrap();
+        let mut same = Accumulator::new(Params {
+            seed: 4,
+            ..params()
+        })
+        .unwrap();
+        let mut shifted = Accumulator::new(params()).unwrap();
+        baseline.observe_many(&vectors(400, 0.5, 1)).unwrap();
+        same.observe_many(&vectors(400, 0.5, 2)).unwrap();
+        let mut moved = vectors(400, 0.5, 3);
+        for vector in &mut moved {
+            vector[0] += 1.5;
+            vector[1] -= 1.0;
+        }
+        shifted.observe_many(&moved).unwrap();
+
+        let quiet = baseline.drift(&same).unwrap();
+        let loud = baseline.drift(&shifted).unwrap();
+        assert!(quiet.mean_shift < 0.01, "{quiet:?}");
+        assert!(loud.mean_shift > 0.2, "{loud:?}");
+        assert!(
+            quiet.ks_max < 0.3 && loud.ks_max > 0.6,
+            "{quiet:?} {loud:?}"
+        );
+        assert!(loud.ks_mean > quiet.ks_mean);
+        assert!(loud.mmd > 10.0 * quiet.mmd, "{quiet:?} {loud:?}");
+
+        let identical = baseline.drift(&baseline).unwrap();
+        assert_eq!(identical.ks_max, 0.0);
+        assert!(identical.mean_shift.abs() < 1.0e-6 && identical.mmd.abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn kolmogorov_smirnov_handles_ties_and_disjoint_samples() {
+        assert_eq!(
+            kolmogorov_smirnov(&mut [1.0, 1.0, 2.0], &mut [1.0, 1.0, 2.0]),
+            0.0
+        );
+        assert_eq!(kolmogorov_smirnov(&mut [0.0, 1.0], &mut [2.0, 3.0]), 1.0);
+        assert_eq!(
+            kolmogorov_smirnov(&mut [0.0, 1.0, 2.0, 3.0], &mut [2.0, 3.0]),
+            0.5
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_params_vectors_and_comparisons() {
+        for reservoir_size in [0, MAX_RESERVOIR_SIZE + 1] {
+            assert!(Accumulator::new(Params {
+                reservoir_size,
+                ..params()
+            })
+            .is_err());
+        }
+        for histogram_bins in [0, MAX_HISTOGRAM_BINS + 1] {
+            assert!(Accumulator::new(Params {
+                histogram_bins,
+                ..params()
+            })
+            .is_err());
+        }
+        assert!(Accumulator::new(Params {
+            max_norm: f32::NAN,
+            ..params()
+        })
+        .is_err());
+
+        let mut accumulator = Accumulator::new(params()).unwrap();
+        let empty = Accumulator::new(params()).unwrap();
+        assert!(accumulator.drift(&empty).is_err());
+        assert!(accumulator
+            .observe_many(&[vec![1.0, 2.0], vec![1.0]])
+            .is_err());
+        assert!(accumulator.observe_many(&[vec![]]).is_err());
+        assert!(accumulator.observe_many(&[vec![f32::INFINITY]]).is_err());
+        assert_eq!(accumulator.summary().count, 0);
+
+        accumulator.observe_many(&[vec![1.0, 2.0]]).unwrap();
+        assert!(accumulator.validate(&[vec![1.0]]).is_err());
+        assert_eq!(accumulator.summary().variance, vec![0.0, 0.0]);
+        let mut other = Accumulator::new(params()).unwrap();
+        other.observe_many(&[vec![1.0, 2.0, 3.0]]).unwrap();
+        assert_eq!(
+            accumulator.drift(&other).err(),
+            Some("dimension mismatch".into())
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Native BM25 full-text index resource.
+//!
+//! Documents are keyed by the same external ids as the flat and HNSW indexes so
+//! lexical hits can be fused with vector hits. Text is split on every
+//! non-alphanumeric Unicode character and lowercased; configured stopwords are
+//! dropped before term frequencies and document lengths are recorded.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::RwLock;
+
+use crate::search::{push_top_k, sorted_hits, Hit};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bm25Params {
+    pub k1: f32,
+    pub b: f32,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self { k1: 1.2, b: 0.75 }
+    }
+}
+
+struct Document {
+    id: String,
+    length: u32,
+    terms: Vec<String>,
+}
+
+pub struct TextIndex {
+    params: Bm25Params,
+    stopwords: HashSet<String>,
+    ids: HashMap<String, u32>,
+    documents: HashMap<u32, Document>,
+    /// Term postings as internal document numbers and term frequencies.
+    postings: HashMap<String, Vec<(u32, u32)>>,
+    total_length: u64,
+    next_document: u32,
+}
+
+impl TextIndex {
+    /// Creates an empty BM25 index with tuning parameters and stopwords.
+    pub fn new(params: Bm25Params, stopwords: Vec<String>) -> Result<Self, String> {
+        if !params.k1.is_finite() || params.k1 < 0.0 {
+            return Err("k1 must be a non-negative finite number".to_string());
+        }
+        if !params.b.is_finite() || !(0.0..=1.0).contains(&params.b) {
+            return Err("b must be within 0.0..=1.0".to_string());
+        }
+
+        Ok(Self {
+            params,
+            stopwords: stopwords.iter().flat_map(|word| tokens(word)).collect(),
+            ids: HashMap::new(),
+            documents: HashMap::new(),
+            postings: HashMap::new(),
+            total_length: 0,
+            next_document: 0,
+        })
+    }
+
+    /// Inserts or replaces one document by external id.
+    pub fn insert(&mut self, id: String, text: &str) -> Result<(), String> {
+        self.delete(&id);
+        let document = self.next_document;
+        self.next_document = document
+            .checked_add(1)
+            .ok_or_else(|| "text document numbers exhausted".to_string())?;
+
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        let mut length = 0u32;
+        for token in self.tokenize(text) {
+            *frequencies.entry(token).or_default() += 1;
+            length = length.saturating_add(1);
+        }
+
+        let mut terms = Vec::with_capacity(frequencies.len());
+        // Document numbers only grow, so appending keeps postings sorted.
+        for (term, frequency) in frequencies {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .push((document, frequency));
+            terms.push(term);
+        }
+        self.total_length += u64::from(length);
+        self.ids.insert(id.clone(), document);
+        self.documents
+            .insert(document, Document { id, length, terms });
+        Ok(())
+    }
+
+    /// Inserts or replaces a batch of documents.
+    pub fn insert_many(&mut self, documents: Vec<(String, String)>) -> Result<(), String> {
+        for (id, text) in documents {
+            self.insert(id, &text)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes one document by external id.
+    pub fn delete(&mut self, id: &str) {
+        let Some(document) = self.ids.remove(id) else {
+            return;
+        };
+        let Some(stored) = self.documents.remove(&document) else {
+            return;
+        };
+
+        self.total_length -= u64::from(stored.length);
+        for term in stored.terms {
+            let Some(list) = self.postings.get_mut(&term) else {
+                continue;
+            };
+            if let Ok(position) = list.binary_search_by_key(&document, |(doc, _)| *doc) {
+                list.remove(position);
+            }
+            if list.is_empty() {
+                self.postings.remove(&term);
+            }
+        }
+    }
+
+    /// Scores documents against a free-text query and returns ids with BM25 scores.
+    ///
+    /// Repeated query terms count once per occurrence. Only documents
+    /// containing at least one query term are returned.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<(String, f32)>, String> {
+        if limit == 0 || self.documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query_terms: Vec<(String, u32)> = Vec::new();
+        for token in self.tokenize(query) {
+            match query_terms.iter_mut().find(|(term, _)| *term == token) {
+                Some((_, count)) => *count += 1,
+                None => query_terms.push((token, 1)),
+            }
+        }
+        // Accumulate in term order so scores do not depend on query word order.
+        query_terms.sort_unstable();
+
+        let document_count = self.documents.len() as f64;
+        let average_length = self.total_length as f64 / document_count;
+        let k1 = f64::from(self.params.k1);
+        let b = f64::from(self.params.b);
+
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        for (term, count) in &query_terms {
+            let Some(list) = self.postings.get(term) else {
+                continue;
+            };
+            let frequency = list.len() as f64;
+            let idf = (1.0 + (document_count - frequency + 0.5) / (frequency + 0.5)).ln();
+            for (document, term_frequency) in list {
+                let length = f64::from(self.documents[document].length);
+                let term_frequency = f64::from(*term_frequency);
+                let norm = if average_length > 0.0 {
+                    1.0 - b + b * length / average_length
+                } else {
+                    1.0
+                };
+                *scores.entry(*document).or_default() +=
+                    f64::from(*count) * idf * term_frequency * (k1 + 1.0)
+                        / (term_frequency + k1 * norm);
+            }
+        }
+
+        let mut hits = BinaryHeap::with_capacity(usize::min(limit, scores.len()));
+        for (document, score) in scores {
+            let id = self.documents[&document].id.clone();
+            push_top_k(&mut hits, Hit::scored(id, score as f32), limit);
+        }
+        Ok(sorted_hits(hits))
+    }
+
+    fn tokenize<'a>(&'a self, text: &'a str) -> impl Iterator<Item = String> + 'a {
+        tokens(text).filter(|token| !self.stopwords.contains(token))
+    }
+}
+
+pub struct TextResource(pub RwLock<TextIndex>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for TextResource {}
+
+/// Splits on non-alphanumeric Unicode characters and lowercases each token.
+pub fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(stopwords: &[&str]) -> TextIndex {
+        let mut index = TextIndex::new(
+            Bm25Params::default(),
+            stopwords.iter().map(|word| word.to_string()).collect(),
+        )
+        .unwrap();
+        index
+            .insert_many(vec![
+                ("a".into(), "The quick brown fox".into()),
+                ("b".into(), "the lazy dog, the lazy cat".into()),
+                ("c".into(), "Quick! Quick! A fox-like dog".into()),
+                ("d".into(), "Über café, naïve résumé".into()),
+            ])
+            .unwrap();
+        index
+    }
+
+    /// Textbook BM25 over the tokenized corpus.
+    fn oracle(corpus: &[Vec<String>], query: &[String], params: Bm25Params) -> Vec<f64> {
+        let count = corpus.len() as f64;
+        let average = corpus.iter().map(Vec::len).sum::<usize>() as f64 / count;
+        corpus
+            .iter()
+            .map(|document| {
+                query
+                    .iter()
+                    .map(|term| {
+                        let frequency = corpus.iter().filter(|d| d.contains(term)).count() as f64;
+                        let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+                        let tf = document.iter().filter(|token| *token == term).count() as f64;
+                        let (k1, b) = (f64::from(params.k1), f64::from(params.b));
+                        idf * tf * (k1 + 1.0)
+                            / (tf + k1 * (1.0 - b + b * document.len() as f64 / average))
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokenizer_splits_unicode_words_and_lowercases() {
+        assert_eq!(
+            tokens("Über café, naïve-résumé! 42x").collect::<Vec<_>>(),
+            vec!["über", "café", "naïve", "résumé", "42x"]
+        );
+        assert_eq!(tokens(" ,;- ").count(), 0);
+    }
+
+    #[test]
+    fn scores_match_textbook_bm25_and_break_ties_by_id() {
+        let params = Bm25Params { k1: 1.5, b: 0.6 };
+        let texts = [
+            "red apple red",
+            "green apple",
+            "red red red car",
+            "blue car",
+            "apple",
+        ];
+        let mut index = TextIndex::new(params, vec![]).unwrap();
+        for (position, text) in texts.iter().enumerate() {
+            index.insert(format!("doc-{position}"), text).unwrap();
+        }
+
+        let corpus: Vec<Vec<String>> = texts.iter().map(|text| tokens(text).collect()).collect();
+        let query: Vec<String> = tokens("red apple").collect();
+        let expected = oracle(&corpus, &query, params);
+
+        let hits = index.search("APPLE red", 10).unwrap();
+        assert_eq!(hits.len(), 4);
+        for (id, score) in &hits {
+            let position: usize = id[4..].parse().unwrap();
+            assert!((f64::from(*score) - expected[position]).abs() < 1.0e-5);
+        }
+        assert!(hits.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        index.insert("doc-5".into(), "blue car").unwrap();
+        assert_eq!(
+            index
+                .search("blue", 2)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec!["doc-3".to_string(), "doc-5".into()]
+        );
+    }
+
+    #[test]
+    fn stopwords_replacement_and_deletes_update_statistics() {
+        let mut index = index(&["The", "a"]);
+        assert_eq!(index.search("the", 10).unwrap(), vec![]);
+        assert_eq!(index.total_length, 3 + 4 + 5 + 4);
+
+        let hits = index.search("quick fox", 10).unwrap();
+        // "a" is shorter, so length normalization outweighs the repeated "quick" in "c".
+        assert_eq!(hits[0].0, "a");
+        assert_eq!(hits[1].0, "c");
+
+        index.insert("c".into(), "nothing relevant").unwrap();
+        index.delete("a");
+        index.delete("missing");
+        assert_eq!(index.search("quick fox", 10).unwrap(), vec![]);
+        assert!(!index.postings.contains_key("quick"));
+        assert_eq!(index.total_length, 4 + 2 + 4);
+        assert_eq!(index.search("CAFÉ", 1).unwrap()[0].0, "d");
+        assert_eq!(index.search("dog", 0).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_invalid_bm25_parameters() {
+        for (k1, b) in [
+            (-1.0, 0.5),
+            (f32::NAN, 0.5),
+            (1.2, 1.5),
+            (1.2, f32::INFINITY),
+        ] {
+            assert!(TextIndex::new(Bm25Params { k1, b }, vec![]).is_err());
+        }
+        let empty = TextIndex::new(Bm25Params { k1: 0.0, b: 0.0 }, vec![]).unwrap();
+        assert_eq!(empty.search("anything", 5).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,411 @@
+//! Document-side token reduction for multi-vector storage.
+//!
+//! Late-interaction documents often carry hundreds of highly redundant token
+//! or patch vectors. These helpers shrink a document before it is stored:
+//! near-duplicate removal, norm-based pruning, and Ward hierarchical pooling
+//! that mean-pools clusters down to `1 / pool_factor` of the tokens. Cosine
+//! documents are compared and pooled on unit-length tokens and pooled means
+//! are renormalized, matching how cosine multi-vector indexes store tokens.
+
+use crate::distances::Metric;
+
+/// Reduction steps applied in order: dedup, norm pruning, then pooling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reduction {
+    /// Drops tokens whose MaxSim similarity to an earlier kept token reaches this value.
+    pub dedup_threshold: Option<f32>,
+    /// Keeps at most this many tokens, preferring the largest L2 norms.
+    pub max_tokens: Option<usize>,
+    /// Pools clusters so roughly `len / pool_factor` tokens remain; 1 disables pooling.
+    pub pool_factor: usize,
+}
+
+impl Reduction {
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .dedup_threshold
+            .is_some_and(|threshold| !threshold.is_finite())
+        {
+            return Err("dedup threshold must be finite".to_string());
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be positive".to_string());
+        }
+        if self.pool_factor == 0 {
+            return Err("pool_factor must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Applies every configured step to one document's token vectors.
+    pub fn apply(&self, vectors: Vec<Vec<f32>>, metric: Metric) -> Result<Vec<Vec<f32>>, String> {
+        self.validate()?;
+        let mut vectors = vectors;
+        if let Some(threshold) = self.dedup_threshold {
+            vectors = deduplicate(vectors, threshold, metric)?;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            vectors = prune_by_norm(vectors, max_tokens)?;
+        }
+        pool(vectors, self.pool_factor, metric)
+    }
+}
+
+/// Keeps the first token of every group whose similarity reaches `threshold`.
+pub fn deduplicate(
+    vectors: Vec<Vec<f32>>,
+    threshold: f32,
+    metric: Metric,
+) -> Result<Vec<Vec<f32>>, String> {
+    crate::multi_vector::validate_standalone_vectors(&vectors)?;
+    let mut kept: Vec<Vec<f32>> = Vec::with_capacity(vectors.len());
+    for vector in vectors {
+        let mut duplicate = false;
+        for existing in &kept {
+            if similarity(metric, &vector, existing)? >= threshold {
+                duplicate = true;
+                break;
+            }
+        }
+        if !duplicate {
+            kept.push(vector);
+        }
+    }
+    Ok(kept)
+}
+
+/// Keeps the `max_tokens` largest-norm tokens in their original order.
+pub fn prune_by_norm(vectors: Vec<Vec<f32>>, max_tokens: usize) -> Result<Vec<Vec<f32>>, String> {
+    crate::multi_vector::validate_standalone_vectors(&vectors)?;
+    if max_tokens == 0 {
+        return Err("max_tokens must be positive".to_string());
+    }
+    if vectors.len() <= max_tokens {
+        return Ok(vectors);
+    }
+
+    let norms: Vec<f32> = vectors
+        .iter()
+        .map(|vector| crate::distances::dot(vector, vector))
+        .collect();
+    let mut order: Vec<usize> = (0..vectors.len()).collect();
+    order.sort_by(|left, right| {
+        norms[*right]
+            .total_cmp(&norms[*left])
+            .then_with(|| left.cmp(right))
+    });
+    let mut keep = vec![false; vectors.len()];
+    for index in order.into_iter().take(max_tokens) {
+        keep[index] = true;
+    }
+    Ok(vectors
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(vector, keep)| keep.then_some(vector))
+        .collect())
+}
+
+/// Ward-clusters tokens into `ceil(len / pool_factor)` groups and returns
+/// their means, ordered by each group's first token.
+pub fn pool(
+    vectors: Vec<Vec<f32>>,
+    pool_factor: usize,
+    metric: Metric,
+) -> Result<Vec<Vec<f32>>, String> {
+    crate::multi_vector::validate_standalone_vectors(&vectors)?;
+    if pool_factor == 0 {
+        return Err("pool_factor must be positive".to_string());
+    }
+    let target = vectors.len().div_ceil(pool_factor);
+    if target >= vectors.len() {
+        return Ok(vectors);
+    }
+
+    let vectors = if metric == Metric::Cosine {
+        vectors
+            .into_iter()
+            .map(crate::distances::normalize_l2)
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vectors
+    };
+
+    let clusters = ward_clusters(&vectors, target);
+    clusters
+        .into_iter()
+        .map(|members| {
+            let mut mean = vec![0.0f64; vectors[0].len()];
+            for member in &members {
+                for (sum, value) in mean.iter_mut().zip(&vectors[*member]) {
+                    *sum += f64::from(*value);
+                }
+            }
+            let mean: Vec<f32> = mean
+                .into_iter()
+                .map(|sum| (sum / members.len() as f64) as f32)
+                .collect();
+            if metric == Metric::Cosine {
+                crate::distances::normalize_l2(mean)
+            } else {
+                Ok(mean)
+            }
+        })
+        .collect()
+}
+
+/// Agglomerates with Ward linkage through Lance-Williams updates on squared
+/// distances until `target` clusters remain. Ties merge the lowest pair first.
+fn ward_clusters(vectors: &[Vec<f32>], target: usize) -> Vec<Vec<usize>> {
+    let count = vectors.len();
+    let mut distances = vec![0.0f64; count * count];
+    for left in 0..count {
+        for right in left + 1..count {
+            let distance = f64::from(crate::distances::l2_squared(
+                &vectors[left],
+                &vectors[right],
+            ));
+            distances[left * count + right] = distance;
+            distances[right * count + left] = distance;
+        }
+    }
+
+    let mut members: Vec<Vec<usize>> = (0..count).map(|index| vec![index]).collect();
+    let mut active: Vec<usize> = (0..count).collect();
+    while active.len() > target {
+        let mut best = (
+            distances[active[0] * count + active[1]],
+            active[0],
+            active[1],
+        );
+        for (position, &left) in active.iter().enumerate() {
+            for &right in &active[position + 1..] {
+                let distance = distances[left * count + right];
+                if distance < best.0 {
+                    best = (distance, left, right);
+                }
+            }
+        }
+
+        let (merged_distance, left, right) = best;
+        let (left_size, right_size) = (members[left].len() as f64, members[right].len() as f64);
+        for &other in &active {
+            if other == left || other == right {
+                continue;
+            }
+            let other_size = members[other].len() as f64;
+            let updated = ((left_size + other_size) * distances[left * count + other]
+                + (right_size + other_size) * distances[right * count + other]
+                - other_size * merged_distance)
+                / (left_size + right_size + other_size);
+            distances[left * count + other] = updated;
+            distances[other * count + left] = updated;
+        }
+        let absorbed = std::mem::take(&mut members[right]);
+        members[left].extend(absorbed);
+        active.retain(|index| *index != right);
+    }
+
+    active
+        .into_iter()
+        .map(|index| {
+            let mut cluster = std::mem::take(&mut members[index]);
+            cluster.sort_unstable();
+            cluster
+        })
+        .collect()
+}
+
+fn similarity(metric: Metric, left: &[f32], right: &[f32]) -> Result<f32, String> {
+    let raw = if metric == Metric::Cosine {
+        crate::distances::cosine(left, right)?
+    } else {
+        crate::distances::compute(metric, left, right)?
+    };
+    Ok(crate::distances::similarity_value(metric, raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muvera::hash4;
+
+    fn value(a: usize, b: usize, c: usize, d: usize) -> f32 {
+        (hash4(a as u64, b as u64, c as u64, d as u64) % 2_000) as f32 / 1_000.0 - 1.0
+    }
+
+    /// Patch-like documents: each of a few regions contributes several
+    /// slightly perturbed copies of its own vector.
+    fn document(index: usize) -> Vec<Vec<f32>> {
+        let regions = 4 + index % 4;
+        (0..regions)
+            .flat_map(|region| {
+                let topic = (index * 7 + region * 3) % 20;
+                (0..6).map(move |patch| {
+                    (0..16)
+                        .map(|dimension| {
+                            value(topic, dimension, 0, 0)
+                                + 0.3 * value(index, region, dimension, 1)
+                                + 0.05 * value(index, region * 8 + patch, dimension, 2)
+                        })
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pooling_merges_the_closest_tokens_and_keeps_means() {
+        let vectors = vec![
+            vec![0.0, 0.0],
+            vec![10.0, 0.0],
+            vec![0.0, 1.0],
+            vec![10.0, 2.0],
+        ];
+        assert_eq!(
+            pool(vectors.clone(), 2, Metric::L2),
+            Ok(vec![vec![0.0, 0.5], vec![10.0, 1.0]])
+        );
+        assert_eq!(pool(vectors.clone(), 1, Metric::L2), Ok(vectors.clone()));
+        assert_eq!(pool(vectors.clone(), 8, Metric::L2).unwrap().len(), 1);
+
+        let cosine = pool(
+            vec![vec![2.0, 0.0], vec![0.0, 3.0], vec![4.0, 0.1]],
+            2,
+            Metric::Cosine,
+        )
+        .unwrap();
+        assert_eq!(cosine.len(), 2);
+        for vector in &cosine {
+            assert!((crate::distances::dot(vector, vector) - 1.0).abs() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn pruning_keeps_the_largest_norms_in_document_order() {
+        let vectors = vec![
+            vec![1.0, 0.0],
+            vec![0.0, 3.0],
+            vec![0.1, 0.1],
+            vec![2.0, 2.0],
+        ];
+        assert_eq!(
+            prune_by_norm(vectors.clone(), 2),
+            Ok(vec![vec![0.0, 3.0], vec![2.0, 2.0]])
+        );
+        assert_eq!(prune_by_norm(vectors.clone(), 10), Ok(vectors));
+    }
+
+    #[test]
+    fn deduplication_drops_near_identical_tokens() {
+        let vectors = vec![
+            vec![1.0, 0.0],
+            vec![0.999, 0.01],
+            vec![0.0, 1.0],
+            vec![2.0, 0.0],
+        ];
+        assert_eq!(
+            deduplicate(vectors.clone(), 0.99, Metric::Cosine),
+            Ok(vec![vec![1.0, 0.0], vec![0.0, 1.0]])
+        );
+        // Under L2 the longer duplicate is far away, so it survives.
+        assert_eq!(
+            deduplicate(vectors, 0.9, Metric::L2),
+            Ok(vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![2.0, 0.0]])
+        );
+    }
+
+    #[test]
+    fn pooled_maxsim_ranking_stays_close_to_the_unpooled_baseline() {
+        let documents: Vec<(String, Vec<Vec<f32>>)> = (0..80)
+            .map(|index| (format!("doc-{index:02}"), document(index)))
+            .collect();
+        let reduction = Reduction {
+            dedup_threshold: Some(0.999),
+            max_tokens: None,
+            pool_factor: 3,
+        };
+        let pooled: Vec<(String, Vec<Vec<f32>>)> = documents
+            .iter()
+            .map(|(id, vectors)| {
+                (
+                    id.clone(),
+                    reduction.apply(vectors.clone(), Metric::Cosine).unwrap(),
+                )
+            })
+            .collect();
+        let original_tokens: usize = documents.iter().map(|(_, vectors)| vectors.len()).sum();
+        let pooled_tokens: usize = pooled.iter().map(|(_, vectors)| vectors.len()).sum();
+        assert!(pooled_tokens * 3 <= original_tokens + documents.len() * 3);
+
+        let mut overlap = 0;
+        let mut largest_score_drift = 0.0f32;
+        for seed in 0..10 {
+            let query: Vec<Vec<f32>> = (0..4)
+                .map(|token| {
+                    let topic = (seed * 11 + token * 5) % 20;
+                    (0..16)
+                        .map(|dimension| {
+                            value(topic, dimension, 0, 0) + 0.2 * value(seed, token, dimension, 3)
+                        })
+                        .collect()
+                })
+                .collect();
+            let baseline =
+                crate::multi_vector::top_k(documents.clone(), &query, Metric::Cosine, 10).unwrap();
+            let reduced =
+                crate::multi_vector::top_k(pooled.clone(), &query, Metric::Cosine, 10).unwrap();
+            overlap += reduced
+                .iter()
+                .filter(|(id, _)| baseline.iter().any(|(expected, _)| expected == id))
+                .count();
+            for (id, score) in &reduced {
+                let exact = crate::multi_vector::score(
+                    &query,
+                    &documents.iter().find(|(other, _)| other == id).unwrap().1,
+                    Metric::Cosine,
+                )
+                .unwrap();
+                largest_score_drift = largest_score_drift.max((exact - score).abs());
+            }
+        }
+        assert!(overlap >= 85, "top-10 overlap {overlap}/100");
+        assert!(
+            largest_score_drift < 0.2,
+            "score drift {largest_score_drift}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_reductions() {
+        let vectors = vec![vec![1.0, 0.0]];
+        let reduction = Reduction {
+            dedup_threshold: None,
+            max_tokens: None,
+            pool_factor: 1,
+        };
+        assert_eq!(
+            reduction.apply(vectors.clone(), Metric::L2),
+            Ok(vectors.clone())
+        );
+        assert!(Reduction {
+            pool_factor: 0,
+            ..reduction
+        }
+        .apply(vectors.clone(), Metric::L2)
+        .is_err());
+        assert!(Reduction {
+            max_tokens: Some(0),
+            ..reduction
+        }
+        .apply(vectors.clone(), Metric::L2)
+        .is_err());
+        assert!(Reduction {
+            dedup_threshold: Some(f32::NAN),
+            ..reduction
+        }
+        .apply(vectors, Metric::L2)
+        .is_err());
+        assert!(pool(vec![vec![1.0], vec![1.0, 2.0]], 2, Metric::L2).is_err());
+        assert_eq!(pool(vec![], 2, Metric::L2), Ok(vec![]));
+    }
+}